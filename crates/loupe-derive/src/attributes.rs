@@ -0,0 +1,367 @@
+//! Parse the `#[loupe(...)]` attributes.
+
+use proc_macro2::Span;
+use syn::{
+    parse_quote, spanned::Spanned, Attribute, Error, Expr, ExprPath, Ident, Lit, LitStr, Meta,
+    NestedMeta, Path, Result,
+};
+
+/// Attributes that can be set on a field, i.e. on a struct field or
+/// on an enum variant field.
+#[derive(Default)]
+pub struct FieldAttributes {
+    /// `#[loupe(skip)]`: the field is only counted by its shallow
+    /// size, which is already part of the container's size.
+    pub skip: bool,
+
+    /// `#[loupe(with = "path")]`: the field is measured by the `path`
+    /// function instead of `MemoryUsage::size_of_val`. It has the same
+    /// signature and semantics, i.e. `fn(&T, &mut dyn
+    /// MemoryUsageTracker) -> usize` returning the whole size of the
+    /// field.
+    pub with: Option<ExprPath>,
+
+    /// `#[loupe(estimate = "expr")]`: the field is measured by the
+    /// `expr` expression, of type `usize`, instead of
+    /// `MemoryUsage::size_of_val`. The expression can use `self`, or
+    /// the variant fields in an enum, and returns the size of the field
+    /// that is not already counted by its shallow size, e.g. the size
+    /// of a buffer that it owns.
+    pub estimate: Option<Expr>,
+
+    /// `#[loupe(shallow)]`: like `skip`, the field is only counted by
+    /// its shallow size, but if it's a shared pointer, like an `Arc`,
+    /// its target is registered in the tracker, so that it isn't
+    /// counted when reached later from another path.
+    pub shallow: bool,
+
+    /// `#[loupe(label = "name")]`: the field is measured within the
+    /// `name` label, see `MemoryUsageTracker::push_label`, whatever
+    /// the attribute measuring it.
+    pub label: Option<LitStr>,
+}
+
+/// Attributes that can be set on an enum variant.
+#[derive(Default)]
+pub struct VariantAttributes {
+    /// `#[loupe(skip)]`: the variant fields are only counted by their
+    /// shallow size, which is already part of the enum's size.
+    pub skip: bool,
+}
+
+/// Attributes that can be set on the struct, enum or union itself.
+#[derive(Default)]
+pub struct ContainerAttributes {
+    /// `#[loupe(crate = "path")]`: path to the `loupe` crate, when it
+    /// isn't available as `loupe`, e.g. when it's re-exported.
+    pub krate: Option<Path>,
+
+    /// `#[loupe(opaque)]`: only the shallow size of the value is
+    /// counted, fields and variants are not analysed.
+    pub opaque: bool,
+
+    /// `#[loupe(remote = "path")]`: the container is a definition
+    /// mirroring the remote type `path`, which isn't owned by the
+    /// current crate. A `size_of_val` function measuring the remote
+    /// type is generated, instead of a `MemoryUsage` impl.
+    pub remote: Option<Path>,
+
+    /// `#[loupe(transparent)]`: the container is a struct with a single
+    /// field that is not zero-sized, and it's measured as this field.
+    pub transparent: bool,
+
+    /// `#[loupe(track_self)]`: the value registers itself in the
+    /// tracker, by address and type, so that it's counted once even
+    /// when it's reached from several paths that are not all pointers.
+    pub track_self: bool,
+
+    /// `#[loupe(skip_bound(T, U, ...))]`: type parameters that don't
+    /// get an inferred `MemoryUsage` bound.
+    pub skip_bounds: Vec<Ident>,
+
+    /// `#[loupe(union_with = "self.method")]`: the union is measured by
+    /// the `method` method or by a function, with the signature
+    /// `fn(&Self, &mut dyn MemoryUsageTracker) -> usize`, returning the
+    /// size that is not already counted by the shallow size of the
+    /// union.
+    pub union_with: Option<Expr>,
+}
+
+impl FieldAttributes {
+    /// Whether no attribute is set on the field.
+    pub fn is_empty(&self) -> bool {
+        !self.skip
+            && !self.shallow
+            && self.with.is_none()
+            && self.estimate.is_none()
+            && self.label.is_none()
+    }
+
+    pub fn parse(attributes: &[Attribute]) -> Result<Self> {
+        Self::from_metas(loupe_metas(attributes)?)
+    }
+
+    /// Same as `parse`, from the items of the `#[loupe(...)]`
+    /// attributes.
+    pub fn from_metas<I>(metas: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = NestedMeta>,
+    {
+        let mut output = Self::default();
+
+        // The attribute deciding how the field is measured, if any:
+        // there can be only one.
+        let mut measured_by = None;
+
+        for meta in metas {
+            match meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    measured_by_one(&mut measured_by, "loupe(skip)", path.span())?;
+                    output.skip = true;
+                }
+
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("shallow") => {
+                    measured_by_one(&mut measured_by, "loupe(shallow)", path.span())?;
+                    output.shallow = true;
+                }
+
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("with") =>
+                {
+                    measured_by_one(
+                        &mut measured_by,
+                        "loupe(with = \"...\")",
+                        name_value.lit.span(),
+                    )?;
+                    output.with = Some(parse_lit_str(&name_value.lit, "a path to a function")?);
+                }
+
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("estimate") =>
+                {
+                    measured_by_one(
+                        &mut measured_by,
+                        "loupe(estimate = \"...\")",
+                        name_value.lit.span(),
+                    )?;
+                    output.estimate = Some(parse_lit_str(&name_value.lit, "an expression")?);
+                }
+
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("label") =>
+                {
+                    output.label = Some(match name_value.lit {
+                        Lit::Str(string) => string,
+                        lit => return Err(Error::new(lit.span(), "expected a string literal")),
+                    });
+                }
+
+                meta => return Err(unknown_attribute(&meta, "field")),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+impl VariantAttributes {
+    pub fn parse(attributes: &[Attribute]) -> Result<Self> {
+        let mut output = Self::default();
+
+        for meta in loupe_metas(attributes)? {
+            match meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    output.skip = true;
+                }
+
+                meta => return Err(unknown_attribute(&meta, "variant")),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+impl ContainerAttributes {
+    pub fn parse(attributes: &[Attribute]) -> Result<Self> {
+        let mut output = Self::default();
+
+        for meta in loupe_metas(attributes)? {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("crate") =>
+                {
+                    output.krate = Some(parse_lit_str(&name_value.lit, "a path to a crate")?);
+                }
+
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("opaque") => {
+                    output.opaque = true;
+                }
+
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transparent") => {
+                    output.transparent = true;
+                }
+
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("track_self") => {
+                    output.track_self = true;
+                }
+
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("union_with") =>
+                {
+                    output.union_with = Some(parse_lit_str(
+                        &name_value.lit,
+                        "a method or a path to a function",
+                    )?);
+                }
+
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("skip_bound") => {
+                    for nested in list.nested {
+                        match nested {
+                            NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                                output.skip_bounds.push(path.get_ident().unwrap().clone());
+                            }
+
+                            nested => {
+                                return Err(Error::new_spanned(nested, "expected a type parameter"))
+                            }
+                        }
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("remote") =>
+                {
+                    let remote: Path = parse_lit_str(&name_value.lit, "a path to a type")?;
+
+                    // The generic arguments of the remote type are the
+                    // ones of the definition.
+                    if let Some(segment) = remote
+                        .segments
+                        .iter()
+                        .find(|segment| !segment.arguments.is_empty())
+                    {
+                        return Err(Error::new_spanned(
+                            segment,
+                            "the remote type must be a path without generic arguments, \
+                             they are taken from the definition",
+                        ));
+                    }
+
+                    output.remote = Some(remote);
+                }
+
+                meta => return Err(unknown_attribute(&meta, "container")),
+            }
+        }
+
+        if output.opaque && output.transparent {
+            return Err(Error::new(
+                Span::call_site(),
+                "`loupe(opaque)` and `loupe(transparent)` can't be used together",
+            ));
+        }
+
+        Ok(output)
+    }
+
+    /// Path to the `loupe` crate in the generated code.
+    pub fn crate_path(&self) -> Path {
+        self.krate.clone().unwrap_or_else(|| parse_quote!(loupe))
+    }
+}
+
+/// Record that a field is measured according to `attribute`, or fail
+/// if another attribute already decides how it's measured.
+fn measured_by_one(
+    measured_by: &mut Option<&'static str>,
+    attribute: &'static str,
+    span: Span,
+) -> Result<()> {
+    match measured_by.replace(attribute) {
+        Some(previous) => Err(Error::new(
+            span,
+            format!("`{}` and `{}` can't be used together", previous, attribute),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Collect the items of all the `#[loupe(...)]` attributes.
+fn loupe_metas(attributes: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut metas = Vec::new();
+
+    for attribute in attributes {
+        if !attribute.path.is_ident("loupe") {
+            continue;
+        }
+
+        match attribute.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "expected an attribute of the form `#[loupe(...)]`",
+                ))
+            }
+        }
+    }
+
+    Ok(metas)
+}
+
+/// Parse the content of a string literal, like `"path::to::function"`.
+/// `expected` describes what's expected, for the error message.
+fn parse_lit_str<T>(lit: &Lit, expected: &str) -> Result<T>
+where
+    T: syn::parse::Parse,
+{
+    match lit {
+        Lit::Str(string) => string.parse().map_err(|_| {
+            Error::new(
+                string.span(),
+                format!("expected {}, found `{}`", expected, string.value()),
+            )
+        }),
+        lit => Err(Error::new(lit.span(), "expected a string literal")),
+    }
+}
+
+/// Where each attribute can be used.
+const KNOWN_ATTRIBUTES: &[(&str, &str)] = &[
+    ("crate", "containers"),
+    ("estimate", "fields"),
+    ("label", "fields"),
+    ("opaque", "containers"),
+    ("remote", "containers"),
+    ("shallow", "fields"),
+    ("skip", "fields and variants"),
+    ("skip_bound", "containers"),
+    ("track_self", "containers"),
+    ("transparent", "containers"),
+    ("union_with", "containers"),
+    ("with", "fields"),
+];
+
+fn unknown_attribute(meta: &NestedMeta, position: &str) -> Error {
+    let path = match meta {
+        NestedMeta::Meta(meta) => meta.path(),
+        NestedMeta::Lit(lit) => {
+            return Error::new(lit.span(), "expected an identifier, found a literal");
+        }
+    };
+
+    let name = quote::quote!(#path).to_string().replace(' ', "");
+
+    match KNOWN_ATTRIBUTES.iter().find(|(known, _)| *known == name) {
+        Some((_, positions)) => Error::new(
+            meta.span(),
+            format!(
+                "`loupe({})` can't be used on a {}, only on {}",
+                name, position, positions
+            ),
+        ),
+
+        None => Error::new(meta.span(), format!("unknown attribute `loupe({})`", name)),
+    }
+}
@@ -0,0 +1,138 @@
+//! Infer the `MemoryUsage` bounds of the generated impl.
+
+use crate::attributes::ContainerAttributes;
+use std::collections::BTreeSet;
+use syn::{
+    parse_quote,
+    visit::{self, Visit},
+    Error, Generics, Ident, Result, Type, TypePath,
+};
+
+/// Add a `MemoryUsage` bound to every type parameter that appears in
+/// `types`, i.e. in the types of the measured fields, like `serde`
+/// does.
+///
+/// A type parameter that only appears inside `PhantomData` doesn't get
+/// a bound, since `PhantomData<T>` implements `MemoryUsage` for any
+/// `T`. Associated types, like `T::Item` or `<T as Trait>::Item`, are
+/// bounded themselves.
+///
+/// Type parameters listed by `#[loupe(skip_bound(...))]` never get a
+/// bound.
+pub fn with_memory_usage_bounds<'a, I>(
+    generics: &Generics,
+    types: I,
+    attributes: &ContainerAttributes,
+) -> Result<Generics>
+where
+    I: IntoIterator<Item = &'a Type>,
+{
+    let krate = attributes.crate_path();
+    let mut parameters: BTreeSet<Ident> = generics
+        .type_params()
+        .map(|parameter| parameter.ident.clone())
+        .collect();
+
+    for skipped in &attributes.skip_bounds {
+        if !parameters.remove(skipped) {
+            return Err(Error::new_spanned(
+                skipped,
+                format!("`{}` is not a type parameter", skipped),
+            ));
+        }
+    }
+
+    let mut collector = TypeParameters {
+        parameters,
+        skipped: attributes.skip_bounds.iter().cloned().collect(),
+        bounded: Vec::new(),
+    };
+
+    if collector.parameters.is_empty() {
+        return Ok(generics.clone());
+    }
+
+    for ty in types {
+        collector.visit_type(ty);
+    }
+
+    let mut generics = generics.clone();
+    let where_clause = generics.make_where_clause();
+    let mut seen = BTreeSet::new();
+
+    for ty in collector.bounded {
+        // `syn::Type` isn't `Ord` without the `extra-traits` feature,
+        // let's deduplicate on the tokens.
+        if seen.insert(quote::quote!(#ty).to_string()) {
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: #krate::MemoryUsage));
+        }
+    }
+
+    Ok(generics)
+}
+
+struct TypeParameters {
+    /// Type parameters of the container that can be bounded.
+    parameters: BTreeSet<Ident>,
+
+    /// Type parameters of the container that must not be bounded.
+    skipped: BTreeSet<Ident>,
+
+    /// Types that must implement `MemoryUsage`.
+    bounded: Vec<TypePath>,
+}
+
+impl<'ast> Visit<'ast> for TypeParameters {
+    fn visit_type_path(&mut self, type_path: &'ast TypePath) {
+        if let Some(qself) = &type_path.qself {
+            // `<T as Trait>::Item`.
+            if self.is_parameter(&qself.ty) {
+                self.bounded.push(type_path.clone());
+
+                return;
+            }
+        } else {
+            if let Some(first) = type_path.path.segments.first() {
+                if self.parameters.contains(&first.ident) {
+                    // `T` or `T::Item`.
+                    self.bounded.push(type_path.clone());
+
+                    return;
+                }
+
+                if self.skipped.contains(&first.ident) {
+                    return;
+                }
+            }
+
+            if let Some(last) = type_path.path.segments.last() {
+                if last.ident == "PhantomData" {
+                    return;
+                }
+            }
+        }
+
+        visit::visit_type_path(self, type_path);
+    }
+}
+
+impl TypeParameters {
+    /// Whether `ty` is one of the type parameters.
+    fn is_parameter(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Path(type_path) => {
+                type_path.qself.is_none()
+                    && type_path
+                        .path
+                        .get_ident()
+                        .map(|ident| self.parameters.contains(ident))
+                        .unwrap_or(false)
+            }
+            Type::Paren(paren) => self.is_parameter(&paren.elem),
+            Type::Group(group) => self.is_parameter(&group.elem),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Implement `MemoryUsageBreakdown`, with
+//! `#[derive(MemoryUsageBreakdown)]`.
+
+use crate::attributes::{ContainerAttributes, FieldAttributes, VariantAttributes};
+use crate::bound::with_memory_usage_bounds;
+use crate::{cfg_attributes, field_size_of_val, is_packed, measured_type, receiver_type};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    ext::IdentExt, spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput, Error, Field,
+    Generics, Index, Path, Result,
+};
+
+pub fn derive_memory_usage_breakdown(input: &DeriveInput) -> Result<TokenStream2> {
+    let attributes = ContainerAttributes::parse(&input.attrs)?;
+
+    if let Some(remote) = &attributes.remote {
+        return Err(Error::new_spanned(
+            remote,
+            "`MemoryUsageBreakdown` can't be derived for a `loupe(remote = \"...\")` definition",
+        ));
+    }
+
+    // Containers whose fields aren't measured one by one are leaves.
+    if attributes.opaque || matches!(input.data, Data::Union(_)) {
+        return Ok(implement_leaf(input, &attributes, &input.generics));
+    }
+
+    match &input.data {
+        Data::Struct(data) => derive_for_struct(input, &attributes, data),
+        Data::Enum(data) => derive_for_enum(input, &attributes, data),
+        Data::Union(_) => unreachable!(),
+    }
+}
+
+/// The name of a field in the tree: its identifier, or its index.
+fn field_name(field: &Field, nth: usize) -> String {
+    match &field.ident {
+        Some(ident) => ident.unraw().to_string(),
+        None => nth.to_string(),
+    }
+}
+
+/// Generate the statement pushing the node of a field to `parent`,
+/// and removing its shallow size from the size of the container's own
+/// node, `node`. `accessor` is an expression of type `&T` where `T` is
+/// the field type.
+fn push_field(
+    field: &Field,
+    nth: usize,
+    accessor: TokenStream2,
+    parent: TokenStream2,
+    krate: &Path,
+) -> Result<TokenStream2> {
+    let attributes = FieldAttributes::parse(&field.attrs)?;
+    let name = field_name(field, nth);
+    let cfgs = cfg_attributes(field);
+
+    let child = if attributes.skip {
+        quote! { #krate::BreakdownNode::new(#name, std::mem::size_of_val(#accessor)) }
+    } else if measured_type(field, &attributes).is_none() {
+        let size_of_val = field_size_of_val(field, &attributes, accessor.clone(), krate);
+
+        quote! {
+            #krate::BreakdownNode::new(#name, std::mem::size_of_val(#accessor) + #size_of_val)
+        }
+    } else {
+        // The method resolution picks `BreakdownNested` if the field
+        // type implements `MemoryUsageBreakdown`, `BreakdownLeaf`
+        // otherwise.
+        quote_spanned! { field.ty.span() =>
+            (&&#krate::__private::Breakdown(#accessor))
+                .breakdown_field(std::borrow::Cow::Borrowed(#name), visited)
+        }
+    };
+
+    Ok(quote! {
+        #(#cfgs)*
+        {
+            node.self_size -= std::mem::size_of_val(#accessor);
+            #parent.children.push(#child);
+        }
+    })
+}
+
+fn derive_for_struct(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataStruct,
+) -> Result<TokenStream2> {
+    let krate = attributes.crate_path();
+    let mut measured_types = Vec::new();
+
+    for field in data.fields.iter() {
+        measured_types.extend(measured_type(field, &FieldAttributes::parse(&field.attrs)?));
+    }
+
+    let generics = with_memory_usage_bounds(&input.generics, measured_types, attributes)?;
+
+    // The fields of a packed struct can't be referenced.
+    if is_packed(&input.attrs)? {
+        return Ok(implement_leaf(input, attributes, &generics));
+    }
+
+    if attributes.transparent {
+        for (nth, field) in data.fields.iter().enumerate() {
+            if crate::is_zero_sized(&field.ty) {
+                continue;
+            }
+
+            let member = match &field.ident {
+                Some(ident) => quote! { #ident },
+                None => {
+                    let index = Index::from(nth);
+
+                    quote! { #index }
+                }
+            };
+
+            return Ok(implement_breakdown(
+                input,
+                attributes,
+                &generics,
+                quote_spanned! { field.ty.span() =>
+                    (&&#krate::__private::Breakdown(&self.#member)).breakdown_field(name, visited)
+                },
+            ));
+        }
+    }
+
+    let mut fields = Vec::new();
+
+    for (nth, field) in data.fields.iter().enumerate() {
+        let accessor = match &field.ident {
+            Some(ident) => quote! { &self.#ident },
+            None => {
+                let index = Index::from(nth);
+
+                quote! { &self.#index }
+            }
+        };
+
+        fields.push(push_field(field, nth, accessor, quote! { node }, &krate)?);
+    }
+
+    Ok(implement_breakdown(
+        input,
+        attributes,
+        &generics,
+        quote! {
+            let mut node = #krate::BreakdownNode::new(name, std::mem::size_of_val(self));
+
+            #(#fields)*
+
+            node
+        },
+    ))
+}
+
+fn derive_for_enum(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataEnum,
+) -> Result<TokenStream2> {
+    let krate = attributes.crate_path();
+    let receiver_type = receiver_type(attributes);
+
+    let mut arms = Vec::new();
+    let mut measured_types = Vec::new();
+
+    for variant in data.variants.iter() {
+        let ident = &variant.ident;
+        let name = ident.unraw().to_string();
+
+        // The fields of a skipped variant are part of the size of the
+        // enum itself.
+        if VariantAttributes::parse(&variant.attrs)?.skip {
+            arms.push(quote! {
+                #receiver_type::#ident { .. } => {
+                    node.children.push(#krate::BreakdownNode::new(#name, 0));
+                }
+            });
+
+            continue;
+        }
+
+        // All fields are bound, with the `{ 0: x0, 1: x1 }` form for
+        // tuple variants, as in the `MemoryUsage` derive.
+        let mut patterns = Vec::new();
+        let mut fields = Vec::new();
+
+        for (nth, field) in variant.fields.iter().enumerate() {
+            let field_attributes = FieldAttributes::parse(&field.attrs)?;
+            let cfgs = cfg_attributes(field);
+
+            let binding = match &field.ident {
+                Some(ident) => {
+                    patterns.push(quote! { #(#cfgs)* #ident });
+
+                    ident.clone()
+                }
+                None => {
+                    let index = Index::from(nth);
+                    let binding = format_ident!("x{}", index);
+
+                    patterns.push(quote! { #(#cfgs)* #index: #binding });
+
+                    binding
+                }
+            };
+
+            fields.push(push_field(
+                field,
+                nth,
+                quote! { #binding },
+                quote! { variant },
+                &krate,
+            )?);
+            measured_types.extend(measured_type(field, &field_attributes));
+        }
+
+        arms.push(quote! {
+            #receiver_type::#ident { #(#patterns),* } => {
+                let mut variant = #krate::BreakdownNode::new(#name, 0);
+
+                #(#fields)*
+
+                node.children.push(variant);
+            }
+        });
+    }
+
+    let generics = with_memory_usage_bounds(&input.generics, measured_types, attributes)?;
+
+    // An enum without variants has no values, but `&Self` does.
+    if data.variants.is_empty() {
+        return Ok(implement_breakdown(
+            input,
+            attributes,
+            &generics,
+            quote! { match *self {} },
+        ));
+    }
+
+    Ok(implement_breakdown(
+        input,
+        attributes,
+        &generics,
+        quote! {
+            let mut node = #krate::BreakdownNode::new(name, std::mem::size_of_val(self));
+
+            match self {
+                #(#arms)*
+            }
+
+            node
+        },
+    ))
+}
+
+/// Generate the implementation returning a single node, with the size
+/// given by `MemoryUsage`.
+fn implement_leaf(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    generics: &Generics,
+) -> TokenStream2 {
+    let krate = attributes.crate_path();
+
+    // `MemoryUsage` already handles `loupe(track_self)`.
+    let attributes = ContainerAttributes {
+        track_self: false,
+        krate: Some(krate.clone()),
+        ..ContainerAttributes::default()
+    };
+
+    implement_breakdown(
+        input,
+        &attributes,
+        generics,
+        quote! {
+            #krate::BreakdownNode::new(name, #krate::MemoryUsage::size_of_val(self, visited))
+        },
+    )
+}
+
+/// Generate the implementation, where `body` computes the root node of
+/// the tree, named `name`.
+fn implement_breakdown(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    generics: &Generics,
+    body: TokenStream2,
+) -> TokenStream2 {
+    let name = &input.ident;
+    let krate = attributes.crate_path();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // A value already visited is only counted by its shallow size.
+    let track_self = if attributes.track_self {
+        quote! {
+            if !#krate::__private::track_self(visited, self) {
+                return #krate::BreakdownNode::new(name, std::mem::size_of_val(self));
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        // Fields that are references have their shallow size computed
+        // with `size_of_val(&&T)`, which is what we want here.
+        #[allow(dead_code, unused_mut, unused_variables, clippy::size_of_ref)]
+        impl #impl_generics #krate::MemoryUsageBreakdown for #name #ty_generics
+        #where_clause
+        {
+            fn breakdown(
+                &self,
+                name: std::borrow::Cow<'static, str>,
+                visited: &mut dyn #krate::MemoryUsageTracker,
+            ) -> #krate::BreakdownNode {
+                #[allow(unused_imports)]
+                use #krate::__private::{BreakdownLeaf as _, BreakdownNested as _};
+
+                #track_self
+
+                #body
+            }
+        }
+    }
+}
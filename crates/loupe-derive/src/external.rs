@@ -0,0 +1,105 @@
+//! Implement `MemoryUsage` for a type from its list of fields, with
+//! the `impl_memory_usage!` macro.
+
+use crate::attributes::FieldAttributes;
+use crate::{field_size_of_val, join_fold};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Field, Member, NestedMeta, Path, Result, Token, Type, TypeInfer, Visibility,
+};
+
+/// `path::to::Type { field, field: option, ... }`.
+pub struct ExternalType {
+    path: Path,
+    fields: Punctuated<ExternalField, Token![,]>,
+}
+
+/// `field` or `field: option`, where `option` is one of the options
+/// of the `#[loupe(...)]` field attribute.
+struct ExternalField {
+    member: Member,
+    option: Option<NestedMeta>,
+}
+
+impl Parse for ExternalType {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path = input.parse()?;
+        let content;
+        braced!(content in input);
+
+        Ok(Self {
+            path,
+            fields: content.parse_terminated(ExternalField::parse)?,
+        })
+    }
+}
+
+impl Parse for ExternalField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let member = input.parse()?;
+        let option = if input.parse::<Option<Token![:]>>()?.is_some() {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { member, option })
+    }
+}
+
+pub fn impl_memory_usage(input: ExternalType) -> Result<TokenStream2> {
+    let krate: Path = parse_quote!(loupe);
+    let path = &input.path;
+    let mut sizes = Vec::new();
+
+    for field in input.fields.iter() {
+        let attributes = FieldAttributes::from_metas(field.option.clone())?;
+
+        if attributes.skip {
+            continue;
+        }
+
+        let member = &field.member;
+
+        // The type of the field is unknown, let it be inferred.
+        let ident = match member {
+            Member::Named(ident) => Some(ident.clone()),
+            Member::Unnamed(_) => None,
+        };
+        let inferred = Field {
+            attrs: Vec::new(),
+            vis: Visibility::Inherited,
+            ident,
+            colon_token: None,
+            ty: Type::Infer(TypeInfer {
+                underscore_token: Token![_](member.span()),
+            }),
+        };
+
+        sizes.push(field_size_of_val(
+            &inferred,
+            &attributes,
+            quote! { &self.#member },
+            &krate,
+        ));
+    }
+
+    let sum = join_fold(sizes.into_iter(), |x, y| quote! { #x + #y }, quote! { 0 });
+
+    Ok(quote! {
+        // Fields that are references have their shallow size computed
+        // with `size_of_val(&&T)`, which is what we want here.
+        #[allow(unused_variables, clippy::size_of_ref)]
+        impl #krate::MemoryUsage for #path {
+            fn size_of_val(&self, visited: &mut dyn #krate::MemoryUsageTracker) -> usize {
+                std::mem::size_of_val(self) + #sum
+            }
+        }
+    })
+}
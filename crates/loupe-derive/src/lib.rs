@@ -1,18 +1,149 @@
 //! Companion of the [`loupe`](../loupe-derive/index.html) crate.
 
+mod attributes;
+mod bound;
+#[cfg(feature = "breakdown")]
+mod breakdown;
+mod external;
+
+use attributes::{ContainerAttributes, FieldAttributes, VariantAttributes};
+use bound::with_memory_usage_bounds;
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse, Attribute, Data, DataEnum, DataStruct, DeriveInput, Fields, Generics, Ident, Index,
+    parse_macro_input, spanned::Spanned, Attribute, Data, DataEnum, DataStruct, DataUnion,
+    DeriveInput, Error, Expr, ExprLit, Field, Fields, Generics, Index, Lit, Meta, NestedMeta, Path,
+    Result, Type,
 };
 
 /// Procedural macro to implement the `loupe::MemoryUsage` trait
-/// automatically for structs and enums.
+/// automatically for structs, enums, and unions.
 ///
 /// All struct fields and enum variants must implement `MemoryUsage`
 /// trait. If it's not possible, the `#[loupe(skip)]` attribute can be
 /// used on a field or a variant to instruct the derive procedural
-/// macro to skip that item.
+/// macro to skip that item. A skipped item is still counted by its
+/// shallow size, as part of the size of the struct or the enum.
+///
+/// A field holding a trait object, like `Box<dyn Backend>`, is measured
+/// through the vtable when `MemoryUsage` is a supertrait of the trait,
+/// i.e. `trait Backend: MemoryUsage`, since `dyn Backend` then
+/// implements `MemoryUsage` too.
+///
+/// The `#[loupe(shallow)]` attribute on a field is like
+/// `#[loupe(skip)]`, except that if the field is a shared pointer,
+/// i.e. an `Arc`, an `Rc` or a reference, its target is registered as
+/// visited, so that it's not counted when reached from another path
+/// later. It's useful for back-references, like an `Arc` to a parent
+/// node, that would otherwise charge the whole parent to its child.
+/// It has no effect in a `#[repr(packed)]` struct.
+///
+/// A field can also be measured by a custom function with the
+/// `#[loupe(with = "path::to::function")]` attribute. The function
+/// has the same signature and semantics as `MemoryUsage::size_of_val`,
+/// i.e. `fn(&T, &mut dyn MemoryUsageTracker) -> usize` where `T` is
+/// the field type.
+///
+/// When the size of a field can't be derived from its type, e.g. when
+/// it's a handle to a buffer whose length is stored in another field,
+/// it can be given by the `#[loupe(estimate = "expression")]`
+/// attribute. The expression has access to `self`, or to the variant
+/// fields in an enum, and is the size of the field that is not already
+/// part of the size of the container, like the size of the buffer:
+///
+/// ```rust,ignore
+/// #[derive(MemoryUsage)]
+/// struct GpuBuffer {
+///     #[loupe(estimate = "self.byte_len")]
+///     handle: u64,
+///     byte_len: usize,
+/// }
+/// ```
+///
+/// The size of a field can be attributed to a logical bucket, like
+/// "code" or "metadata", with the `#[loupe(label = "name")]`
+/// attribute: the field is measured between the
+/// `MemoryUsageTracker::push_label` and `pop_label` hooks, which
+/// `loupe::LabelTracker` uses to sum the sizes per label.
+///
+/// Unions are supported when all their members are primitive types,
+/// raw pointers, or arrays or tuples of them, since the active member
+/// is unknown: their size is their shallow size.
+///
+/// Other unions can be measured with the
+/// `#[loupe(union_with = "self.method")]` attribute, where the method,
+/// or a function given by its path, has the signature
+/// `fn(&Self, &mut dyn MemoryUsageTracker) -> usize`. It returns the
+/// size of the active member that is not already counted by the
+/// shallow size of the union, usually by reading a tag:
+///
+/// ```rust,ignore
+/// #[derive(MemoryUsage)]
+/// #[loupe(union_with = "self.size_of_active")]
+/// union Event {
+///     kind: u32,
+///     text: TextEvent,
+/// }
+/// ```
+///
+/// The `#[loupe(opaque)]` attribute on the container makes the derive
+/// count the shallow size of the value only, without looking at its
+/// fields or variants, which don't need to implement `MemoryUsage`.
+///
+/// A struct wrapping a single value, like `struct Bytes(Vec<u8>)`, can
+/// be measured as this value with the `#[loupe(transparent)]`
+/// attribute on the container. Its other fields, if any, must be
+/// zero-sized, like `PhantomData`.
+///
+/// A value that is shared, while not always being reached through a
+/// pointer, like a value owned by a struct and borrowed by others, is
+/// counted once per path leading to it. With the
+/// `#[loupe(track_self)]` attribute on the container, the value
+/// registers itself in the tracker, and is only counted by its shallow
+/// size when it has already been visited: what it owns is counted
+/// once.
+///
+/// When `loupe` isn't a direct dependency, e.g. when it's re-exported
+/// by another crate, its path can be set with the
+/// `#[loupe(crate = "path::to::loupe")]` attribute on the container.
+///
+/// A type from another crate can be measured by deriving on a local
+/// definition mirroring it, with the same fields, and the
+/// `#[loupe(remote = "path::to::Type")]` attribute, like `serde`
+/// does. Instead of implementing `MemoryUsage` for the definition,
+/// the derive generates a `size_of_val` function on it, with the
+/// signature expected by `#[loupe(with = "...")]`. If the remote type
+/// is a `#[non_exhaustive]` enum, the definition must be
+/// `#[non_exhaustive]` too, so that unknown variants are counted by
+/// their shallow size:
+///
+/// ```rust,ignore
+/// #[derive(MemoryUsage)]
+/// #[loupe(remote = "third_party::Config")]
+/// struct ConfigDef {
+///     name: String,
+///     values: Vec<f64>,
+/// }
+///
+/// #[derive(MemoryUsage)]
+/// struct Settings {
+///     #[loupe(with = "ConfigDef::size_of_val")]
+///     config: third_party::Config,
+/// }
+/// ```
+///
+/// Fields with `#[cfg(...)]` attributes are only measured when they
+/// are compiled. In tuple structs and variants, only the last fields
+/// can have such attributes, since the indices of the next fields
+/// would change.
+///
+/// Type parameters used by the measured fields get a `MemoryUsage`
+/// bound in the generated impl, except when they only appear inside
+/// `PhantomData`, or when they are listed by the
+/// `#[loupe(skip_bound(T, U, ...))]` attribute on the container, e.g.
+/// when a field type implements `MemoryUsage` for any `T`, like
+/// `Weak<T>`.
 ///
 /// # Example
 ///
@@ -31,32 +162,125 @@ use syn::{
 ///
 ///     #[loupe(skip)]
 ///     other: Mystery,
+///
+///     #[loupe(with = "size_of_reserved")]
+///     buffer: Vec<u8>,
+/// }
+///
+/// fn size_of_reserved(buffer: &Vec<u8>, _: &mut dyn MemoryUsageTracker) -> usize {
+///     std::mem::size_of_val(buffer) + buffer.capacity()
 /// }
 /// ```
 #[proc_macro_derive(MemoryUsage, attributes(loupe))]
 pub fn derive_memory_usage(input: TokenStream) -> TokenStream {
-    let derive_input: DeriveInput = parse(input).unwrap();
+    let derive_input = parse_macro_input!(input as DeriveInput);
+
+    let output = ContainerAttributes::parse(&derive_input.attrs).and_then(|attributes| {
+        if attributes.opaque {
+            return Ok(derive_opaque_memory_usage(&derive_input, &attributes));
+        }
+
+        if attributes.transparent {
+            return match derive_input.data {
+                Data::Struct(ref struct_data) => {
+                    derive_transparent_memory_usage(&derive_input, &attributes, struct_data)
+                }
 
-    match derive_input.data {
-        Data::Struct(ref struct_data) => {
-            derive_memory_usage_for_struct(&derive_input.ident, struct_data, &derive_input.generics)
+                _ => Err(Error::new_spanned(
+                    &derive_input.ident,
+                    "`loupe(transparent)` can only be used on structs",
+                )),
+            };
         }
 
-        Data::Enum(ref enum_data) => {
-            derive_memory_usage_for_enum(&derive_input.ident, enum_data, &derive_input.generics)
+        if let Some(union_with) = &attributes.union_with {
+            if !matches!(derive_input.data, Data::Union(_)) {
+                return Err(Error::new_spanned(
+                    union_with,
+                    "`loupe(union_with = \"...\")` can only be used on unions",
+                ));
+            }
         }
 
-        Data::Union(_) => panic!("unions are not yet implemented"),
-        /*
-        // TODO: unions.
-        // We have no way of knowing which union member is active, so we should
-        // refuse to derive an impl except for unions where all members are
-        // primitive types or arrays of them.
-        Data::Union(ref union_data) => {
-            derive_memory_usage_union(union_data)
-        },
-        */
-    }
+        match derive_input.data {
+            Data::Struct(ref struct_data) => {
+                derive_memory_usage_for_struct(&derive_input, &attributes, struct_data)
+            }
+
+            Data::Enum(ref enum_data) => {
+                derive_memory_usage_for_enum(&derive_input, &attributes, enum_data)
+            }
+
+            Data::Union(ref union_data) => {
+                derive_memory_usage_for_union(&derive_input, &attributes, union_data)
+            }
+        }
+    });
+
+    output
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Procedural macro to implement the `loupe::MemoryUsageBreakdown`
+/// trait automatically for structs, enums, and unions, in addition to
+/// `#[derive(MemoryUsage)]`. It requires the `breakdown` feature.
+///
+/// Each field is a child of the tree, named after the field, and the
+/// active variant of an enum is a child whose children are its fields.
+/// A field whose type implements `MemoryUsageBreakdown` is broken down
+/// further, other fields are leaves. The `#[loupe(...)]` attributes are
+/// the ones of `#[derive(MemoryUsage)]`, and have the same effect on
+/// the sizes: skipped fields and variants are leaves too, counted by
+/// their shallow size.
+///
+/// Unions, `#[loupe(opaque)]` and `#[repr(packed)]` containers are
+/// leaves, and `#[loupe(remote = "...")]` definitions are not
+/// supported.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(MemoryUsage, MemoryUsageBreakdown)]
+/// struct Session {
+///     user: String,
+///     history: Vec<u64>,
+/// }
+///
+/// let tree = loupe::breakdown(&session);
+/// ```
+#[cfg(feature = "breakdown")]
+#[proc_macro_derive(MemoryUsageBreakdown, attributes(loupe))]
+pub fn derive_memory_usage_breakdown(input: TokenStream) -> TokenStream {
+    let derive_input = parse_macro_input!(input as DeriveInput);
+
+    breakdown::derive_memory_usage_breakdown(&derive_input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Procedural macro to implement the `loupe::MemoryUsage` trait for a
+/// type whose fields are accessible, by listing the fields to measure,
+/// e.g. for a type that can't derive `MemoryUsage`.
+///
+/// A field can be followed by one of the options of the
+/// `#[loupe(...)]` field attribute of the derive, like
+/// `with = "path::to::function"`. Fields that are not listed are only
+/// counted by their shallow size, like skipped fields.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl_memory_usage!(geometry::Point { x, y });
+/// impl_memory_usage!(io::Buffer { data: with = "buffer_bytes", len });
+/// ```
+#[proc_macro]
+pub fn impl_memory_usage(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as external::ExternalType);
+
+    external::impl_memory_usage(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
 
 // TODO: use Iterator::fold_first once it's stable. https://github.com/rust-lang/rust/pull/79805
@@ -72,243 +296,807 @@ where
     }
 }
 
-fn derive_memory_usage_for_struct(
-    struct_name: &Ident,
-    data: &DataStruct,
-    generics: &Generics,
-) -> TokenStream {
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+/// Generate the expression computing the size of a field that is not
+/// already counted by the container, i.e. its size minus its shallow
+/// size. `accessor` is an expression of type `&T` where `T` is the
+/// field type.
+fn field_size_of_val(
+    field: &Field,
+    attributes: &FieldAttributes,
+    accessor: TokenStream2,
+    krate: &Path,
+) -> TokenStream2 {
+    let size_of_val = unlabeled_field_size_of_val(field, attributes, accessor, krate);
 
-    let sum = join_fold(
-        // Check all fields of the `struct`.
-        match &data.fields {
-            // Field has the form:
-            //
-            //     F { x, y }
-            Fields::Named(ref fields) => fields
-                .named
-                .iter()
-                .filter_map(|field| {
-                    if must_skip(&field.attrs) {
-                        return None;
-                    }
+    match &attributes.label {
+        Some(label) => quote! {
+            ({
+                visited.push_label(#label);
+                let size: usize = #size_of_val;
+                visited.pop_label(size);
 
-                    let ident = field.ident.as_ref().unwrap();
-                    let span = ident.span();
+                size
+            })
+        },
+        None => size_of_val,
+    }
+}
 
-                    Some(quote_spanned!(
-                        span => loupe::MemoryUsage::size_of_val(&self.#ident, visited) - std::mem::size_of_val(&self.#ident)
-                    ))
-                })
-                .collect(),
+/// Same as `field_size_of_val`, without the `loupe(label = "...")`
+/// attribute.
+fn unlabeled_field_size_of_val(
+    field: &Field,
+    attributes: &FieldAttributes,
+    accessor: TokenStream2,
+    krate: &Path,
+) -> TokenStream2 {
+    // Errors about the field, like its type not implementing
+    // `MemoryUsage`, point at its type.
+    let span = field.ty.span();
 
-            // Field has the form:
-            //
-            //     F
-            Fields::Unit => vec![],
+    // The method resolution picks `TrackShallow` if the field is a
+    // shared pointer, `IgnoreShallow` otherwise.
+    if attributes.shallow {
+        return quote_spanned! { span =>
+            ({
+                #[allow(unused_imports)]
+                use #krate::__private::{IgnoreShallow as _, TrackShallow as _};
 
-            // Field has the form:
-            //
-            //     F(x, y)
-            Fields::Unnamed(ref fields) => fields
-                .unnamed
-                .iter()
-                .enumerate()
-                .filter_map(|(nth, field)| {
-                    if must_skip(&field.attrs) {
-                        return None;
-                    }
+                (&&#krate::__private::Shallow(#accessor)).track_shallow(visited);
 
-                    let ident = Index::from(nth);
+                0
+            })
+        };
+    }
 
-                    Some(quote! { loupe::MemoryUsage::size_of_val(&self.#ident, visited) - std::mem::size_of_val(&self.#ident) })
+    if let Some(estimate) = &attributes.estimate {
+        return quote! {
+            ({
+                let estimate: usize = #estimate;
+
+                estimate
+            })
+        };
+    }
+
+    let size_of_val = match &attributes.with {
+        // Coerce the function to a function pointer, so that a wrong
+        // signature is reported as such.
+        Some(path) => {
+            let ty = &field.ty;
+
+            quote! {
+                ({
+                    let size_of_val: fn(&#ty, &mut dyn #krate::MemoryUsageTracker) -> usize = #path;
+
+                    size_of_val
                 })
-                .collect(),
+            }
         }
-        .into_iter(),
-        |x, y| quote! { #x + #y },
-        quote! { 0 },
-    );
 
-    // Implement the `MemoryUsage` trait for `struct_name`.
-    (quote! {
-        #[allow(dead_code)]
-        impl #impl_generics loupe::MemoryUsage for #struct_name #ty_generics
-        #where_clause
+        None => {
+            let ty = &field.ty;
+
+            quote_spanned! { span => <#ty as #krate::MemoryUsage>::size_of_val }
+        }
+    };
+
+    // An implementation could return less than the shallow size, like
+    // `loupe::Ignored`, don't underflow.
+    quote_spanned! { span =>
+        #size_of_val(#accessor, visited).saturating_sub(std::mem::size_of_val(#accessor))
+    }
+}
+
+/// The `#[cfg(...)]` attributes of a field.
+fn cfg_attributes(field: &Field) -> Vec<&Attribute> {
+    field
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path.is_ident("cfg"))
+        .collect()
+}
+
+/// Compile the size of a field, as computed by `size`, only when the
+/// field itself is compiled, according to its `#[cfg(...)]`
+/// attributes.
+fn cfg_gated(field: &Field, size: TokenStream2) -> TokenStream2 {
+    let cfgs = cfg_attributes(field);
+
+    if cfgs.is_empty() {
+        return size;
+    }
+
+    quote! {
+        ({
+            let mut size = 0;
+
+            #(#cfgs)*
+            {
+                size = #size;
+            }
+
+            size
+        })
+    }
+}
+
+/// Same as `field_size_of_val`, for a field of a `#[repr(packed)]`
+/// struct. Such a field may be unaligned, so no reference to it can be
+/// created: it's copied out first, which requires it to be `Copy`.
+/// `place` is the field expression, like `self.x`.
+fn packed_field_size_of_val(
+    field: &Field,
+    attributes: &FieldAttributes,
+    place: TokenStream2,
+    krate: &Path,
+) -> TokenStream2 {
+    let ty = &field.ty;
+    let size_of_val = field_size_of_val(field, attributes, quote! { &value }, krate);
+
+    // An estimate doesn't read the field. A shallow field can't be
+    // copied out if it isn't `Copy`, so it isn't registered.
+    if attributes.estimate.is_some() {
+        return size_of_val;
+    }
+
+    if attributes.shallow {
+        return quote! { 0 };
+    }
+
+    quote! {
         {
-            fn size_of_val(&self, visited: &mut loupe::MemoryUsageTracker) -> usize {
-                std::mem::size_of_val(self) + #sum
+            fn packed_field_must_be_copy<T: Copy>() {}
+            packed_field_must_be_copy::<#ty>();
+
+            let value = #place;
+
+            #size_of_val
+        }
+    }
+}
+
+/// Whether the container has a `#[repr(packed)]` or
+/// `#[repr(packed(N))]` attribute.
+fn is_packed(attributes: &[Attribute]) -> Result<bool> {
+    for attribute in attributes {
+        if !attribute.path.is_ident("repr") {
+            continue;
+        }
+
+        if let Meta::List(list) = attribute.parse_meta()? {
+            let packed = list.nested.iter().any(|meta| match meta {
+                NestedMeta::Meta(meta) => meta.path().is_ident("packed"),
+                NestedMeta::Lit(_) => false,
+            });
+
+            if packed {
+                return Ok(true);
             }
         }
-    })
-    .into()
+    }
+
+    Ok(false)
 }
 
-fn derive_memory_usage_for_enum(
-    enum_name: &Ident,
-    data: &DataEnum,
+/// Whether the container has a `#[non_exhaustive]` attribute.
+fn is_non_exhaustive(attributes: &[Attribute]) -> bool {
+    attributes
+        .iter()
+        .any(|attribute| attribute.path.is_ident("non_exhaustive"))
+}
+
+/// Whether a union member can be measured without knowing whether it's
+/// the active one, i.e. whether it owns no heap: primitive types, raw
+/// pointers, and arrays or tuples of them.
+fn is_plain_old_data(ty: &Type) -> bool {
+    const PRIMITIVES: &[&str] = &[
+        "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16",
+        "u32", "u64", "u128", "usize",
+    ];
+
+    match ty {
+        Type::Path(type_path) => {
+            type_path.qself.is_none()
+                && PRIMITIVES
+                    .iter()
+                    .any(|primitive| type_path.path.is_ident(primitive))
+        }
+        Type::Array(array) => is_plain_old_data(&array.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().all(is_plain_old_data),
+        Type::Paren(paren) => is_plain_old_data(&paren.elem),
+        Type::Group(group) => is_plain_old_data(&group.elem),
+        Type::Ptr(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether a type is known to be zero-sized: `PhantomData`,
+/// `PhantomPinned`, `()`, or an array of length 0.
+fn is_zero_sized(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "PhantomData" || segment.ident == "PhantomPinned")
+            .unwrap_or(false),
+        Type::Tuple(tuple) => tuple.elems.is_empty(),
+        Type::Array(array) => matches!(
+            &array.len,
+            Expr::Lit(ExprLit { lit: Lit::Int(length), .. }) if length.base10_digits() == "0"
+        ),
+        Type::Paren(paren) => is_zero_sized(&paren.elem),
+        Type::Group(group) => is_zero_sized(&group.elem),
+        _ => false,
+    }
+}
+
+/// The type of a field that is measured with `MemoryUsage`, if any.
+fn measured_type<'a>(field: &'a Field, attributes: &FieldAttributes) -> Option<&'a Type> {
+    if attributes.skip
+        || attributes.shallow
+        || attributes.with.is_some()
+        || attributes.estimate.is_some()
+    {
+        None
+    } else {
+        Some(&field.ty)
+    }
+}
+
+/// Implement `MemoryUsage` as the shallow size of the value, whatever
+/// its fields or variants are.
+fn derive_opaque_memory_usage(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+) -> TokenStream2 {
+    implement_memory_usage(input, attributes, &input.generics, quote! { 0 })
+}
+
+/// Implement `MemoryUsage` by forwarding to the single field that is
+/// not zero-sized: the size of the struct is the size of this field.
+fn derive_transparent_memory_usage(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataStruct,
+) -> Result<TokenStream2> {
+    if is_packed(&input.attrs)? {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`loupe(transparent)` can't be used on `#[repr(packed)]` structs",
+        ));
+    }
+
+    let mut forwarded = None;
+
+    for (nth, field) in data.fields.iter().enumerate() {
+        let field_attributes = FieldAttributes::parse(&field.attrs)?;
+
+        if is_zero_sized(&field.ty) {
+            continue;
+        }
+
+        if forwarded.is_some() {
+            return Err(Error::new_spanned(
+                field,
+                "`loupe(transparent)` requires a single field that is not zero-sized, \
+                 other fields must be `PhantomData`, `PhantomPinned`, `()` or empty arrays",
+            ));
+        }
+
+        if !field_attributes.is_empty() {
+            return Err(Error::new_spanned(
+                field,
+                "the field of a `loupe(transparent)` struct can't have `loupe(...)` attributes",
+            ));
+        }
+
+        let member = match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = Index::from(nth);
+
+                quote! { #index }
+            }
+        };
+
+        forwarded = Some((field, member));
+    }
+
+    let (field, member) = forwarded.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "`loupe(transparent)` requires a field that is not zero-sized",
+        )
+    })?;
+
+    let krate = attributes.crate_path();
+    let receiver = receiver(attributes);
+    let generics = with_memory_usage_bounds(&input.generics, Some(&field.ty), attributes)?;
+
+    Ok(implement_size_of_val(
+        input,
+        attributes,
+        &generics,
+        quote! { #krate::MemoryUsage::size_of_val(&#receiver.#member, visited) },
+    ))
+}
+
+/// Generate the implementation measuring the container, where `sum`
+/// computes the size of its fields that is not already counted by its
+/// shallow size.
+fn implement_memory_usage(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
     generics: &Generics,
-) -> TokenStream {
+    sum: TokenStream2,
+) -> TokenStream2 {
+    let receiver = receiver(attributes);
+
+    implement_size_of_val(
+        input,
+        attributes,
+        generics,
+        quote! { std::mem::size_of_val(#receiver) + #sum },
+    )
+}
+
+/// Generate the implementation measuring the container, where `size`
+/// computes its whole size.
+///
+/// With `#[loupe(remote = "...")]`, the container mirrors a remote
+/// type: a `size_of_val` function measuring the remote type is
+/// generated on the container, instead of a `MemoryUsage` impl, to be
+/// used with `#[loupe(with = "...")]`.
+fn implement_size_of_val(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    generics: &Generics,
+    size: TokenStream2,
+) -> TokenStream2 {
+    let name = &input.ident;
+    let krate = attributes.crate_path();
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let match_arms = join_fold(
-        data.variants
-            .iter()
-            .map(|variant| {
-                let ident = &variant.ident;
-                let span = ident.span();
-
-                // Check all the variants of the `enum`.
-                //
-                // We want to generate something like this:
-                //
-                //     Self::Variant ... => { ... }
-                //           ^^^^^^^ ^^^      ^^^
-                //           |       |        |
-                //           |       |        given by the `sum` variable
-                //           |       given by the `pattern` variable
-                //           given by the `ident` variable
-                //
-                // Let's compute the `pattern` and `sum` parts.
-                let (pattern, mut sum) = match variant.fields {
-                    // Variant has the form:
-                    //
-                    //     V { x, y }
-                    //
-                    // We want to generate:
-                    //
-                    //     Self::V { x, y } => { /* memory usage of x + y */ }
-                    Fields::Named(ref fields) => {
-                        // Collect the identifiers.
-                        let identifiers = fields.named.iter().map(|field| {
-                            let ident = field.ident.as_ref().unwrap();
-                            let span = ident.span();
-
-                            quote_spanned!(span => #ident)
-                        });
-
-                        // Generate the `pattern` part.
-                        let pattern = {
-                            let pattern = join_fold(
-                                identifiers.clone(),
-                                |x, y| quote! { #x , #y },
-                                quote! {}
-                            );
-
-                            quote! { { #pattern } }
-                        };
-
-                        // Generate the `sum` part.
-                        let sum = {
-                            let sum = join_fold(
-                                identifiers.map(|ident| quote! {
-                                    loupe::MemoryUsage::size_of_val(#ident, visited) - std::mem::size_of_val(#ident)
-                                }),
-                                |x, y| quote! { #x + #y },
-                                quote! { 0 },
-                            );
-
-                            quote! { #sum }
-                        };
-
-                        (pattern, sum)
+    // A value already visited is only counted by its shallow size,
+    // which is already part of the size of its container.
+    let size = if attributes.track_self {
+        let receiver = receiver(attributes);
+
+        quote! {
+            if #krate::__private::track_self(visited, #receiver) {
+                #size
+            } else {
+                std::mem::size_of_val(#receiver)
+            }
+        }
+    } else {
+        size
+    };
+
+    match &attributes.remote {
+        Some(remote) => {
+            let visibility = &input.vis;
+
+            quote! {
+                // Fields that are references have their shallow size
+                // computed with `size_of_val(&&T)`, which is what we
+                // want here.
+                #[allow(dead_code, unused_variables, clippy::size_of_ref)]
+                impl #impl_generics #name #ty_generics
+                #where_clause
+                {
+                    #visibility fn size_of_val(
+                        value: &#remote #ty_generics,
+                        visited: &mut dyn #krate::MemoryUsageTracker,
+                    ) -> usize {
+                        #size
                     }
+                }
+            }
+        }
+
+        None => quote! {
+            // Fields that are references have their shallow size computed
+            // with `size_of_val(&&T)`, which is what we want here.
+            #[allow(dead_code, unused_variables, clippy::size_of_ref)]
+            impl #impl_generics #krate::MemoryUsage for #name #ty_generics
+            #where_clause
+            {
+                fn size_of_val(&self, visited: &mut dyn #krate::MemoryUsageTracker) -> usize {
+                    #size
+                }
+            }
+        },
+    }
+}
+
+/// The measured value in the generated code: `self`, or the `value`
+/// argument of the generated function for a remote type.
+fn receiver(attributes: &ContainerAttributes) -> TokenStream2 {
+    match attributes.remote {
+        Some(_) => quote! { value },
+        None => quote! { self },
+    }
+}
+
+/// The measured type in the generated code: `Self`, or the remote
+/// type.
+fn receiver_type(attributes: &ContainerAttributes) -> TokenStream2 {
+    match &attributes.remote {
+        Some(remote) => quote! { #remote },
+        None => quote! { Self },
+    }
+}
+
+fn derive_memory_usage_for_struct(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataStruct,
+) -> Result<TokenStream2> {
+    let krate = attributes.crate_path();
+    let receiver = receiver(attributes);
+    let packed = is_packed(&input.attrs)?;
 
-                    // Variant has the form:
-                    //
-                    //     V
-                    //
-                    // We want to generate:
-                    //
-                    //     Self::V => { 0 }
-                    Fields::Unit => {
-                        let pattern = quote! {};
-                        let sum = quote! { 0 };
-
-                        (pattern, sum)
+    let mut fields = Vec::new();
+    let mut measured_types = Vec::new();
+
+    // Check all fields of the `struct`.
+    match &data.fields {
+        // Field has the form:
+        //
+        //     F { x, y }
+        Fields::Named(ref named_fields) => {
+            for field in named_fields.named.iter() {
+                let attributes = FieldAttributes::parse(&field.attrs)?;
+
+                if attributes.skip {
+                    continue;
+                }
+
+                let ident = field.ident.as_ref().unwrap();
+
+                fields.push(cfg_gated(
+                    field,
+                    if packed {
+                        packed_field_size_of_val(
+                            field,
+                            &attributes,
+                            quote! { #receiver.#ident },
+                            &krate,
+                        )
+                    } else {
+                        field_size_of_val(field, &attributes, quote! { &#receiver.#ident }, &krate)
                     },
+                ));
+                measured_types.extend(measured_type(field, &attributes));
+            }
+        }
+
+        // Field has the form:
+        //
+        //     F
+        Fields::Unit => {}
+
+        // Field has the form:
+        //
+        //     F(x, y)
+        Fields::Unnamed(ref unnamed_fields) => {
+            for (nth, field) in unnamed_fields.unnamed.iter().enumerate() {
+                let attributes = FieldAttributes::parse(&field.attrs)?;
+
+                if attributes.skip {
+                    continue;
+                }
+
+                let ident = Index::from(nth);
+
+                fields.push(cfg_gated(
+                    field,
+                    if packed {
+                        packed_field_size_of_val(
+                            field,
+                            &attributes,
+                            quote! { #receiver.#ident },
+                            &krate,
+                        )
+                    } else {
+                        field_size_of_val(field, &attributes, quote! { &#receiver.#ident }, &krate)
+                    },
+                ));
+                measured_types.extend(measured_type(field, &attributes));
+            }
+        }
+    }
+
+    let sum = join_fold(fields.into_iter(), |x, y| quote! { #x + #y }, quote! { 0 });
+
+    let generics = with_memory_usage_bounds(&input.generics, measured_types, attributes)?;
+
+    Ok(implement_memory_usage(input, attributes, &generics, sum))
+}
+
+fn derive_memory_usage_for_union(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataUnion,
+) -> Result<TokenStream2> {
+    // The active member is known by the user-supplied function, which
+    // measures what isn't already counted by the shallow size.
+    if let Some(union_with) = &attributes.union_with {
+        for field in data.fields.named.iter() {
+            FieldAttributes::parse(&field.attrs)?;
+        }
+
+        let receiver = receiver(attributes);
+        let size_of_active = match union_with {
+            // `self.method`.
+            Expr::Field(field) => {
+                let method = &field.member;
+
+                quote! { #receiver.#method(visited) }
+            }
+
+            // `path::to::function`.
+            Expr::Path(path) => quote! { #path(#receiver, visited) },
 
-                    // Variant has the form:
-                    //
-                    //     V(x, y)
-                    //
-                    // We want to generate:
-                    //
-                    //     Self::V(x, y) => { /* memory usage of x + y */ }
-                    Fields::Unnamed(ref fields) => {
-                        // Collect the identifiers. They are unnamed,
-                        // so let's use the `xi` convention where `i`
-                        // is the identifier index.
-                        let identifiers = fields
-                            .unnamed
-                            .iter()
-                            .enumerate()
-                            .map(|(nth, _field)| {
-                                let ident = format_ident!("x{}", Index::from(nth));
-
-                                quote! { #ident }
-                            });
-
-                        // Generate the `pattern` part.
-                        let pattern = {
-                            let pattern = join_fold(
-                                identifiers.clone(),
-                                |x, y| quote! { #x , #y },
-                                quote! {}
-                            );
-
-                            quote! { ( #pattern ) }
-                        };
-
-                        // Generate the `sum` part.
-                        let sum = {
-                            let sum = join_fold(
-                                identifiers.map(|ident| quote! {
-                                    loupe::MemoryUsage::size_of_val(#ident, visited) - std::mem::size_of_val(#ident)
-                                }),
-                                |x, y| quote! { #x + #y },
-                                quote! { 0 },
-                            );
-
-                            quote! { #sum }
-                        };
-
-                        (pattern, sum)
+            expression => {
+                return Err(Error::new_spanned(
+                    expression,
+                    "expected a method, like `self.method`, or a path to a function",
+                ))
+            }
+        };
+
+        return Ok(implement_memory_usage(
+            input,
+            attributes,
+            &input.generics,
+            size_of_active,
+        ));
+    }
+
+    // We have no way of knowing which union member is active, so the
+    // derive is refused except for unions where all members own no
+    // heap: the size of the union is then its shallow size.
+    for field in data.fields.named.iter() {
+        let attributes = FieldAttributes::parse(&field.attrs)?;
+
+        if let Some(with) = attributes.with {
+            return Err(Error::new_spanned(
+                with,
+                "`loupe(with = \"...\")` can't be used on union members",
+            ));
+        }
+
+        if let Some(estimate) = attributes.estimate {
+            return Err(Error::new_spanned(
+                estimate,
+                "`loupe(estimate = \"...\")` can't be used on union members",
+            ));
+        }
+
+        if attributes.shallow {
+            return Err(Error::new_spanned(
+                field,
+                "`loupe(shallow)` can't be used on union members",
+            ));
+        }
+
+        if !attributes.skip && !is_plain_old_data(&field.ty) {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "`MemoryUsage` can only be derived for unions whose members are primitive types, \
+                 raw pointers, or arrays or tuples of them; other members must be skipped with \
+                 `#[loupe(skip)]`, or the union must be `#[loupe(opaque)]` or measured with \
+                 `#[loupe(union_with = \"...\")]`",
+            ));
+        }
+    }
+
+    Ok(implement_memory_usage(
+        input,
+        attributes,
+        &input.generics,
+        quote! { 0 },
+    ))
+}
+
+fn derive_memory_usage_for_enum(
+    input: &DeriveInput,
+    attributes: &ContainerAttributes,
+    data: &DataEnum,
+) -> Result<TokenStream2> {
+    let krate = attributes.crate_path();
+    let receiver = receiver(attributes);
+    let receiver_type = receiver_type(attributes);
+
+    let mut arms = Vec::new();
+    let mut measured_types = Vec::new();
+
+    for variant in data.variants.iter() {
+        let ident = &variant.ident;
+        let span = ident.span();
+
+        // A skipped variant is only counted by its shallow size. Its
+        // fields aren't bound, so their types don't need to implement
+        // `MemoryUsage`. The `{ .. }` pattern matches any kind of
+        // variant.
+        if VariantAttributes::parse(&variant.attrs)?.skip {
+            for field in variant.fields.iter() {
+                FieldAttributes::parse(&field.attrs)?;
+            }
+
+            arms.push(quote_spanned! { span => #receiver_type::#ident { .. } => 0 });
+
+            continue;
+        }
+
+        // Check all the variants of the `enum`.
+        //
+        // We want to generate something like this:
+        //
+        //     Self::Variant ... => { ... }
+        //           ^^^^^^^ ^^^      ^^^
+        //           |       |        |
+        //           |       |        given by the `sum` variable
+        //           |       given by the `pattern` variable
+        //           given by the `ident` variable
+        //
+        // Let's compute the `pattern` and `sum` parts.
+        let (pattern, sum) = match variant.fields {
+            // Variant has the form:
+            //
+            //     V { x, y }
+            //
+            // We want to generate:
+            //
+            //     Self::V { x, y } => { /* memory usage of x + y */ }
+            //
+            // Skipped fields are bound to `_`. Fields keep their
+            // `#[cfg(...)]` attributes.
+            Fields::Named(ref fields) => {
+                let mut patterns = Vec::new();
+                let mut sizes = Vec::new();
+
+                for field in fields.named.iter() {
+                    let ident = field.ident.as_ref().unwrap();
+                    let span = ident.span();
+                    let attributes = FieldAttributes::parse(&field.attrs)?;
+                    let cfgs = cfg_attributes(field);
+
+                    if attributes.skip {
+                        patterns.push(quote_spanned!(span => #(#cfgs)* #ident: _));
+                    } else {
+                        patterns.push(quote_spanned!(span => #(#cfgs)* #ident));
+                        sizes.push(cfg_gated(
+                            field,
+                            field_size_of_val(field, &attributes, quote! { #ident }, &krate),
+                        ));
+
+                        measured_types.extend(measured_type(field, &attributes));
                     }
+                }
+
+                // Generate the `pattern` part.
+                let pattern = {
+                    let pattern =
+                        join_fold(patterns.into_iter(), |x, y| quote! { #x , #y }, quote! {});
+
+                    quote! { { #pattern } }
                 };
 
-                if must_skip(&variant.attrs) {
-                    sum = quote! { 0 };
-                }
+                // Generate the `sum` part.
+                let sum = join_fold(sizes.into_iter(), |x, y| quote! { #x + #y }, quote! { 0 });
 
-                // At this step, `pattern` and `sum` are well
-                // defined. Let's generate the full arm for the
-                // `match` statement.
-                quote_spanned! { span => Self::#ident#pattern => #sum }
+                (pattern, sum)
             }
-        ),
-        |x, y| quote! { #x , #y },
-        quote! {},
-    );
-
-    // Implement the `MemoryUsage` trait for `enum_name`.
-    (quote! {
-        #[allow(dead_code)]
-        impl #impl_generics loupe::MemoryUsage for #enum_name #ty_generics
-        #where_clause
-        {
-            fn size_of_val(&self, visited: &mut loupe::MemoryUsageTracker) -> usize {
-                std::mem::size_of_val(self) + match self {
-                    #match_arms
+
+            // Variant has the form:
+            //
+            //     V
+            //
+            // We want to generate:
+            //
+            //     Self::V => { 0 }
+            Fields::Unit => {
+                let pattern = quote! {};
+                let sum = quote! { 0 };
+
+                (pattern, sum)
+            }
+
+            // Variant has the form:
+            //
+            //     V(x, y)
+            //
+            // We want to generate:
+            //
+            //     Self::V { 0: x0, 1: x1 } => { /* memory usage of x0 + x1 */ }
+            //
+            // Skipped fields are bound to `_`. The braced form allows
+            // fields to keep their `#[cfg(...)]` attributes.
+            Fields::Unnamed(ref fields) => {
+                let mut patterns = Vec::new();
+                let mut sizes = Vec::new();
+
+                // The fields are unnamed, so let's use the `xi`
+                // convention where `i` is the identifier index.
+                for (nth, field) in fields.unnamed.iter().enumerate() {
+                    let attributes = FieldAttributes::parse(&field.attrs)?;
+                    let cfgs = cfg_attributes(field);
+                    let index = Index::from(nth);
+
+                    if attributes.skip {
+                        patterns.push(quote! { #(#cfgs)* #index: _ });
+                    } else {
+                        let ident = format_ident!("x{}", index);
+
+                        patterns.push(quote! { #(#cfgs)* #index: #ident });
+                        sizes.push(cfg_gated(
+                            field,
+                            field_size_of_val(field, &attributes, quote! { #ident }, &krate),
+                        ));
+
+                        measured_types.extend(measured_type(field, &attributes));
+                    }
                 }
+
+                // Generate the `pattern` part.
+                let pattern = {
+                    let pattern =
+                        join_fold(patterns.into_iter(), |x, y| quote! { #x , #y }, quote! {});
+
+                    quote! { { #pattern } }
+                };
+
+                // Generate the `sum` part.
+                let sum = join_fold(sizes.into_iter(), |x, y| quote! { #x + #y }, quote! { 0 });
+
+                (pattern, sum)
             }
+        };
+
+        // At this step, `pattern` and `sum` are well defined. Let's
+        // generate the full arm for the `match` statement.
+        arms.push(quote_spanned! { span => #receiver_type::#ident#pattern => #sum });
+    }
+
+    // The size of the discriminant, whatever its `repr`, is part of
+    // the shallow size of the enum, so variants only count their
+    // fields.
+    //
+    // A `#[non_exhaustive]` enum can be matched exhaustively in its
+    // own crate, but not from another one: a definition mirroring a
+    // remote `#[non_exhaustive]` enum gets a wildcard arm, counting
+    // the unknown variants by their shallow size.
+    if attributes.remote.is_some() && is_non_exhaustive(&input.attrs) {
+        arms.push(quote! {
+            #[allow(unreachable_patterns)]
+            _ => 0
+        });
+    }
+
+    let match_arms = join_fold(arms.into_iter(), |x, y| quote! { #x , #y }, quote! {});
+
+    let generics = with_memory_usage_bounds(&input.generics, measured_types, attributes)?;
+
+    // An enum without variants has no values, but `&Self` does.
+    if data.variants.is_empty() {
+        return Ok(implement_size_of_val(
+            input,
+            attributes,
+            &generics,
+            quote! { match *#receiver {} },
+        ));
+    }
+
+    let sum = quote! {
+        match #receiver {
+            #match_arms
         }
-    })
-    .into()
-}
+    };
 
-fn must_skip(attrs: &[Attribute]) -> bool {
-    attrs.iter().any(|attr| {
-        attr.path.is_ident("loupe") && matches!(attr.parse_args::<Ident>(), Ok(a) if a == "skip")
-    })
+    Ok(implement_memory_usage(input, attributes, &generics, sum))
 }
@@ -0,0 +1,9 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+struct S {
+    #[loupe(skip,,)]
+    x: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use loupe::{MemoryUsage, MemoryUsageTracker};
+
+fn size_of_buffer(buffer: &Vec<u8>, _: &mut dyn MemoryUsageTracker) -> usize {
+    buffer.capacity()
+}
+
+#[derive(MemoryUsage)]
+struct S {
+    #[loupe(with = "size_of_buffer", estimate = "64")]
+    buffer: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use loupe::MemoryUsage;
+
+struct RawFd(i32);
+
+#[derive(MemoryUsage)]
+struct Connection {
+    name: String,
+    socket: RawFd,
+}
+
+#[derive(MemoryUsage)]
+enum Endpoint {
+    Named { name: String },
+    Socket(RawFd),
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+// The orphan rule forbids implementing `MemoryUsage` for a type of
+// another crate.
+loupe::opaque_impl!(std::fs::File);
+
+fn main() {}
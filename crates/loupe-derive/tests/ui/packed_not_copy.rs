@@ -0,0 +1,10 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+#[repr(C, packed)]
+struct S {
+    tag: u8,
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use loupe::MemoryUsage;
+
+mod third_party {
+    pub struct Pair<T> {
+        pub left: T,
+        pub right: T,
+    }
+}
+
+#[derive(MemoryUsage)]
+#[loupe(remote = "third_party::Pair<T>")]
+struct PairDef<T> {
+    left: T,
+    right: T,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use loupe::MemoryUsage;
+use std::sync::Arc;
+
+#[derive(MemoryUsage)]
+struct S {
+    #[loupe(skip, shallow)]
+    parent: Arc<String>,
+}
+
+fn main() {}
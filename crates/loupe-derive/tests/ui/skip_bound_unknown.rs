@@ -0,0 +1,9 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+#[loupe(skip_bound(U))]
+struct S<T> {
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+#[loupe(skip)]
+struct S {
+    x: Vec<u8>,
+}
+
+fn main() {}
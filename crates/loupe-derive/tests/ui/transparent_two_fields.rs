@@ -0,0 +1,10 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+#[loupe(transparent)]
+struct Pair {
+    left: String,
+    right: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use loupe::MemoryUsage;
+use std::mem::ManuallyDrop;
+
+#[derive(MemoryUsage)]
+union U {
+    x: u64,
+    y: ManuallyDrop<String>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use loupe::MemoryUsage;
+
+#[derive(MemoryUsage)]
+#[loupe(union_with = "self.size_of_active")]
+struct Event {
+    kind: u32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use loupe::{MemoryUsage, MemoryUsageTracker};
+
+fn size_of_length(length: &usize, _: &mut dyn MemoryUsageTracker) -> usize {
+    *length
+}
+
+#[derive(MemoryUsage)]
+struct S {
+    #[loupe(skip, with = "size_of_length")]
+    length: usize,
+}
+
+fn main() {}
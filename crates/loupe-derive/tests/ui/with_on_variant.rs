@@ -0,0 +1,13 @@
+use loupe::{MemoryUsage, MemoryUsageTracker};
+
+fn size_of_e(_: &E, _: &mut dyn MemoryUsageTracker) -> usize {
+    0
+}
+
+#[derive(MemoryUsage)]
+enum E {
+    #[loupe(with = "size_of_e")]
+    A(Vec<u8>),
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use loupe::MemoryUsage;
+
+fn size_of_length(length: usize) -> usize {
+    length
+}
+
+#[derive(MemoryUsage)]
+struct S {
+    #[loupe(with = "size_of_length")]
+    length: usize,
+}
+
+fn main() {}
@@ -0,0 +1,187 @@
+//! Helpers for the code generated by `loupe-derive`. Not public API.
+
+use crate::MemoryUsageTracker;
+#[cfg(feature = "breakdown")]
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::{any, ptr};
+
+/// A field marked with `#[loupe(shallow)]`.
+///
+/// `(&&Shallow(&field)).track_shallow(tracker)` registers the value
+/// pointed to by the field in `tracker` if the field is a shared
+/// pointer, so that the value is not counted when reached later from
+/// another path. It does nothing for other types, which don't need to
+/// implement any trait: `TrackShallow` is implemented for
+/// `&Shallow<T>` and is preferred by the method resolution when it
+/// applies, otherwise `IgnoreShallow`, implemented for `Shallow<T>`,
+/// is picked.
+pub struct Shallow<'a, T: ?Sized>(pub &'a T);
+
+pub trait TrackShallow {
+    fn track_shallow(&self, tracker: &mut dyn MemoryUsageTracker);
+}
+
+pub trait IgnoreShallow {
+    fn track_shallow(&self, _: &mut dyn MemoryUsageTracker) {}
+}
+
+impl<T: ?Sized> IgnoreShallow for Shallow<'_, T> {}
+
+// The addresses are the ones tracked by the `MemoryUsage` impls.
+
+impl<T: ?Sized> TrackShallow for &Shallow<'_, Arc<T>> {
+    fn track_shallow(&self, tracker: &mut dyn MemoryUsageTracker) {
+        tracker.track(Arc::as_ptr(self.0).cast());
+    }
+}
+
+impl<T: ?Sized> TrackShallow for &Shallow<'_, Rc<T>> {
+    fn track_shallow(&self, tracker: &mut dyn MemoryUsageTracker) {
+        tracker.track(Rc::as_ptr(self.0).cast());
+    }
+}
+
+impl<T: ?Sized> TrackShallow for &Shallow<'_, &T> {
+    fn track_shallow(&self, tracker: &mut dyn MemoryUsageTracker) {
+        tracker.track(ptr::from_ref::<T>(*self.0).cast());
+    }
+}
+
+/// Register a `#[loupe(track_self)]` value, with
+/// `MemoryUsageTracker::track_self`: returns whether it's seen for the
+/// first time.
+pub fn track_self<T: ?Sized>(tracker: &mut dyn MemoryUsageTracker, value: &T) -> bool {
+    tracker.track_self(ptr::from_ref::<T>(value).cast(), any::type_name::<T>())
+}
+
+/// A field measured by `#[derive(MemoryUsageBreakdown)]`.
+///
+/// `(&&Breakdown(&field)).breakdown_field(name, tracker)` returns the
+/// tree of the field if its type implements `MemoryUsageBreakdown`, and
+/// a leaf with its size otherwise, with the same autoref trick as
+/// `Shallow`.
+#[cfg(feature = "breakdown")]
+pub struct Breakdown<'a, T: ?Sized>(pub &'a T);
+
+#[cfg(feature = "breakdown")]
+pub trait BreakdownNested {
+    fn breakdown_field(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> crate::BreakdownNode;
+}
+
+#[cfg(feature = "breakdown")]
+pub trait BreakdownLeaf {
+    fn breakdown_field(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> crate::BreakdownNode;
+}
+
+#[cfg(feature = "breakdown")]
+impl<T: crate::MemoryUsageBreakdown + ?Sized> BreakdownNested for &Breakdown<'_, T> {
+    fn breakdown_field(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> crate::BreakdownNode {
+        self.0.breakdown(name, tracker)
+    }
+}
+
+#[cfg(feature = "breakdown")]
+impl<T: crate::MemoryUsage + ?Sized> BreakdownLeaf for Breakdown<'_, T> {
+    fn breakdown_field(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> crate::BreakdownNode {
+        crate::BreakdownNode::new(name, self.0.size_of_val(tracker))
+    }
+}
+
+/// The measurement of a value by `assert_no_growth!`.
+///
+/// `(&&GrowthProbe(&value)).measure_growth()` also records the sizes of
+/// the parts of the value if its type implements
+/// `MemoryUsageBreakdown`, with the same autoref trick as `Shallow`, so
+/// that a failure shows which parts grew. `GrowthNested` has no impls
+/// without the `breakdown` feature.
+pub struct GrowthProbe<'a, T: ?Sized>(pub &'a T);
+
+pub struct Growth {
+    size: usize,
+    #[cfg(feature = "breakdown")]
+    snapshot: Option<crate::Snapshot>,
+}
+
+pub trait GrowthNested {
+    fn measure_growth(&self) -> Growth;
+}
+
+pub trait GrowthLeaf {
+    fn measure_growth(&self) -> Growth;
+}
+
+#[cfg(feature = "breakdown")]
+impl<T: crate::MemoryUsageBreakdown + ?Sized> GrowthNested for &GrowthProbe<'_, T> {
+    fn measure_growth(&self) -> Growth {
+        let tree = crate::breakdown(self.0);
+
+        Growth {
+            size: tree.total(),
+            snapshot: Some(crate::Snapshot::from_breakdown(&tree)),
+        }
+    }
+}
+
+impl<T: crate::MemoryUsage + ?Sized> GrowthLeaf for GrowthProbe<'_, T> {
+    fn measure_growth(&self) -> Growth {
+        Growth {
+            size: crate::size_of_val(self.0),
+            #[cfg(feature = "breakdown")]
+            snapshot: None,
+        }
+    }
+}
+
+/// The number of growing parts listed by `assert_no_growth!`.
+#[cfg(feature = "breakdown")]
+const GROWING_PARTS: usize = 5;
+
+pub fn assert_no_growth(name: &str, before: Growth, after: Growth, tolerance: usize) {
+    if after.size <= before.size + tolerance {
+        return;
+    }
+
+    #[allow(unused_mut)]
+    let mut message = format!(
+        "`{}` grew by {} bytes, from {} to {} bytes, more than the tolerance of {} bytes",
+        name,
+        after.size - before.size,
+        before.size,
+        after.size,
+        tolerance
+    );
+
+    #[cfg(feature = "breakdown")]
+    if let (Some(before), Some(after)) = (&before.snapshot, &after.snapshot) {
+        message.push_str("\ntop growing parts:");
+
+        for entry in after
+            .diff(before)
+            .iter()
+            .filter(|entry| entry.delta > 0)
+            .take(GROWING_PARTS)
+        {
+            message.push_str(&format!("\n    {}: +{} bytes", entry.path, entry.delta));
+        }
+    }
+
+    panic!("{}", message);
+}
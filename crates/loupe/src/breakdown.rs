@@ -0,0 +1,363 @@
+//! Break the memory usage of a value down into a tree, to know which
+//! field is big. It must be enabled with the `breakdown` feature.
+
+#[cfg(test)]
+use crate::POINTER_BYTE_SIZE;
+use crate::{
+    memory_usage::{heap_size_of_values, size_of_pointee, track_buffer},
+    policy::{allocation_size, buffer_size},
+    CollectionPolicy, MemoryUsage, MemoryUsageTracker,
+};
+use std::any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+
+/// A node of the tree returned by [`MemoryUsageBreakdown`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakdownNode {
+    /// The name of the value, like the name of a field.
+    pub name: Cow<'static, str>,
+
+    /// The size of the value that isn't part of its children, like
+    /// padding bytes.
+    pub self_size: usize,
+
+    /// The parts of the value.
+    pub children: Vec<BreakdownNode>,
+}
+
+impl BreakdownNode {
+    /// A node without children.
+    pub fn new(name: impl Into<Cow<'static, str>>, self_size: usize) -> Self {
+        Self {
+            name: name.into(),
+            self_size,
+            children: Vec::new(),
+        }
+    }
+
+    /// The whole size of the value: its own size plus the size of its
+    /// children, i.e. its [`size_of_val`](crate::size_of_val).
+    pub fn total(&self) -> usize {
+        self.self_size
+            + self
+                .children
+                .iter()
+                .map(BreakdownNode::total)
+                .sum::<usize>()
+    }
+}
+
+/// Serialize as `{ "name", "self_size", "heap_size", "children" }`,
+/// where `heap_size` is the size of the children, i.e. `total() -
+/// self_size`. Sizes are numbers.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BreakdownNode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut node = serializer.serialize_struct("BreakdownNode", 4)?;
+        node.serialize_field("name", &self.name)?;
+        node.serialize_field("self_size", &self.self_size)?;
+        node.serialize_field("heap_size", &(self.total() - self.self_size))?;
+        node.serialize_field("children", &self.children)?;
+        node.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl BreakdownNode {
+    /// Serialize the tree to pretty-printed JSON, e.g. to send it to a
+    /// dashboard. It requires the `serde` feature.
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a breakdown tree is always serializable")
+    }
+}
+
+/// Break the memory usage of a value down into a tree of
+/// [`BreakdownNode`]s.
+///
+/// The trait is derived with `#[derive(MemoryUsageBreakdown)]`, in
+/// addition to `#[derive(MemoryUsage)]`: each field is a child, named
+/// after the field, and the active variant of an enum is a child whose
+/// children are its fields. Fields whose type doesn't implement
+/// `MemoryUsageBreakdown` are leaves.
+pub trait MemoryUsageBreakdown: MemoryUsage {
+    /// Returns the tree of the value, whose root is named `name`.
+    ///
+    /// The total of the tree is the size returned by
+    /// `MemoryUsage::size_of_val` with the same tracker.
+    fn breakdown(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> BreakdownNode;
+}
+
+/// Same as [`size_of_val`](crate::size_of_val), but returns the tree
+/// of the memory usage of the value. The root is named after the type
+/// of the value.
+///
+/// ```rust
+/// use loupe::{MemoryUsage, MemoryUsageBreakdown};
+///
+/// #[derive(MemoryUsage, MemoryUsageBreakdown)]
+/// struct Session {
+///     user: String,
+///     history: Vec<u64>,
+/// }
+///
+/// let session = Session {
+///     user: String::from("root"),
+///     history: vec![1, 2, 3],
+/// };
+///
+/// let mut tree = loupe::breakdown(&session);
+/// tree.children.sort_by_key(|child| std::cmp::Reverse(child.total()));
+///
+/// assert_eq!(tree.children[0].name, "history");
+/// assert_eq!(tree.total(), loupe::size_of_val(&session));
+/// ```
+pub fn breakdown<T: MemoryUsageBreakdown + ?Sized>(value: &T) -> BreakdownNode {
+    value.breakdown(
+        Cow::Borrowed(std::any::type_name::<T>()),
+        &mut crate::DefaultTracker::new(),
+    )
+}
+
+impl<T> MemoryUsageBreakdown for Box<T>
+where
+    T: MemoryUsageBreakdown + ?Sized,
+{
+    fn breakdown(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> BreakdownNode {
+        // Same as `MemoryUsage`, the overhead of the allocator being
+        // part of the boxed value.
+        let reference = self.as_ref();
+        let bytes = allocation_size(tracker, mem::size_of_val(reference));
+        let mut node = BreakdownNode::new(name, mem::size_of_val(self));
+        let mut boxed = None;
+
+        size_of_pointee(tracker, ptr::from_ref::<T>(reference), bytes, |tracker| {
+            tracker.record(any::type_name::<Self>(), bytes);
+
+            let mut child = reference.breakdown(Cow::Borrowed("boxed"), tracker);
+            child.self_size += bytes - mem::size_of_val(reference);
+            let size = child.total();
+            boxed = Some(child);
+
+            size
+        });
+        node.children.extend(boxed);
+
+        node
+    }
+}
+
+// The elements of collections are grouped, so that large collections
+// don't produce large trees. The unused part of the buffer, counted
+// with `CollectionPolicy::Capacity`, is a "spare capacity" child.
+
+impl<T> MemoryUsageBreakdown for Vec<T>
+where
+    T: MemoryUsage,
+{
+    fn breakdown(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> BreakdownNode {
+        // Same as `MemoryUsage`.
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<T>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+        track_buffer(tracker, self.as_ptr().cast(), bytes);
+
+        let used = self.len() * mem::size_of::<T>();
+        let mut node = BreakdownNode::new(name, mem::size_of_val(self));
+        node.children.push(BreakdownNode::new(
+            "elements",
+            used + heap_size_of_values(tracker, self),
+        ));
+        push_spare_capacity(&mut node, bytes - used);
+
+        node
+    }
+}
+
+/// Add the unused bytes of the buffer of a collection to its node, if
+/// any.
+fn push_spare_capacity(node: &mut BreakdownNode, bytes: usize) {
+    if bytes > 0 {
+        node.children
+            .push(BreakdownNode::new("spare capacity", bytes));
+    }
+}
+
+impl<K, V, S> MemoryUsageBreakdown for HashMap<K, V, S>
+where
+    K: MemoryUsage,
+    V: MemoryUsage,
+{
+    fn breakdown(
+        &self,
+        name: Cow<'static, str>,
+        tracker: &mut dyn MemoryUsageTracker,
+    ) -> BreakdownNode {
+        // Same as `MemoryUsage`.
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<K>() + mem::size_of::<V>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        if let Some(key) = self.keys().next() {
+            track_buffer(tracker, ptr::from_ref::<K>(key).cast(), bytes);
+        }
+
+        let mut keys = self.len() * mem::size_of::<K>();
+        let mut values = self.len() * mem::size_of::<V>();
+
+        for (key, value) in self {
+            if tracker.should_stop() {
+                break;
+            }
+
+            keys += crate::heap_size_of_val_with(key, tracker);
+            values += crate::heap_size_of_val_with(value, tracker);
+        }
+
+        let mut node = BreakdownNode::new(name, mem::size_of_val(self));
+        node.children.push(BreakdownNode::new("keys", keys));
+        node.children.push(BreakdownNode::new("values", values));
+        push_spare_capacity(
+            &mut node,
+            bytes - self.len() * (mem::size_of::<K>() + mem::size_of::<V>()),
+        );
+
+        node
+    }
+}
+
+#[cfg(test)]
+mod test_breakdown_types {
+    use super::*;
+    use crate::size_of_val;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_box() {
+        let value: Box<Box<Vec<u32>>> = Box::new(Box::new(vec![1, 2]));
+        let tree = breakdown(&value);
+
+        assert_eq!(tree.self_size, POINTER_BYTE_SIZE);
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].name, "boxed");
+        assert_eq!(tree.children[0].self_size, POINTER_BYTE_SIZE);
+        assert_eq!(tree.children[0].children[0].name, "boxed");
+        assert_eq!(
+            tree.children[0].children[0].children,
+            [BreakdownNode::new("elements", 2 * 4)]
+        );
+        assert_eq!(tree.total(), size_of_val(&value));
+    }
+
+    #[test]
+    fn test_vec() {
+        let value: Vec<String> = vec![String::from("a"), String::from("bc")];
+        let tree = breakdown(&value);
+
+        assert_eq!(tree.self_size, 3 * POINTER_BYTE_SIZE);
+        assert_eq!(
+            tree.children,
+            [BreakdownNode::new(
+                "elements",
+                2 * 3 * POINTER_BYTE_SIZE + 3
+            )]
+        );
+        assert_eq!(tree.total(), size_of_val(&value));
+    }
+
+    #[test]
+    fn test_spare_capacity() {
+        use crate::policy::PolicyTracker;
+        use crate::MeasureOptions;
+
+        let mut value: Vec<u32> = Vec::with_capacity(16);
+        value.extend([1, 2]);
+        let spare = 4 * (value.capacity() - 2);
+
+        // Only counted with the `Capacity` policy.
+        assert_eq!(
+            breakdown(&value).children,
+            [BreakdownNode::new("elements", 2 * 4)]
+        );
+
+        let tree = value.breakdown(
+            Cow::Borrowed("vec"),
+            &mut PolicyTracker::new(MeasureOptions::default()),
+        );
+        assert_eq!(
+            tree.children,
+            [
+                BreakdownNode::new("elements", 2 * 4),
+                BreakdownNode::new("spare capacity", spare),
+            ]
+        );
+        assert_eq!(
+            tree.total(),
+            crate::size_of_val_with(&value, &mut PolicyTracker::new(MeasureOptions::default()))
+        );
+
+        let mut value: HashMap<u32, u32> = HashMap::with_capacity(16);
+        value.insert(1, 2);
+
+        let mut tracker = PolicyTracker::new(MeasureOptions::default());
+        let tree = value.breakdown(Cow::Borrowed("map"), &mut tracker);
+        assert_eq!(tree.children[2].name, "spare capacity");
+        assert_eq!(
+            tree.total(),
+            crate::size_of_val_with(&value, &mut PolicyTracker::new(MeasureOptions::default()))
+        );
+    }
+
+    #[test]
+    fn test_hashmap() {
+        let shared = Arc::new(0u64);
+        let mut value: HashMap<u8, Arc<u64>> = HashMap::new();
+        value.insert(1, shared.clone());
+        value.insert(2, shared);
+
+        let tree = breakdown(&value);
+
+        assert_eq!(tree.self_size, mem::size_of_val(&value));
+        assert_eq!(tree.children[0], BreakdownNode::new("keys", 2));
+        assert_eq!(tree.children[1].name, "values");
+        assert_eq!(tree.total(), size_of_val(&value));
+
+        // The shared value is counted once.
+        let mut tracker = BTreeSet::new();
+        let tree = value.breakdown(Cow::Borrowed("map"), &mut tracker);
+        assert_eq!(
+            tree.children[1].self_size,
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + 8
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Measure a value within a budget, see
+//! [`size_of_val_bounded`](crate::size_of_val_bounded).
+
+use crate::MemoryUsageTracker;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+
+/// The limits of a measurement. The traversal stops once one of them
+/// is reached. The default budget has no limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// The maximum total size of the allocations to visit, as recorded
+    /// by `MemoryUsageTracker::record`.
+    pub max_bytes: Option<usize>,
+
+    /// The maximum number of allocations to visit, as recorded by
+    /// `MemoryUsageTracker::record`.
+    pub max_nodes: Option<usize>,
+}
+
+/// The result of a measurement within a [`Budget`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeEstimate {
+    /// The traversal completed, the size is exact.
+    Exact(usize),
+
+    /// The budget was exhausted before the end of the traversal, the
+    /// size is a lower bound.
+    AtLeast(usize),
+}
+
+impl SizeEstimate {
+    /// The size, exact or not.
+    pub fn bytes(&self) -> usize {
+        match *self {
+            Self::Exact(bytes) | Self::AtLeast(bytes) => bytes,
+        }
+    }
+
+    /// Whether the traversal completed.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Self::Exact(_))
+    }
+}
+
+/// A tracker asking the traversal to stop once the budget is
+/// exhausted.
+pub(crate) struct BudgetTracker {
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    budget: Budget,
+    bytes: usize,
+    nodes: usize,
+    /// Whether `should_stop` returned true, i.e. some values weren't
+    /// measured: the traversal may end exactly at the budget.
+    exhausted: Cell<bool>,
+}
+
+impl BudgetTracker {
+    pub(crate) fn new(budget: Budget) -> Self {
+        Self {
+            visited: BTreeSet::new(),
+            values: BTreeSet::new(),
+            budget,
+            bytes: 0,
+            nodes: 0,
+            exhausted: Cell::new(false),
+        }
+    }
+
+    /// Whether the traversal stopped before measuring all the values.
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.exhausted.get()
+    }
+}
+
+impl MemoryUsageTracker for BudgetTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn record(&mut self, _type_name: &'static str, bytes: usize) {
+        self.bytes += bytes;
+        self.nodes += 1;
+    }
+
+    fn should_stop(&self) -> bool {
+        let stop = self
+            .budget
+            .max_bytes
+            .is_some_and(|max_bytes| self.bytes >= max_bytes)
+            || self
+                .budget
+                .max_nodes
+                .is_some_and(|max_nodes| self.nodes >= max_nodes);
+
+        // The traversal only asks before measuring a value.
+        if stop {
+            self.exhausted.set(true);
+        }
+
+        stop
+    }
+}
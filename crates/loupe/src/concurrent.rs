@@ -0,0 +1,193 @@
+//! A tracker shared between threads.
+
+use crate::MemoryUsageTracker;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// The number of sets of addresses, so that threads measuring at the
+/// same time seldom wait for each other.
+const SHARDS: usize = 16;
+
+/// A tracker that can be shared between threads, so that values
+/// measured from different threads, e.g. the subsystems of a process,
+/// share their deduplication state: data shared between threads with
+/// `Arc`s is counted once overall.
+///
+/// The tracker is used through a shared reference: `&ConcurrentTracker`
+/// implements `MemoryUsageTracker`.
+///
+/// ```rust
+/// use loupe::{ConcurrentTracker, MemoryUsage};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let shared = Arc::new(vec![0u8; 1024]);
+/// let subsystems = vec![vec![Arc::clone(&shared)], vec![shared]];
+/// let tracker = ConcurrentTracker::new();
+///
+/// let total = thread::scope(|scope| {
+///     let handles = subsystems
+///         .iter()
+///         .map(|subsystem| {
+///             let mut tracker = &tracker;
+///
+///             scope.spawn(move || subsystem.size_of_val(&mut tracker))
+///         })
+///         .collect::<Vec<_>>();
+///
+///     handles.into_iter().map(|handle| handle.join().unwrap()).sum::<usize>()
+/// });
+///
+/// assert!(total < 2 * loupe::size_of_val(&subsystems[0]));
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentTracker {
+    shards: Vec<Mutex<HashSet<usize>>>,
+    /// The values registered by `track_self`, which are few.
+    values: Mutex<HashSet<(usize, &'static str)>>,
+}
+
+impl ConcurrentTracker {
+    /// An empty tracker.
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            values: Mutex::default(),
+        }
+    }
+
+    /// Track `address`, like `MemoryUsageTracker::track`: returns
+    /// whether it's seen for the first time, by any thread.
+    pub fn track(&self, address: *const ()) -> bool {
+        // Pointers aren't `Send`, but addresses are only compared.
+        let address = address.addr();
+
+        self.shard(address).insert(address)
+    }
+
+    /// Track the value at `address`, like
+    /// `MemoryUsageTracker::track_self`.
+    pub fn track_self(&self, address: *const (), type_name: &'static str) -> bool {
+        self.values
+            .lock()
+            .unwrap()
+            .insert((address.addr(), type_name))
+    }
+
+    /// Forget all the addresses seen so far.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().unwrap().clear();
+        }
+
+        self.values.lock().unwrap().clear();
+    }
+
+    /// The number of addresses seen so far.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Whether no address has been seen so far.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, address: usize) -> std::sync::MutexGuard<'_, HashSet<usize>> {
+        // The low bits of an address are mostly alignment.
+        self.shards[(address >> 4) % SHARDS].lock().unwrap()
+    }
+}
+
+impl Default for ConcurrentTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryUsageTracker for ConcurrentTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        ConcurrentTracker::track(self, address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        ConcurrentTracker::track_self(self, address, type_name)
+    }
+}
+
+impl MemoryUsageTracker for &ConcurrentTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        ConcurrentTracker::track(self, address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        ConcurrentTracker::track_self(self, address, type_name)
+    }
+}
+
+#[cfg(test)]
+mod test_concurrent_types {
+    use super::*;
+    use crate::{size_of_val, MemoryUsage, POINTER_BYTE_SIZE};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<ConcurrentTracker>();
+    }
+
+    #[test]
+    fn test_threads() {
+        let shared = Arc::new(vec![7u8; 4096]);
+        let subsystems = (0..8)
+            .map(|nth| vec![Arc::clone(&shared), Arc::new(vec![nth; 16])])
+            .collect::<Vec<_>>();
+        drop(shared);
+
+        let tracker = ConcurrentTracker::new();
+        let total = thread::scope(|scope| {
+            let handles = subsystems
+                .iter()
+                .map(|subsystem| {
+                    let mut tracker = &tracker;
+
+                    scope.spawn(move || subsystem.size_of_val(&mut tracker))
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum::<usize>()
+        });
+
+        let arc_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE;
+
+        // The shared buffer is counted once, by one of the threads.
+        assert_eq!(
+            total,
+            8 * (3 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + arc_size + 16) + arc_size + 4096
+        );
+        assert_eq!(total + 3 * POINTER_BYTE_SIZE, size_of_val(&subsystems));
+        // The `Arc`s and the buffers of the `Vec`s.
+        assert_eq!(tracker.len(), 9 + 9 + 8);
+
+        tracker.clear();
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn test_owned_tracker() {
+        let shared = Arc::new(1u64);
+        let mut tracker = ConcurrentTracker::new();
+
+        assert!(Arc::clone(&shared).size_of_val(&mut tracker) > POINTER_BYTE_SIZE);
+        assert_eq!(shared.size_of_val(&mut tracker), POINTER_BYTE_SIZE);
+    }
+}
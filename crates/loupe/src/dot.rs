@@ -0,0 +1,199 @@
+//! Export the ownership graph of a value to the
+//! [DOT](https://graphviz.org/doc/info/lang.html) format of Graphviz.
+//! It must be enabled with the `dot` feature.
+//!
+//! Nodes are the value and the values it points to, like the value of
+//! a `Box` or of an `Arc`, labelled with their type and their size.
+//! Edges go from a value to the values it points to. A value reached
+//! from several pointers, like an `Arc` with several owners, is charged
+//! to the first one, and is highlighted.
+
+use crate::{HumanSize, MemoryUsage, MemoryUsageTracker};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ptr;
+
+/// Returns the ownership graph of a value, in the DOT format.
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// let shared = Arc::new(String::from("shared"));
+/// let dot = loupe::dot::to_dot(&(Arc::clone(&shared), shared));
+///
+/// assert!(dot.starts_with("digraph loupe {"));
+/// ```
+pub fn to_dot<T: MemoryUsage + ?Sized>(value: &T) -> String {
+    let mut tracker = DotTracker::new();
+
+    // The root isn't tracked, so that it's measured as with
+    // `size_of_val`.
+    tracker.enter(ptr::from_ref::<T>(value).cast(), std::any::type_name::<T>());
+    let size = value.size_of_val(&mut tracker);
+    tracker.leave(size);
+
+    tracker.to_dot()
+}
+
+/// A value of the graph.
+struct Node {
+    type_name: &'static str,
+    size: usize,
+    shared: bool,
+}
+
+/// A tracker recording the ownership graph of the measured values.
+#[derive(Default)]
+pub struct DotTracker {
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    nodes: Vec<Node>,
+    indices: BTreeMap<*const (), usize>,
+    edges: BTreeSet<(usize, usize)>,
+    stack: Vec<usize>,
+}
+
+impl DotTracker {
+    /// A tracker with an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the graph recorded so far, in the DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut output = String::from("digraph loupe {\n    node [shape=box];\n");
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let label = format!("{}\\n{:#}", escape(node.type_name), HumanSize(node.size));
+
+            if node.shared {
+                writeln!(
+                    output,
+                    "    n{} [label=\"{}\", style=filled, fillcolor=gold];",
+                    index, label
+                )
+            } else {
+                writeln!(output, "    n{} [label=\"{}\"];", index, label)
+            }
+            .unwrap();
+        }
+
+        for (from, to) in &self.edges {
+            writeln!(output, "    n{} -> n{};", from, to).unwrap();
+        }
+
+        output.push_str("}\n");
+
+        output
+    }
+}
+
+impl MemoryUsageTracker for DotTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        if self.visited.insert(address) {
+            return true;
+        }
+
+        // A value reached again gets an edge from its new owner. Only
+        // values entered by a pointer are nodes.
+        if let (Some(&from), Some(&to)) = (self.stack.last(), self.indices.get(&address)) {
+            self.nodes[to].shared = true;
+            self.edges.insert((from, to));
+        }
+
+        false
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        let index = self.nodes.len();
+
+        self.nodes.push(Node {
+            type_name,
+            size: 0,
+            shared: false,
+        });
+        self.indices.insert(address, index);
+
+        if let Some(&from) = self.stack.last() {
+            self.edges.insert((from, index));
+        }
+
+        self.stack.push(index);
+    }
+
+    fn leave(&mut self, size: usize) {
+        if let Some(index) = self.stack.pop() {
+            self.nodes[index].size = size;
+        }
+    }
+}
+
+/// Escape a string for a quoted DOT identifier.
+fn escape(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test_dot_types {
+    use super::*;
+    use crate::POINTER_BYTE_SIZE;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_to_dot() {
+        struct Config {
+            name: Box<u32>,
+            first: Arc<u64>,
+            second: Arc<u64>,
+        }
+
+        impl MemoryUsage for Config {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                std::mem::size_of_val(self)
+                    + crate::heap_size_of_val_with(&self.name, tracker)
+                    + crate::heap_size_of_val_with(&self.first, tracker)
+                    + crate::heap_size_of_val_with(&self.second, tracker)
+            }
+        }
+
+        let shared = Arc::new(7);
+        let config = Config {
+            name: Box::new(1),
+            first: shared.clone(),
+            second: shared,
+        };
+
+        let dot = to_dot(&config);
+        let expected = format!(
+            "digraph loupe {{
+    node [shape=box];
+    n0 [label=\"{}\\n{} B\"];
+    n1 [label=\"u32\\n4 B\"];
+    n2 [label=\"u64\\n{} B\", style=filled, fillcolor=gold];
+    n0 -> n1;
+    n0 -> n2;
+}}
+",
+            std::any::type_name::<Config>(),
+            crate::size_of_val(&config),
+            2 * POINTER_BYTE_SIZE + 8,
+        );
+
+        assert_eq!(dot, expected);
+    }
+
+    #[test]
+    fn test_nested_pointers() {
+        let value: Box<Box<String>> = Box::new(Box::new(String::from("abc")));
+        let dot = to_dot(&value);
+
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("n1 -> n2;"));
+        assert!(!dot.contains("n0 -> n2;"));
+        assert!(dot.contains("n2 [label=\"alloc::string::String\\n"));
+    }
+}
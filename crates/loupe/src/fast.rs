@@ -0,0 +1,214 @@
+//! A tracker for large values, with a fast set of addresses.
+
+use crate::MemoryUsageTracker;
+use std::collections::BTreeSet;
+
+/// A tracker storing the addresses in a hash table specialized for
+/// addresses, for values with millions of pointers.
+///
+/// Addresses are already well distributed, so they are hashed with a
+/// single multiplication, and the table uses linear probing. It's much
+/// faster than the `BTreeSet` of [`DefaultTracker`](crate::DefaultTracker)
+/// when the pointers are spread over the heap, and as fast when they
+/// point to consecutive allocations, as long as the capacity is given
+/// upfront with [`FastTracker::with_capacity`].
+///
+/// ```rust
+/// use loupe::{FastTracker, MemoryUsage};
+///
+/// let value: Vec<Box<u64>> = (0..1000).map(Box::new).collect();
+/// let mut tracker = FastTracker::with_capacity(value.len());
+///
+/// assert_eq!(value.size_of_val(&mut tracker), loupe::size_of_val(&value));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FastTracker {
+    /// The addresses, where 0 is an empty slot. The length is 0 or a
+    /// power of two.
+    slots: Vec<usize>,
+    /// Whether the null address has been seen, since it can't be stored
+    /// in `slots`.
+    null: bool,
+    len: usize,
+    /// The values registered by `track_self`, which are few.
+    values: BTreeSet<(usize, &'static str)>,
+}
+
+impl FastTracker {
+    /// An empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty tracker, which can hold `capacity` addresses without
+    /// reallocating, e.g. the number of pointers of the measured value.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut tracker = Self::new();
+        tracker.reserve(capacity);
+
+        tracker
+    }
+
+    /// Forget all the addresses seen so far. The capacity is kept.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = 0);
+        self.null = false;
+        self.len = 0;
+        self.values.clear();
+    }
+
+    /// The number of addresses seen so far, including the null one.
+    pub fn len(&self) -> usize {
+        self.len + usize::from(self.null)
+    }
+
+    /// Whether no address has been seen so far.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make room for `additional` more addresses. The table is kept at
+    /// most half full, so that probe sequences are short.
+    fn reserve(&mut self, additional: usize) {
+        let required = (self.len + additional)
+            .saturating_mul(2)
+            .next_power_of_two();
+
+        if required <= self.slots.len() {
+            return;
+        }
+
+        let slots = std::mem::replace(&mut self.slots, vec![0; required]);
+
+        for address in slots.into_iter().filter(|&address| address != 0) {
+            let index = self.probe(address);
+            self.slots[index] = address;
+        }
+    }
+
+    /// The index of the slot of `address`, or of the empty slot where it
+    /// belongs.
+    fn probe(&self, address: usize) -> usize {
+        let mask = self.slots.len() - 1;
+
+        // Fibonacci hashing: the high bits of the product are the best
+        // mixed, the low bits of addresses are mostly alignment.
+        let hash = (address as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut index = (hash >> 32) as usize & mask;
+
+        loop {
+            let slot = self.slots[index];
+
+            if slot == 0 || slot == address {
+                return index;
+            }
+
+            index = (index + 1) & mask;
+        }
+    }
+}
+
+impl MemoryUsageTracker for FastTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        let address = address.addr();
+
+        if address == 0 {
+            return !std::mem::replace(&mut self.null, true);
+        }
+
+        self.reserve(1);
+
+        let index = self.probe(address);
+
+        if self.slots[index] == address {
+            return false;
+        }
+
+        self.slots[index] = address;
+        self.len += 1;
+
+        true
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address.addr(), type_name))
+    }
+}
+
+#[cfg(test)]
+mod test_fast_types {
+    use super::*;
+    use crate::MemoryUsage;
+    use std::collections::{BTreeSet, HashMap};
+    use std::ptr;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_track() {
+        let mut tracker = FastTracker::new();
+        let values = (0..1000u64).collect::<Vec<_>>();
+
+        for value in &values {
+            assert!(tracker.track(ptr::from_ref(value).cast()));
+        }
+
+        for value in &values {
+            assert!(!tracker.track(ptr::from_ref(value).cast()));
+        }
+
+        assert!(tracker.track(ptr::null()));
+        assert!(!tracker.track(ptr::null()));
+        assert_eq!(tracker.len(), 1001);
+
+        tracker.clear();
+        assert!(tracker.is_empty());
+        assert!(tracker.track(ptr::from_ref(&values[0]).cast()));
+        assert!(tracker.track(ptr::null()));
+    }
+
+    #[test]
+    fn test_same_len_as_default_tracker() {
+        let value: Vec<Box<u64>> = (0..100).map(Box::new).collect();
+        let mut fast = FastTracker::new();
+        let mut default = crate::DefaultTracker::new();
+
+        value.size_of_val(&mut fast);
+        value.size_of_val(&mut default);
+        assert_eq!(fast.len(), default.len());
+
+        // The null address is counted by both.
+        fast.track(ptr::null());
+        default.track(ptr::null());
+        assert_eq!(fast.len(), default.len());
+        assert_eq!(fast.len(), 1 + 100 + 1);
+    }
+
+    #[test]
+    fn test_same_totals_as_btreeset() {
+        let shared = Rc::new(String::from("shared"));
+        let boxes: Vec<Box<u64>> = (0..10_000).map(Box::new).collect();
+        let rcs: Vec<Rc<String>> = (0..100)
+            .map(|nth| {
+                if nth % 3 == 0 {
+                    Rc::clone(&shared)
+                } else {
+                    Rc::new(nth.to_string())
+                }
+            })
+            .collect();
+        let mut map: HashMap<u32, Box<Rc<String>>> = HashMap::new();
+        map.insert(1, Box::new(Rc::clone(&shared)));
+        map.insert(2, Box::new(shared));
+
+        let value = (boxes, rcs, map);
+
+        assert_eq!(
+            value.size_of_val(&mut FastTracker::new()),
+            value.size_of_val(&mut BTreeSet::new())
+        );
+        assert_eq!(
+            value.size_of_val(&mut FastTracker::with_capacity(10)),
+            value.size_of_val(&mut BTreeSet::new())
+        );
+    }
+}
@@ -86,13 +86,59 @@
 //! Finally, our implementations are certainly not perfect! Feel free to
 //! challenge it and come to discuss!
 
+// Tests spell sizes out as `count * size` to stay readable.
+#![cfg_attr(test, allow(clippy::identity_op, clippy::erasing_op))]
+
+#[cfg(feature = "breakdown")]
+mod breakdown;
+mod budget;
+mod concurrent;
+#[cfg(feature = "dot")]
+pub mod dot;
+mod fast;
 mod memory_usage;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod policy;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod session;
+mod size;
+#[cfg(feature = "breakdown")]
+mod snapshot;
+mod stats;
+#[cfg(feature = "tracing")]
+mod trace;
+mod visit;
+
+#[doc(hidden)]
+pub mod __private;
 
+#[cfg(feature = "breakdown")]
+pub use breakdown::{breakdown, BreakdownNode, MemoryUsageBreakdown};
+pub use budget::{Budget, SizeEstimate};
+pub use concurrent::ConcurrentTracker;
+pub use fast::FastTracker;
 #[cfg(feature = "derive")]
 pub use loupe_derive::*;
 pub use memory_usage::*;
+pub use policy::{
+    AllocationModel, CollectionPolicy, JemallocModel, MeasureOptions, SharedPolicy, SizePolicy,
+    SystemMallocModel,
+};
+pub use session::{MemoryUsageSession, RootSize, SessionReport};
+pub use size::{format_size, HumanSize, MemorySize};
+#[cfg(feature = "breakdown")]
+pub use snapshot::{diff, DeltaEntry, Snapshot};
+pub use stats::{
+    AccountingTracker, AllocationHistogram, HistogramBucket, LabelReport, LabelTracker,
+    StatsTracker, TrackerStats, TypeStats, TypeStatsTracker,
+};
+#[cfg(feature = "tracing")]
+pub use trace::TracingTracker;
+pub use visit::{visit, MemoryVisitor};
 
-use std::collections::BTreeSet;
+use std::mem;
 
 /// Returns the size of the pointer-to value in bytes. The size is
 /// calculated with `MemoryUsage::size_of_val`.
@@ -119,16 +165,745 @@ use std::collections::BTreeSet;
 ///     assert_eq!(72, loupe::size_of_val(&s));
 /// }
 /// ```
-pub fn size_of_val<T: MemoryUsage>(value: &T) -> usize {
-    <T as MemoryUsage>::size_of_val(value, &mut BTreeSet::new())
+pub fn size_of_val<T: MemoryUsage + ?Sized>(value: &T) -> usize {
+    size_of_val_with(value, &mut DefaultTracker::new())
+}
+
+/// Same as [`size_of_val`], but with a caller-provided tracker.
+///
+/// Values already registered in the tracker, e.g. by measuring another
+/// value with the same tracker, are not counted again. It allows
+/// several roots to share their deduplication state, so that data
+/// shared between them is counted once overall:
+///
+/// ```rust
+/// use std::collections::BTreeSet;
+/// use std::sync::Arc;
+///
+/// let shared = Arc::new(vec![0u8; 64]);
+/// let (a, b) = (Arc::clone(&shared), shared);
+///
+/// let mut tracker = BTreeSet::new();
+/// let total = loupe::size_of_val_with(&a, &mut tracker)
+///     + loupe::size_of_val_with(&b, &mut tracker);
+///
+/// assert!(total < loupe::size_of_val(&a) + loupe::size_of_val(&b));
+/// ```
+///
+/// With the `tracing` feature, [`size_of_val`] and this function emit a
+/// `loupe::size_of_val` span at the `DEBUG` level, with the type of the
+/// value as the `type_name` field, and its size as the `bytes` field.
+pub fn size_of_val_with<T: MemoryUsage + ?Sized>(
+    value: &T,
+    tracker: &mut dyn MemoryUsageTracker,
+) -> usize {
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "loupe::size_of_val",
+        type_name = std::any::type_name::<T>(),
+        bytes = tracing::field::Empty,
+    )
+    .entered();
+
+    let size = <T as MemoryUsage>::size_of_val(value, tracker);
+
+    #[cfg(feature = "tracing")]
+    span.record("bytes", size);
+
+    size
+}
+
+/// Returns the size of several values, e.g. given by an iterator,
+/// measured with the same tracker: data shared between the values is
+/// counted once.
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// let shared = Arc::new(vec![0u8; 64]);
+/// let sessions = vec![Arc::clone(&shared), shared];
+///
+/// assert!(loupe::size_of_vals(&sessions) < 2 * loupe::size_of_val(&sessions[0]));
+/// ```
+pub fn size_of_vals<'a, T, I>(values: I) -> usize
+where
+    T: MemoryUsage + ?Sized + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    size_of_each_val(values).into_iter().sum()
+}
+
+/// Same as [`size_of_vals`], but returns the size of each value, in the
+/// iteration order. Data shared between values is counted in the size
+/// of the first value that reaches it only.
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// let shared = Arc::new(vec![0u8; 64]);
+/// let sizes = loupe::size_of_each_val(&[Arc::clone(&shared), shared]);
+///
+/// assert!(sizes[0] > sizes[1]);
+/// ```
+pub fn size_of_each_val<'a, T, I>(values: I) -> Vec<usize>
+where
+    T: MemoryUsage + ?Sized + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let mut tracker = DefaultTracker::new();
+
+    values
+        .into_iter()
+        .map(|value| <T as MemoryUsage>::size_of_val(value, &mut tracker))
+        .collect()
+}
+
+/// Same as [`size_of_val`], but the traversal stops early once the
+/// budget is exhausted, so that measuring a large value doesn't take
+/// too long. The size is then a lower bound.
+///
+/// ```rust
+/// use loupe::{Budget, SizeEstimate};
+///
+/// let value = vec![vec![0u8; 100]; 1000];
+/// let budget = Budget {
+///     max_nodes: Some(10),
+///     ..Budget::default()
+/// };
+///
+/// assert!(matches!(loupe::size_of_val_bounded(&value, budget), SizeEstimate::AtLeast(_)));
+/// ```
+pub fn size_of_val_bounded<T: MemoryUsage + ?Sized>(value: &T, budget: Budget) -> SizeEstimate {
+    let mut tracker = budget::BudgetTracker::new(budget);
+    let size = <T as MemoryUsage>::size_of_val(value, &mut tracker);
+
+    if tracker.is_exhausted() {
+        SizeEstimate::AtLeast(size)
+    } else {
+        SizeEstimate::Exact(size)
+    }
+}
+
+/// Returns the size of the memory owned by a value, i.e. its
+/// [`size_of_val`] without its shallow size, as given by
+/// [`std::mem::size_of_val`].
+///
+/// It's the size to add for a value whose shallow size is already
+/// counted, e.g. because it's stored inline in a parent value:
+///
+/// ```rust
+/// assert_eq!(loupe::heap_size_of_val(&42u64), 0);
+/// assert_eq!(loupe::heap_size_of_val(&vec![0u8; 100]), 100);
+/// ```
+pub fn heap_size_of_val<T: MemoryUsage + ?Sized>(value: &T) -> usize {
+    heap_size_of_val_with(value, &mut DefaultTracker::new())
+}
+
+/// Same as [`heap_size_of_val`], but with a caller-provided tracker,
+/// like [`size_of_val_with`].
+///
+/// It's the building block of manual implementations of
+/// [`MemoryUsage`]: the size of a value is its shallow size, plus the
+/// heap size of each of its fields.
+pub fn heap_size_of_val_with<T: MemoryUsage + ?Sized>(
+    value: &T,
+    tracker: &mut dyn MemoryUsageTracker,
+) -> usize {
+    // An implementation could return less than the shallow size, don't
+    // underflow.
+    <T as MemoryUsage>::size_of_val(value, tracker).saturating_sub(mem::size_of_val(value))
+}
+
+/// Returns the shallow size of a type, as given by
+/// [`MemoryUsageSized::SHALLOW_SIZE`].
+///
+/// ```rust
+/// assert_eq!(loupe::shallow_size_of::<u64>(), 8);
+/// ```
+pub fn shallow_size_of<T: MemoryUsage>() -> usize {
+    T::SHALLOW_SIZE
+}
+
+/// Same as [`size_of_val`], but the shallow size of the value and the
+/// size of the memory it owns are returned separately.
+///
+/// Collections are counted as they always were, without a policy, see
+/// [`MemoryUsageTracker::size_policy`]: `VecDeque` by its capacity, and
+/// the other collections, like `Vec` or `String`, by their length. It
+/// differs from [`measure_with`] with the default options, which count
+/// the whole capacity of all of them.
+///
+/// ```rust
+/// let size = loupe::measure(&vec![1u8, 2, 3]);
+///
+/// assert_eq!(size.stack, std::mem::size_of::<Vec<u8>>());
+/// assert_eq!(size.heap, 3);
+/// assert_eq!(size.total(), loupe::size_of_val(&vec![1u8, 2, 3]));
+/// ```
+pub fn measure<T: MemoryUsage + ?Sized>(value: &T) -> MemorySize {
+    MemorySize {
+        stack: mem::size_of_val(value),
+        heap: heap_size_of_val(value),
+    }
+}
+
+/// Same as [`measure`], with options, e.g. to count the whole
+/// capacity of collections, see [`SizePolicy`], or the overhead of the
+/// allocator, see [`AllocationModel`].
+///
+/// The default options count the capacity of collections, the memory
+/// they actually hold, while [`measure`] counts most of them by their
+/// length: the two differ for a collection with spare capacity.
+///
+/// ```rust
+/// use loupe::{CollectionPolicy, MeasureOptions, SizePolicy};
+///
+/// let mut value = Vec::<u64>::with_capacity(1024);
+/// value.push(1);
+///
+/// let options = MeasureOptions {
+///     policy: SizePolicy {
+///         collections: CollectionPolicy::Len,
+///         ..SizePolicy::default()
+///     },
+///     ..MeasureOptions::default()
+/// };
+///
+/// assert!(loupe::measure_with(&value, MeasureOptions::default()).heap >= 1024 * 8);
+/// assert_eq!(loupe::measure_with(&value, options), loupe::measure(&value));
+/// ```
+pub fn measure_with<T: MemoryUsage + ?Sized>(value: &T, options: MeasureOptions) -> MemorySize {
+    MemorySize {
+        stack: mem::size_of_val(value),
+        heap: heap_size_of_val_with(value, &mut policy::PolicyTracker::new(options)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_size_of_vals() {
+        use std::sync::Arc;
+
+        let shared = Arc::new(vec![0u8; 100]);
+        let items = vec![
+            (1u32, Arc::clone(&shared)),
+            (2u32, Arc::new(vec![1u8; 10])),
+            (3u32, shared),
+        ];
+
+        let shared_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 100;
+        let item_size = mem::size_of::<(u32, Arc<Vec<u8>>)>();
+
+        assert_eq!(
+            size_of_vals(&items),
+            3 * item_size + shared_size + (shared_size - 100 + 10)
+        );
+        assert_eq!(
+            size_of_vals(items.iter().filter(|(id, _)| *id != 2)),
+            2 * item_size + shared_size
+        );
+
+        // The first item reaching the shared buffer is charged with it.
+        assert_eq!(
+            size_of_each_val(&items),
+            [
+                item_size + shared_size,
+                item_size + shared_size - 100 + 10,
+                item_size
+            ]
+        );
+        assert_eq!(
+            size_of_each_val(items.iter().rev()),
+            [
+                item_size + shared_size,
+                item_size + shared_size - 100 + 10,
+                item_size
+            ]
+        );
+        assert!(size_of_each_val(&items[..0]).is_empty());
+    }
+
     #[test]
     fn test_size_of_val_helper() {
         assert_eq!(size_of_val(&"abc"), 2 * POINTER_BYTE_SIZE + 1 * 3);
+
+        let value: &dyn MemoryUsage = &String::from("abc");
+        assert_eq!(size_of_val(value), 3 * POINTER_BYTE_SIZE + 1 * 3);
+    }
+
+    #[test]
+    fn test_size_of_val_with_helper() {
+        use std::collections::BTreeSet;
+        use std::sync::Arc;
+
+        struct Cache {
+            entries: Vec<Arc<String>>,
+        }
+
+        impl MemoryUsage for Cache {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                std::mem::size_of_val(self) + self.entries.size_of_val(tracker)
+                    - std::mem::size_of_val(&self.entries)
+            }
+        }
+
+        let shared = Arc::new(String::from("shared"));
+        let first = Cache {
+            entries: vec![shared.clone()],
+        };
+        let second = Cache {
+            entries: vec![shared.clone(), shared],
+        };
+
+        // `Arc` counters and `String`.
+        let shared_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 6;
+
+        let mut tracker = BTreeSet::new();
+        let first_size = size_of_val_with(&first, &mut tracker);
+        let second_size = size_of_val_with(&second, &mut tracker);
+
+        assert_eq!(first_size, size_of_val(&first));
+        assert_eq!(
+            first_size,
+            3 * POINTER_BYTE_SIZE + 1 * POINTER_BYTE_SIZE + shared_size
+        );
+        assert_eq!(second_size, 3 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE);
+        assert_eq!(second_size + shared_size, size_of_val(&second));
+
+        // Unsized values.
+        let mut tracker = BTreeSet::new();
+        assert_eq!(
+            size_of_val_with("abc", &mut tracker),
+            size_of_val(&"abc") - 2 * POINTER_BYTE_SIZE
+        );
+        assert_eq!(size_of_val_with(&[1u16, 2][..], &mut tracker), 2 * 2);
+
+        let value: &dyn MemoryUsage = &String::from("abc");
+        assert_eq!(
+            size_of_val_with(value, &mut tracker),
+            3 * POINTER_BYTE_SIZE + 1 * 3
+        );
+    }
+
+    #[test]
+    fn test_size_of_val_bounded_helper() {
+        let value = vec![vec![0u8; 100]; 100_000];
+        let exact = size_of_val(&value);
+
+        assert_eq!(
+            size_of_val_bounded(&value, Budget::default()),
+            SizeEstimate::Exact(exact)
+        );
+
+        // The outer vector, then 9 inner vectors. The remaining inner
+        // vectors are counted by their shallow size.
+        let estimate = size_of_val_bounded(
+            &value,
+            Budget {
+                max_nodes: Some(10),
+                max_bytes: None,
+            },
+        );
+        assert_eq!(
+            estimate,
+            SizeEstimate::AtLeast(
+                3 * POINTER_BYTE_SIZE + 100_000 * 3 * POINTER_BYTE_SIZE + 9 * 100
+            )
+        );
+
+        let estimate = size_of_val_bounded(
+            &value,
+            Budget {
+                max_nodes: None,
+                max_bytes: Some(100_000 * 3 * POINTER_BYTE_SIZE + 1000),
+            },
+        );
+        assert!(!estimate.is_exact());
+        assert!(estimate.bytes() < exact);
+
+        // Pointers stop too: the first `Box` and its vector are
+        // visited.
+        let value = (Box::new(vec![1u8; 10]), Box::new(vec![2u8; 10]));
+        let estimate = size_of_val_bounded(
+            &value,
+            Budget {
+                max_nodes: Some(2),
+                max_bytes: None,
+            },
+        );
+        assert_eq!(
+            estimate,
+            SizeEstimate::AtLeast(2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 10)
+        );
+
+        // A traversal ending exactly at the budget is complete: the
+        // buffer, then the 2 boxes.
+        let value = vec![Box::new(1u64), Box::new(2u64)];
+        let estimate = size_of_val_bounded(
+            &value,
+            Budget {
+                max_nodes: Some(3),
+                max_bytes: None,
+            },
+        );
+        assert_eq!(estimate, SizeEstimate::Exact(size_of_val(&value)));
+
+        // All the collections stop.
+        let value = (0..100)
+            .map(|nth| (nth, vec![0u8; 100]))
+            .collect::<std::collections::BTreeMap<u32, _>>();
+        let estimate = size_of_val_bounded(
+            &value,
+            Budget {
+                max_nodes: Some(10),
+                max_bytes: None,
+            },
+        );
+        assert!(!estimate.is_exact());
+        assert_eq!(estimate.bytes(), size_of_val(&value) - 91 * 100);
+    }
+
+    #[test]
+    fn test_measure_with_helper() {
+        use std::collections::{HashMap, VecDeque};
+
+        let options = |collections, include_allocator_overhead| MeasureOptions {
+            policy: SizePolicy {
+                collections,
+                include_allocator_overhead,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        };
+        let capacity = options(CollectionPolicy::Capacity, false);
+        let len = options(CollectionPolicy::Len, false);
+
+        let mut vec = Vec::<u32>::with_capacity(1024);
+        vec.extend([1, 2, 3]);
+        let vec_size = 3 * POINTER_BYTE_SIZE;
+
+        assert_eq!(MeasureOptions::default().policy, capacity.policy);
+        assert_eq!(
+            measure_with(&vec, capacity).total(),
+            vec_size + 4 * vec.capacity()
+        );
+        assert_eq!(measure_with(&vec, len).total(), vec_size + 4 * 3);
+        assert_eq!(
+            measure_with(&vec, len),
+            MemorySize {
+                stack: vec_size,
+                heap: 4 * 3
+            }
+        );
+        assert_eq!(
+            measure_with(&vec, options(CollectionPolicy::Len, true)).total(),
+            vec_size + (4 * 3usize).next_multiple_of(2 * POINTER_BYTE_SIZE)
+        );
+        // `size_of_val` doesn't change.
+        assert_eq!(size_of_val(&vec), vec_size + 4 * 3);
+
+        let mut string = String::with_capacity(1024);
+        string.push_str("abc");
+
+        assert_eq!(
+            measure_with(&string, capacity).total(),
+            3 * POINTER_BYTE_SIZE + string.capacity()
+        );
+        assert_eq!(measure_with(&string, len).total(), size_of_val(&string));
+
+        let mut deque = VecDeque::<u32>::with_capacity(1024);
+        deque.extend([1, 2, 3]);
+
+        assert_eq!(measure_with(&deque, capacity).total(), size_of_val(&deque));
+        assert_eq!(
+            measure_with(&deque, len).total(),
+            mem::size_of_val(&deque) + 4 * 3
+        );
+
+        let mut map = HashMap::<u32, u32>::with_capacity(1024);
+        map.extend([(1, 1), (2, 2), (3, 3)]);
+
+        assert_eq!(
+            measure_with(&map, capacity).total(),
+            mem::size_of_val(&map) + 8 * map.capacity()
+        );
+        assert_eq!(measure_with(&map, len).total(), size_of_val(&map));
+
+        // The policy applies to nested collections too.
+        let nested = Box::new(vec![vec]);
+        assert_eq!(
+            measure_with(&nested, capacity).total(),
+            POINTER_BYTE_SIZE + 2 * vec_size + 4 * nested[0].capacity()
+        );
+    }
+
+    #[test]
+    fn test_measure_with_default_options() {
+        use std::collections::VecDeque;
+
+        let mut vec = Vec::<u32>::with_capacity(1024);
+        vec.extend([1, 2, 3]);
+
+        // `measure` counts the length of a `Vec`, `measure_with` its
+        // capacity by default.
+        assert_eq!(measure(&vec).heap, 4 * 3);
+        assert_eq!(
+            measure_with(&vec, MeasureOptions::default()).heap,
+            4 * vec.capacity()
+        );
+
+        // Both count the capacity of a `VecDeque`.
+        let mut deque = VecDeque::<u32>::with_capacity(1024);
+        deque.extend([1, 2, 3]);
+        assert_eq!(
+            measure(&deque),
+            measure_with(&deque, MeasureOptions::default())
+        );
+        assert_eq!(measure(&deque).heap, 4 * deque.capacity());
+    }
+
+    #[test]
+    fn test_measure_with_allocation_model() {
+        use std::sync::Arc;
+
+        let boxes = (0..1000).map(|nth| Box::new(nth as u8)).collect::<Vec<_>>();
+        let buffer = 1000 * POINTER_BYTE_SIZE;
+        let options = |allocation_model| MeasureOptions {
+            policy: SizePolicy {
+                collections: CollectionPolicy::Len,
+                include_allocator_overhead: true,
+                ..SizePolicy::default()
+            },
+            allocation_model,
+        };
+
+        // Off by default.
+        assert_eq!(
+            measure_with(&boxes, MeasureOptions::default()).total(),
+            3 * POINTER_BYTE_SIZE + buffer + 1000 * 1
+        );
+        assert_eq!(
+            measure_with(&boxes, options(&SystemMallocModel)).total(),
+            3 * POINTER_BYTE_SIZE + buffer + 1000 * 2 * POINTER_BYTE_SIZE
+        );
+        assert_eq!(
+            measure_with(&boxes, options(&JemallocModel)).total(),
+            3 * POINTER_BYTE_SIZE + JemallocModel.allocation_size(buffer) + 1000 * 8
+        );
+
+        // The counters of an `Arc` are part of its allocation.
+        let value = Arc::new(1u8);
+        assert_eq!(
+            measure_with(&value, options(&JemallocModel)).total(),
+            POINTER_BYTE_SIZE + JemallocModel.allocation_size(2 * POINTER_BYTE_SIZE + 1)
+        );
+
+        let value = String::from("abc");
+        assert_eq!(
+            measure_with(&value, options(&JemallocModel)).total(),
+            3 * POINTER_BYTE_SIZE + 8
+        );
+    }
+
+    #[test]
+    fn test_measure_with_amortized_sharing() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[allow(dead_code)]
+        struct Owner {
+            id: u32,
+            data: Arc<Vec<u8>>,
+        }
+
+        impl MemoryUsage for Owner {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self) + heap_size_of_val_with(&self.data, tracker)
+            }
+        }
+
+        let amortized = MeasureOptions {
+            policy: SizePolicy {
+                shared: SharedPolicy::Amortized,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        };
+
+        let data = Arc::new(vec![0u8; 1001]);
+        let owners = (0..4)
+            .map(|id| Owner {
+                id,
+                data: Arc::clone(&data),
+            })
+            .collect::<Vec<_>>();
+        drop(data);
+
+        let total = measure_with(&owners, MeasureOptions::default()).total();
+        let shares = owners
+            .iter()
+            .map(|owner| measure_with(owner, amortized).total())
+            .collect::<Vec<_>>();
+
+        // 1001 bytes aren't divisible by 4.
+        let sum = shares.iter().sum::<usize>() + 3 * POINTER_BYTE_SIZE;
+        assert!(sum <= total && total - sum < 4, "{} vs. {}", sum, total);
+        assert!(shares.iter().all(|&share| share == shares[0]));
+
+        // An owner measured alone is charged the whole value.
+        assert!(measure_with(&owners[0], MeasureOptions::default()).total() > 3 * shares[0]);
+
+        // Clones held by the same value are charged one share.
+        let value = Rc::new(0u64);
+        let clones = (Rc::clone(&value), value);
+        assert_eq!(
+            measure_with(&clones, amortized).total(),
+            2 * POINTER_BYTE_SIZE + (2 * POINTER_BYTE_SIZE + 8) / 2
+        );
+    }
+
+    #[test]
+    fn test_measure_with_owned_only() {
+        use std::borrow::Cow;
+
+        static TABLE: [u64; 512] = [0; 512];
+
+        struct Entry {
+            kind: &'static str,
+            table: &'static [u64],
+            config: Cow<'static, str>,
+            name: String,
+        }
+
+        impl MemoryUsage for Entry {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+                    + heap_size_of_val_with(&self.kind, tracker)
+                    + heap_size_of_val_with(&self.table, tracker)
+                    + heap_size_of_val_with(&self.config, tracker)
+                    + heap_size_of_val_with(&self.name, tracker)
+            }
+        }
+
+        let owned_only = MeasureOptions {
+            policy: SizePolicy {
+                owned_only: true,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        };
+
+        let entry = Entry {
+            kind: "a long-lived description of the kind of the entry",
+            table: &TABLE,
+            config: Cow::Borrowed("verbose"),
+            name: String::from("abc"),
+        };
+
+        assert_eq!(
+            measure_with(&entry, owned_only).total(),
+            mem::size_of::<Entry>() + entry.name.capacity()
+        );
+        assert_eq!(
+            measure_with(&entry, MeasureOptions::default()).total(),
+            mem::size_of::<Entry>() + entry.kind.len() + 8 * 512 + 7 + entry.name.capacity()
+        );
+
+        // Owning pointers are still followed.
+        let value = (Box::new(1u64), &TABLE);
+        assert_eq!(
+            measure_with(&value, owned_only).total(),
+            2 * POINTER_BYTE_SIZE + 8
+        );
+    }
+
+    #[test]
+    fn test_heap_size_of_val_helper() {
+        use std::collections::BTreeSet;
+        use std::sync::Arc;
+
+        assert_eq!(heap_size_of_val(&42u64), 0);
+        assert_eq!(heap_size_of_val(&vec![0u8; 100]), 100);
+        assert_eq!(heap_size_of_val("abc"), 0);
+        assert_eq!(heap_size_of_val(&"abc"), 3);
+
+        let value: &dyn MemoryUsage = &String::from("abc");
+        assert_eq!(heap_size_of_val(value), 3);
+
+        // Less than the shallow size.
+        struct Lying(#[allow(dead_code)] [u8; 16]);
+
+        impl MemoryUsage for Lying {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                0
+            }
+        }
+
+        assert_eq!(heap_size_of_val(&Lying([0; 16])), 0);
+
+        let shared = Arc::new(1u32);
+        let mut tracker = BTreeSet::new();
+        assert_eq!(
+            heap_size_of_val_with(&shared, &mut tracker),
+            2 * POINTER_BYTE_SIZE + 4
+        );
+        assert_eq!(heap_size_of_val_with(&shared, &mut tracker), 0);
+    }
+
+    #[test]
+    fn test_measure_helper() {
+        #[allow(dead_code)]
+        enum Inline {
+            Empty,
+            Payload([u64; 8]),
+        }
+
+        #[allow(dead_code)]
+        enum Boxed {
+            Empty,
+            Payload(Box<[u64; 8]>),
+        }
+
+        impl MemoryUsage for Inline {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+            }
+        }
+
+        impl MemoryUsage for Boxed {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+                    + match self {
+                        Boxed::Empty => 0,
+                        Boxed::Payload(payload) => heap_size_of_val_with(payload, tracker),
+                    }
+            }
+        }
+
+        let inline = [Inline::Empty, Inline::Empty, Inline::Payload([0; 8])];
+        let boxed = [Boxed::Empty, Boxed::Empty, Boxed::Payload(Box::new([0; 8]))];
+
+        let inline_size: MemorySize = inline.iter().map(measure).sum();
+        let boxed_size: MemorySize = boxed.iter().map(measure).sum();
+
+        assert_eq!(
+            inline_size,
+            MemorySize {
+                stack: 3 * mem::size_of::<Inline>(),
+                heap: 0
+            }
+        );
+        assert_eq!(
+            boxed_size,
+            MemorySize {
+                stack: 3 * POINTER_BYTE_SIZE,
+                heap: 8 * 8
+            }
+        );
+        assert!(boxed_size.total() < inline_size.total());
+        assert_eq!(measure(&boxed[2]).total(), size_of_val(&boxed[2]));
     }
 }
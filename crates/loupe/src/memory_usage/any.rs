@@ -2,14 +2,34 @@
 use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
 use crate::{MemoryUsage, MemoryUsageTracker};
 use std::any::Any;
+use std::error::Error;
 use std::mem;
 
-impl MemoryUsage for dyn Any {
-    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-    }
+macro_rules! impl_memory_usage_for_trait_object {
+    ( $( $type:ty ),+ $(,)* ) => {
+        $(
+            /// The concrete value is unknown: only its shallow size, as
+            /// given by the vtable, is counted.
+            impl MemoryUsage for $type {
+                fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                    mem::size_of_val(self)
+                }
+            }
+        )+
+    };
 }
 
+impl_memory_usage_for_trait_object!(
+    dyn Any,
+    dyn Any + Send,
+    dyn Any + Sync,
+    dyn Any + Send + Sync,
+    dyn Error + '_,
+    dyn Error + Send + '_,
+    dyn Error + Sync + '_,
+    dyn Error + Send + Sync + '_,
+);
+
 #[cfg(test)]
 mod test_any_types {
     use super::*;
@@ -19,4 +39,41 @@ mod test_any_types {
         let b: Box<dyn Any> = Box::new(1i8);
         assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 1);
     }
+
+    #[test]
+    fn test_boxed_any_send_sync() {
+        let b: Box<dyn Any + Send> = Box::new([1u8; 32]);
+        assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 32);
+
+        let b: Box<dyn Any + Send + Sync> = Box::new(String::from("abc"));
+        assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE);
+    }
+
+    #[test]
+    fn test_boxed_error() {
+        #[derive(Debug)]
+        struct LargeError {
+            _context: [u8; 128],
+            _message: String,
+        }
+
+        impl std::fmt::Display for LargeError {
+            fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "large error")
+            }
+        }
+
+        impl Error for LargeError {}
+
+        let error = || LargeError {
+            _context: [0; 128],
+            _message: String::from("the heap of the message is not traversed"),
+        };
+
+        let b: Box<dyn Error> = Box::new(error());
+        assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 128 + 3 * POINTER_BYTE_SIZE);
+
+        let b: Box<dyn Error + Send + Sync> = Box::new(error());
+        assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 128 + 3 * POINTER_BYTE_SIZE);
+    }
 }
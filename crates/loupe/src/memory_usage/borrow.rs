@@ -0,0 +1,112 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{policy::follows_references, MemoryUsage, MemoryUsageTracker};
+use std::borrow::{Cow, ToOwned};
+use std::mem;
+use std::ptr;
+
+impl<B> MemoryUsage for Cow<'_, B>
+where
+    B: ToOwned + MemoryUsage + ?Sized,
+    B::Owned: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + match self {
+                Cow::Borrowed(borrowed) => {
+                    if follows_references(tracker)
+                        && tracker.track(ptr::from_ref::<B>(*borrowed).cast())
+                    {
+                        (**borrowed).size_of_val(tracker)
+                    } else {
+                        0
+                    }
+                }
+
+                Cow::Owned(owned) => crate::heap_size_of_val_with(owned, tracker),
+            }
+    }
+}
+
+#[cfg(test)]
+mod test_borrow_types {
+    use super::*;
+    use crate::policy::PolicyTracker;
+    use crate::{CollectionPolicy, MeasureOptions, SizePolicy};
+    use std::collections::BTreeSet;
+
+    fn policy(collections: CollectionPolicy) -> PolicyTracker {
+        PolicyTracker::new(MeasureOptions {
+            policy: SizePolicy {
+                collections,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_cow_borrowed() {
+        let bytes: &[u8] = &[1, 2, 3];
+        let cow: Cow<[u8]> = Cow::Borrowed(bytes);
+        let cow_size = mem::size_of_val(&cow);
+        assert_size_of_val_eq!(cow, cow_size + 1 * 3);
+
+        // The borrowed data is charged once.
+        let mut tracker = BTreeSet::new();
+        assert_size_of_val_eq!(cow, cow_size + 1 * 3, &mut tracker);
+        assert_size_of_val_eq!(cow, cow_size + 0, &mut tracker);
+    }
+
+    #[test]
+    fn test_cow_owned() {
+        let cow: Cow<[u8]> = Cow::Owned(vec![1, 2, 3, 4]);
+        assert_size_of_val_eq!(cow, mem::size_of_val(&cow) + 1 * 4);
+
+        let mut owned = Vec::with_capacity(64);
+        owned.extend_from_slice(&[1, 2, 3]);
+        let cow: Cow<[u8]> = Cow::Owned(owned);
+
+        // With the policy of the tracker, like the owned value itself.
+        assert_size_of_val_eq!(cow, mem::size_of_val(&cow) + 1 * 3);
+        assert_size_of_val_eq!(
+            cow,
+            mem::size_of_val(&cow) + 1 * 3,
+            &mut policy(CollectionPolicy::Len)
+        );
+        assert_size_of_val_eq!(
+            cow,
+            mem::size_of_val(&cow) + 1 * 64,
+            &mut policy(CollectionPolicy::Capacity)
+        );
+
+        let cow: Cow<[u8]> = Cow::Owned(vec![]);
+        assert_size_of_val_eq!(cow, mem::size_of_val(&cow) + 1 * 0);
+    }
+
+    #[test]
+    fn test_cow_owned_contents() {
+        let mut strings = vec![String::with_capacity(64), String::with_capacity(64)];
+        strings[0].push_str("abc");
+        let cow: Cow<[String]> = Cow::Owned(strings);
+        let owned = match &cow {
+            Cow::Owned(owned) => owned,
+            Cow::Borrowed(_) => unreachable!(),
+        };
+
+        // The strings are counted as if they weren't in a `Cow`.
+        for &collections in &[CollectionPolicy::Len, CollectionPolicy::Capacity] {
+            assert_size_of_val_eq!(
+                cow,
+                mem::size_of_val(&cow)
+                    + crate::heap_size_of_val_with(owned, &mut policy(collections)),
+                &mut policy(collections)
+            );
+        }
+
+        assert_size_of_val_eq!(
+            cow,
+            mem::size_of_val(&cow) + 2 * mem::size_of::<String>() + 1 * 3
+        );
+    }
+}
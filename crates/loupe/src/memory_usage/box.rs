@@ -1,7 +1,13 @@
 #[cfg(test)]
 use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
-use crate::{MemoryUsage, MemoryUsageTracker};
+use crate::{
+    memory_usage::size_of_pointee, policy::allocation_size, MemoryUsage, MemoryUsageTracker,
+};
+use std::any;
 use std::mem;
+use std::ptr;
+#[cfg(test)]
+use std::ptr::NonNull;
 
 impl<T> MemoryUsage for Box<T>
 where
@@ -9,13 +15,14 @@ where
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
         let reference = self.as_ref();
+        let bytes = allocation_size(tracker, mem::size_of_val(reference));
 
         mem::size_of_val(self)
-            + if tracker.track(reference as *const _ as *const ()) {
-                reference.size_of_val(tracker)
-            } else {
-                0
-            }
+            + size_of_pointee(tracker, ptr::from_ref::<T>(reference), bytes, |tracker| {
+                tracker.record(any::type_name::<Self>(), bytes);
+
+                bytes - mem::size_of_val(reference) + reference.size_of_val(tracker)
+            })
     }
 }
 
@@ -49,4 +56,68 @@ mod test_box_types {
         let b: Box<[u8]> = vec![1, 2, 3].into_boxed_slice();
         assert_size_of_val_eq!(b, 2 * POINTER_BYTE_SIZE + 1 * 3);
     }
+
+    #[test]
+    fn test_box_shared_allocation() {
+        #[allow(dead_code)]
+        struct Value([u8; 16]);
+
+        impl MemoryUsage for Value {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+            }
+        }
+
+        // A value owned by a `Box`, and aliased by a pointer, which
+        // registers the allocation like a reference.
+        struct Aliased {
+            boxed: Box<Value>,
+            alias: NonNull<Value>,
+            alias_first: bool,
+        }
+
+        impl Aliased {
+            fn size_of_alias(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                if tracker.track(self.alias.as_ptr().cast()) {
+                    mem::size_of::<Value>()
+                } else {
+                    0
+                }
+            }
+        }
+
+        impl MemoryUsage for Aliased {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                let size = mem::size_of_val(self);
+
+                if self.alias_first {
+                    size + self.size_of_alias(tracker)
+                        + crate::heap_size_of_val_with(&self.boxed, tracker)
+                } else {
+                    size + crate::heap_size_of_val_with(&self.boxed, tracker)
+                        + self.size_of_alias(tracker)
+                }
+            }
+        }
+
+        for &alias_first in &[false, true] {
+            let boxed = Box::new(Value([1; 16]));
+            let alias = NonNull::from(&*boxed);
+            let value = Aliased {
+                boxed,
+                alias,
+                alias_first,
+            };
+
+            // The allocation is counted once, whichever path comes
+            // first.
+            assert_size_of_val_eq!(value, mem::size_of_val(&value) + 16);
+        }
+
+        let b: Box<i32> = Box::new(1);
+        assert_size_of_val_eq!(
+            (&*b, &b),
+            2 * POINTER_BYTE_SIZE + 4 + POINTER_BYTE_SIZE + 0, /* already tracked by `&i32` */
+        );
+    }
 }
@@ -1,13 +1,16 @@
 #[cfg(test)]
 use crate::assert_size_of_val_eq;
-use crate::{MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
-use std::cell::{RefCell, UnsafeCell};
+use crate::{policy::follows_references, MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
+#[rustversion::since(1.94)]
+use std::cell::LazyCell;
+use std::cell::{OnceCell, Ref, RefCell, RefMut, UnsafeCell};
 use std::mem;
+use std::ptr;
 
 impl<T> MemoryUsage for UnsafeCell<T> {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
         mem::size_of_val(self)
-            + if tracker.track(self.get() as *const ()) {
+            + if tracker.track(self.get().cast()) {
                 POINTER_BYTE_SIZE
             } else {
                 0
@@ -22,8 +25,8 @@ where
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
         mem::size_of_val(self)
             + match self.try_borrow() {
-                Ok(borrowed) if tracker.track(self.as_ptr() as *const _ as *const ()) => {
-                    borrowed.size_of_val(tracker)
+                Ok(borrowed) if tracker.track(self.as_ptr().cast()) => {
+                    crate::heap_size_of_val_with(&*borrowed, tracker)
                 }
 
                 _ => 0,
@@ -31,6 +34,63 @@ where
     }
 }
 
+impl<T> MemoryUsage for Ref<'_, T>
+where
+    T: MemoryUsage + ?Sized,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + if follows_references(tracker) && tracker.track(ptr::from_ref::<T>(&**self).cast()) {
+                (**self).size_of_val(tracker)
+            } else {
+                0
+            }
+    }
+}
+
+impl<T> MemoryUsage for RefMut<'_, T>
+where
+    T: MemoryUsage + ?Sized,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + if follows_references(tracker) && tracker.track(ptr::from_ref::<T>(&**self).cast()) {
+                (**self).size_of_val(tracker)
+            } else {
+                0
+            }
+    }
+}
+
+/// Measuring never initializes the cell.
+impl<T> MemoryUsage for OnceCell<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self
+                .get()
+                .map(|value| crate::heap_size_of_val_with(value, tracker))
+                .unwrap_or(0)
+    }
+}
+
+/// Measuring never forces the evaluation: the value is only counted
+/// once it has been materialized.
+#[rustversion::since(1.94)]
+impl<T, F> MemoryUsage for LazyCell<T, F>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + LazyCell::get(self)
+                .map(|value| crate::heap_size_of_val_with(value, tracker))
+                .unwrap_or(0)
+    }
+}
+
 #[cfg(test)]
 mod test_cell_types {
     use super::*;
@@ -62,4 +122,58 @@ mod test_cell_types {
 
         assert_size_of_val_eq!(cell, cell_size + 2);
     }
+
+    #[test]
+    fn test_refcell_bytes() {
+        let cell = RefCell::<Vec<u8>>::new(vec![1, 2, 3]);
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell) + 1 * 3);
+    }
+
+    #[test]
+    fn test_ref() {
+        let cell = RefCell::<Vec<u8>>::new(vec![1, 2, 3]);
+        let borrowed = cell.borrow();
+        let ref_size = mem::size_of_val(&borrowed);
+
+        assert_size_of_val_eq!(borrowed, ref_size + mem::size_of::<Vec<u8>>() + 1 * 3);
+
+        // The guard and the cell point to the same value.
+        let mut tracker = std::collections::BTreeSet::new();
+        assert_size_of_val_eq!(
+            borrowed,
+            ref_size + mem::size_of::<Vec<u8>>() + 1 * 3,
+            &mut tracker
+        );
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell), &mut tracker);
+    }
+
+    #[test]
+    fn test_refmut() {
+        let cell = RefCell::<Vec<u8>>::new(vec![1, 2, 3]);
+        let mut borrowed = cell.borrow_mut();
+        borrowed.push(4);
+        let ref_size = mem::size_of_val(&borrowed);
+
+        assert_size_of_val_eq!(borrowed, ref_size + mem::size_of::<Vec<u8>>() + 1 * 4);
+    }
+
+    #[test]
+    fn test_oncecell() {
+        let cell: OnceCell<Vec<u8>> = OnceCell::new();
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell));
+
+        cell.set(vec![1, 2, 3]).unwrap();
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell) + 1 * 3);
+    }
+
+    #[rustversion::since(1.94)]
+    #[test]
+    fn test_lazycell() {
+        let cell: LazyCell<Vec<u8>> = LazyCell::new(|| vec![1, 2, 3]);
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell));
+        assert!(LazyCell::get(&cell).is_none());
+
+        LazyCell::force(&cell);
+        assert_size_of_val_eq!(cell, mem::size_of_val(&cell) + 1 * 3);
+    }
 }
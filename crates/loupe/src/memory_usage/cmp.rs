@@ -0,0 +1,38 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::cmp::Reverse;
+
+impl<T> MemoryUsage for Reverse<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        self.0.size_of_val(tracker)
+    }
+}
+
+#[cfg(test)]
+mod test_cmp_types {
+    use super::*;
+    use std::collections::BinaryHeap;
+    use std::mem;
+
+    #[test]
+    fn test_reverse() {
+        assert_size_of_val_eq!(Reverse(1u32), 4);
+        assert_size_of_val_eq!(Reverse("abc".to_string()), mem::size_of::<String>() + 1 * 3);
+    }
+
+    #[test]
+    fn test_reverse_in_binaryheap() {
+        let mut heap: BinaryHeap<Reverse<String>> = BinaryHeap::with_capacity(4);
+
+        heap.push(Reverse("abc".to_string()));
+        heap.push(Reverse("de".to_string()));
+        assert_size_of_val_eq!(
+            heap,
+            mem::size_of_val(&heap) + mem::size_of::<String>() * 2 + 1 * 3 + 1 * 2
+        );
+    }
+}
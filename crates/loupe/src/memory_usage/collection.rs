@@ -1,19 +1,33 @@
 #[cfg(test)]
-use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
-use crate::{MemoryUsage, MemoryUsageTracker};
-use std::collections::{BTreeMap, HashMap};
+use crate::{
+    assert_size_of_val_approx, assert_size_of_val_between, assert_size_of_val_eq, POINTER_BYTE_SIZE,
+};
+use crate::{
+    memory_usage::{heap_size_of_values, track_buffer},
+    policy::buffer_size,
+    CollectionPolicy, MemoryUsage, MemoryUsageTracker,
+};
+use std::any;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
 use std::mem;
+use std::ptr;
 
 impl<T> MemoryUsage for Vec<T>
 where
     T: MemoryUsage,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + self
-                .iter()
-                .map(|value| value.size_of_val(tracker))
-                .sum::<usize>()
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<T>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+        track_buffer(tracker, self.as_ptr().cast(), bytes);
+
+        mem::size_of_val(self) + bytes + heap_size_of_values(tracker, self)
     }
 }
 
@@ -62,37 +76,293 @@ mod test_vec_types {
     }
 }
 
-impl<K, V> MemoryUsage for HashMap<K, V>
+impl<T> MemoryUsage for VecDeque<T>
 where
-    K: MemoryUsage,
-    V: MemoryUsage,
+    T: MemoryUsage,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
         mem::size_of_val(self)
-            + self
-                .iter()
-                .map(|(key, value)| key.size_of_val(tracker) + value.size_of_val(tracker))
-                .sum::<usize>()
+            + buffer_size(
+                tracker,
+                CollectionPolicy::Capacity,
+                self.len(),
+                self.capacity(),
+                mem::size_of::<T>(),
+            )
+            + heap_size_of_values(tracker, self)
     }
 }
 
-impl<K, V> MemoryUsage for BTreeMap<K, V>
+#[cfg(test)]
+mod test_vecdeque_types {
+    use super::*;
+
+    #[test]
+    fn test_vecdeque_empty() {
+        let deque: VecDeque<i32> = VecDeque::new();
+        assert_size_of_val_eq!(deque, mem::size_of_val(&deque));
+
+        let deque: VecDeque<i32> = VecDeque::with_capacity(8);
+        assert_size_of_val_eq!(deque, mem::size_of_val(&deque) + 4 * deque.capacity());
+    }
+
+    #[test]
+    fn test_vecdeque() {
+        let mut deque: VecDeque<String> = VecDeque::with_capacity(4);
+        let capacity = deque.capacity();
+
+        deque.push_back("a".to_string());
+        deque.push_back("bc".to_string());
+        assert_size_of_val_eq!(
+            deque,
+            mem::size_of_val(&deque) + mem::size_of::<String>() * capacity + 1 * 1 + 1 * 2
+        );
+    }
+
+    #[test]
+    fn test_vecdeque_wrap_around() {
+        let mut deque: VecDeque<String> = VecDeque::with_capacity(4);
+        let capacity = deque.capacity();
+
+        for _ in 0..capacity {
+            deque.push_back("abc".to_string());
+        }
+
+        // Rotate the head so that the elements are split across the
+        // end and the start of the buffer.
+        for _ in 0..capacity / 2 + 1 {
+            deque.pop_front();
+            deque.push_back("de".to_string());
+        }
+
+        let (front, back) = deque.as_slices();
+        assert!(!front.is_empty() && !back.is_empty());
+        assert_eq!(deque.capacity(), capacity);
+
+        let strings = deque.iter().map(|string| string.len()).sum::<usize>();
+        assert_size_of_val_eq!(
+            deque,
+            mem::size_of_val(&deque) + mem::size_of::<String>() * capacity + 1 * strings
+        );
+    }
+}
+
+impl<T> MemoryUsage for BinaryHeap<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<T>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+        track_buffer(tracker, self.as_slice().as_ptr().cast(), bytes);
+
+        mem::size_of_val(self) + bytes + heap_size_of_values(tracker, self)
+    }
+}
+
+#[cfg(test)]
+mod test_binaryheap_types {
+    use super::*;
+    use crate::policy::PolicyTracker;
+    use crate::{MeasureOptions, SizePolicy};
+
+    fn policy(collections: CollectionPolicy) -> PolicyTracker {
+        PolicyTracker::new(MeasureOptions {
+            policy: SizePolicy {
+                collections,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_binaryheap_empty() {
+        let heap: BinaryHeap<String> = BinaryHeap::new();
+        assert_size_of_val_eq!(heap, mem::size_of_val(&heap));
+    }
+
+    #[test]
+    fn test_binaryheap() {
+        let mut heap: BinaryHeap<String> = BinaryHeap::with_capacity(16);
+        let capacity = heap.capacity();
+
+        heap.push("abc".to_string());
+        heap.push("de".to_string());
+
+        // Like `Vec`, the length without a policy.
+        assert_size_of_val_eq!(
+            heap,
+            mem::size_of_val(&heap) + mem::size_of::<String>() * 2 + 1 * 3 + 1 * 2
+        );
+        assert_size_of_val_eq!(
+            heap,
+            mem::size_of_val(&heap) + mem::size_of::<String>() * 2 + 1 * 3 + 1 * 2,
+            &mut policy(CollectionPolicy::Len)
+        );
+        assert_size_of_val_eq!(
+            heap,
+            mem::size_of_val(&heap) + mem::size_of::<String>() * capacity + 1 * 3 + 1 * 2,
+            &mut policy(CollectionPolicy::Capacity)
+        );
+
+        // The capacity is at least the requested one.
+        let heap: BinaryHeap<u64> = (0..10).collect();
+        assert_size_of_val_between!(
+            heap,
+            mem::size_of_val(&heap) + 8 * 10,
+            mem::size_of_val(&heap) + 8 * 20,
+            &mut policy(CollectionPolicy::Capacity)
+        );
+    }
+}
+
+/// Mirror of a node of `LinkedList`: each element is allocated
+/// separately, next to the pointers to its siblings.
+#[allow(dead_code)]
+struct LinkedListNode<T> {
+    next: *const (),
+    prev: *const (),
+    element: T,
+}
+
+impl<T> MemoryUsage for LinkedList<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let bytes = self.len() * mem::size_of::<LinkedListNode<T>>();
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        mem::size_of_val(self) + bytes + heap_size_of_values(tracker, self)
+    }
+}
+
+#[cfg(test)]
+mod test_linkedlist_types {
+    use super::*;
+
+    #[test]
+    fn test_linkedlist() {
+        let mut list: LinkedList<String> = LinkedList::new();
+        let empty_list_size = mem::size_of_val(&list);
+        assert_size_of_val_eq!(list, empty_list_size);
+
+        list.push_back("abc".to_string());
+        list.push_back("de".to_string());
+        assert_size_of_val_eq!(
+            list,
+            empty_list_size
+                + 2 * (2 * POINTER_BYTE_SIZE + mem::size_of::<String>())
+                + 1 * 3
+                + 1 * 2
+        );
+    }
+
+    #[test]
+    fn test_linkedlist_overhead() {
+        let list: LinkedList<u8> = (0..=255).cycle().take(1000).collect();
+        let vec: Vec<u8> = list.iter().copied().collect();
+
+        // About 2 pointers and the padded `u8` for each node, vs 1 byte,
+        // depending on the layout of the nodes.
+        assert_size_of_val_approx!(
+            list,
+            mem::size_of_val(&list) + 1000 * (2 * POINTER_BYTE_SIZE + POINTER_BYTE_SIZE),
+            10
+        );
+        assert!(crate::size_of_val(&list) > 10 * crate::size_of_val(&vec));
+    }
+}
+
+impl<K, V, S> MemoryUsage for HashMap<K, V, S>
 where
     K: MemoryUsage,
     V: MemoryUsage,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + self
-                .iter()
-                .map(|(key, value)| key.size_of_val(tracker) + value.size_of_val(tracker))
-                .sum::<usize>()
+        // The capacity is the number of entries that fit without
+        // growing the table, which has a few more buckets, and control
+        // bytes.
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<K>() + mem::size_of::<V>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        // The table has no address of its own: it's tracked by the
+        // address of a key.
+        if let Some(key) = self.keys().next() {
+            track_buffer(tracker, ptr::from_ref::<K>(key).cast(), bytes);
+        }
+
+        // The entries are visited in the order of the table, which
+        // only changes when the map is modified: repeated measurements
+        // charge the shared values to the same entries.
+        mem::size_of_val(self) + bytes + heap_size_of_entries(tracker, self)
+    }
+}
+
+/// Same as `heap_size_of_values`, for the entries of a map.
+fn heap_size_of_entries<'a, K, V, I>(tracker: &mut dyn MemoryUsageTracker, entries: I) -> usize
+where
+    K: MemoryUsage + 'a,
+    V: MemoryUsage + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    let mut size = 0;
+
+    for (key, value) in entries {
+        if tracker.should_stop() {
+            break;
+        }
+
+        size += crate::heap_size_of_val_with(key, tracker)
+            + crate::heap_size_of_val_with(value, tracker);
+    }
+
+    size
+}
+
+impl<T, S> MemoryUsage for HashSet<T, S>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        // Counted like `HashMap`.
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            mem::size_of::<T>(),
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        if let Some(value) = self.iter().next() {
+            track_buffer(tracker, ptr::from_ref::<T>(value).cast(), bytes);
+        }
+
+        mem::size_of_val(self) + bytes + heap_size_of_values(tracker, self)
     }
 }
 
 #[cfg(test)]
 mod test_collection_types {
     use super::*;
+    use crate::policy::PolicyTracker;
+    use crate::{MeasureOptions, SizePolicy};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
 
     #[test]
     fn test_hashmap() {
@@ -138,47 +408,223 @@ mod test_collection_types {
         );
     }
 
+    #[test]
+    fn test_hashmap_with_hasher() {
+        let mut hashmap: HashMap<i8, String> = HashMap::new();
+        let mut hashmap_with_hasher: HashMap<i8, String, BuildHasherDefault<DefaultHasher>> =
+            HashMap::default();
+
+        for nth in 0..8 {
+            hashmap.insert(nth, nth.to_string());
+            hashmap_with_hasher.insert(nth, nth.to_string());
+        }
+
+        assert_eq!(
+            crate::size_of_val(&hashmap) - mem::size_of_val(&hashmap),
+            crate::size_of_val(&hashmap_with_hasher) - mem::size_of_val(&hashmap_with_hasher)
+        );
+    }
+
+    #[test]
+    fn test_hashset() {
+        let mut hashset: HashSet<String> = HashSet::with_capacity(64);
+        let empty_hashset_size = mem::size_of_val(&hashset);
+        assert_size_of_val_eq!(hashset, empty_hashset_size);
+
+        hashset.insert("abc".to_string());
+        hashset.insert("de".to_string());
+        assert_size_of_val_eq!(
+            hashset,
+            empty_hashset_size + 2 * mem::size_of::<String>() + 1 * 3 + 1 * 2
+        );
+
+        // The spare capacity is counted with the `Capacity` policy.
+        let capacity = hashset.capacity();
+        assert!(capacity >= 64);
+
+        let mut tracker = PolicyTracker::new(MeasureOptions {
+            policy: SizePolicy {
+                collections: CollectionPolicy::Capacity,
+                ..SizePolicy::default()
+            },
+            ..MeasureOptions::default()
+        });
+        assert_size_of_val_eq!(
+            hashset,
+            empty_hashset_size + capacity * mem::size_of::<String>() + 1 * 3 + 1 * 2,
+            &mut tracker
+        );
+    }
+
+    #[test]
+    fn test_hashset_not_unique() {
+        let one: i32 = 1;
+        let two: i32 = 2;
+
+        let first: HashSet<&i32> = vec![&one, &two].into_iter().collect();
+        let second: HashSet<&i32> = vec![&one].into_iter().collect();
+        let empty_hashset_size = mem::size_of_val(&first);
+
+        let mut tracker = BTreeSet::new();
+        assert_size_of_val_eq!(
+            first,
+            empty_hashset_size + (POINTER_BYTE_SIZE + 4) * 2,
+            &mut tracker
+        );
+        assert_size_of_val_eq!(
+            second,
+            empty_hashset_size + POINTER_BYTE_SIZE + 0, /* already tracked */
+            &mut tracker
+        );
+    }
+}
+
+/// Mirror of a leaf node of `std`'s B-tree: a parent pointer, the
+/// index in the parent, the number of entries, and the keys and
+/// values.
+#[allow(dead_code)]
+struct BTreeLeafNode<K, V> {
+    parent: *const (),
+    parent_index: u16,
+    len: u16,
+    keys: [mem::MaybeUninit<K>; BTREE_CAPACITY],
+    values: [mem::MaybeUninit<V>; BTREE_CAPACITY],
+}
+
+/// Mirror of an internal node of `std`'s B-tree: a leaf node plus the
+/// edges to its children.
+#[allow(dead_code)]
+struct BTreeInternalNode<K, V> {
+    data: BTreeLeafNode<K, V>,
+    edges: [*const (); BTREE_CAPACITY + 1],
+}
+
+/// Maximum number of entries in a B-tree node.
+const BTREE_CAPACITY: usize = 11;
+
+/// Approximate the bytes allocated for the nodes of a B-tree holding
+/// `len` entries.
+///
+/// The model is the following: entries live in leaf nodes that are
+/// assumed to be 2/3 full, which is the average occupancy after
+/// random insertions (bulk-built trees are denser, so this slightly
+/// over-estimates them). Each level of internal nodes has one node
+/// per group of children, with the same occupancy. A tree with at
+/// most `BTREE_CAPACITY` entries is a single leaf.
+fn btree_nodes_byte_size<K, V>(len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+
+    if len <= BTREE_CAPACITY {
+        return mem::size_of::<BTreeLeafNode<K, V>>();
+    }
+
+    let occupancy = BTREE_CAPACITY * 2 / 3;
+    let leaves = len.div_ceil(occupancy);
+    let mut internals = 0;
+    let mut level = leaves;
+
+    while level > 1 {
+        level = level.div_ceil(occupancy + 1);
+        internals += level;
+    }
+
+    leaves * mem::size_of::<BTreeLeafNode<K, V>>()
+        + internals * mem::size_of::<BTreeInternalNode<K, V>>()
+}
+
+impl<K, V> MemoryUsage for BTreeMap<K, V>
+where
+    K: MemoryUsage,
+    V: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let bytes = btree_nodes_byte_size::<K, V>(self.len());
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        mem::size_of_val(self) + bytes + heap_size_of_entries(tracker, self)
+    }
+}
+
+impl<T> MemoryUsage for BTreeSet<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let bytes = btree_nodes_byte_size::<T, ()>(self.len());
+        tracker.record(any::type_name::<Self>(), bytes);
+
+        mem::size_of_val(self) + bytes + heap_size_of_values(tracker, self)
+    }
+}
+
+#[cfg(test)]
+mod test_btree_types {
+    use super::*;
+
     #[test]
     fn test_btreemap() {
+        let leaf_size = mem::size_of::<BTreeLeafNode<i8, i32>>();
+
         let mut btreemap: BTreeMap<i8, i32> = BTreeMap::new();
         let empty_btreemap_size = mem::size_of_val(&btreemap);
-        assert_size_of_val_eq!(btreemap, empty_btreemap_size + 1 * 0 + 4 * 0);
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size);
 
         btreemap.insert(1, 1);
-        assert_size_of_val_eq!(btreemap, empty_btreemap_size + 1 * 1 + 4 * 1);
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size + leaf_size);
 
         btreemap.insert(2, 2);
-        assert_size_of_val_eq!(btreemap, empty_btreemap_size + 1 * 2 + 4 * 2);
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size + leaf_size);
     }
 
     #[test]
     fn test_btreemap_not_unique() {
+        let leaf_size = mem::size_of::<BTreeLeafNode<i8, &i32>>();
+
         let mut btreemap: BTreeMap<i8, &i32> = BTreeMap::new();
         let empty_btreemap_size = mem::size_of_val(&btreemap);
-        assert_size_of_val_eq!(
-            btreemap,
-            empty_btreemap_size + 1 * 0 + (POINTER_BYTE_SIZE + 4) * 0
-        );
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size);
 
         let one: i32 = 1;
         btreemap.insert(1, &one);
-        assert_size_of_val_eq!(
-            btreemap,
-            empty_btreemap_size + 1 * 1 + (POINTER_BYTE_SIZE + 4) * 1
-        );
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size + leaf_size + 4 * 1);
 
         let two: i32 = 2;
         btreemap.insert(2, &two);
-        assert_size_of_val_eq!(
-            btreemap,
-            empty_btreemap_size + 1 * 2 + (POINTER_BYTE_SIZE + 4) * 2
-        );
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size + leaf_size + 4 * 2);
 
         // Push a reference to an item that already exists!
         btreemap.insert(3, &one);
-        assert_size_of_val_eq!(
-            btreemap,
-            empty_btreemap_size + 1 * 3 + (POINTER_BYTE_SIZE + 4) * 2 + POINTER_BYTE_SIZE + 0 /* no i32 */
-        );
+        assert_size_of_val_eq!(btreemap, empty_btreemap_size + leaf_size + 4 * 2);
+    }
+
+    #[test]
+    fn test_btreemap_large() {
+        let btreemap: BTreeMap<u64, u64> = (0..10_000).map(|nth| (nth, nth)).collect();
+        let size = crate::size_of_val(&btreemap);
+        let entries = 10_000 * (8 + 8);
+
+        // Nodes hold up to 11 entries plus a header, so the overhead
+        // must be visible, without being absurd.
+        assert!(size > entries + entries / 10, "{} is too small", size);
+        assert!(size < 3 * entries, "{} is too large", size);
+    }
+
+    #[test]
+    fn test_btreeset() {
+        let leaf_size = mem::size_of::<BTreeLeafNode<String, ()>>();
+
+        let mut btreeset: BTreeSet<String> = BTreeSet::new();
+        let empty_btreeset_size = mem::size_of_val(&btreeset);
+        assert_size_of_val_eq!(btreeset, empty_btreeset_size);
+
+        btreeset.insert("abc".to_string());
+        btreeset.insert("de".to_string());
+        assert_size_of_val_eq!(btreeset, empty_btreeset_size + leaf_size + 1 * 3 + 1 * 2);
+
+        let btreeset: BTreeSet<u32> = (0..1_000).collect();
+        let size = crate::size_of_val(&btreeset);
+        assert!(size > 1_000 * 4 && size < 3 * 1_000 * 4);
     }
 }
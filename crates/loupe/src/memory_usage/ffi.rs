@@ -0,0 +1,106 @@
+#[cfg(test)]
+use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::mem;
+
+impl MemoryUsage for CStr {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        self.to_bytes_with_nul().len()
+    }
+}
+
+impl MemoryUsage for CString {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.as_bytes_with_nul().len()
+    }
+}
+
+impl MemoryUsage for OsStr {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        self.as_encoded_bytes().len()
+    }
+}
+
+impl MemoryUsage for OsString {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.capacity()
+    }
+}
+
+#[cfg(test)]
+mod test_ffi_types {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cstring() {
+        let string = CString::new("").unwrap();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 1 /* nul */);
+
+        let string = CString::new("abc").unwrap();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3 + 1 /* nul */);
+
+        let string = CString::new("…").unwrap();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3 + 1 /* nul */);
+    }
+
+    #[test]
+    fn test_cstr() {
+        let string: Box<CStr> = CString::new("abc").unwrap().into_boxed_c_str();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3 + 1 /* nul */);
+
+        let string: Arc<CStr> = Arc::from(CString::new("abc").unwrap());
+        assert_size_of_val_eq!(
+            string,
+            mem::size_of_val(&string) + 2 * POINTER_BYTE_SIZE /* counters */ + 1 * 3 + 1 /* nul */
+        );
+    }
+
+    #[test]
+    fn test_osstr() {
+        let string: Box<OsStr> = OsStr::new("").into();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 0);
+
+        let string: Box<OsStr> = OsStr::new("abc").into();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3);
+
+        let string: Box<OsStr> = OsStr::new("…").into();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3);
+    }
+
+    #[test]
+    fn test_osstring() {
+        let string = OsString::new();
+        let empty_string_size = mem::size_of_val(&string);
+        assert_size_of_val_eq!(string, empty_string_size);
+
+        let mut string = OsString::with_capacity(8);
+        string.push("…");
+        assert_size_of_val_eq!(string, empty_string_size + 1 * 8);
+
+        let string = OsString::from("abc");
+        assert_size_of_val_eq!(string, empty_string_size + 1 * string.capacity());
+    }
+
+    #[test]
+    fn test_environment() {
+        use std::collections::HashMap;
+
+        let environment: HashMap<OsString, OsString> = vec![
+            (OsString::from("HOME"), OsString::from("/home/loupe")),
+            (OsString::from("LANG"), OsString::from("fr_FR.UTF-8")),
+        ]
+        .into_iter()
+        .collect();
+        let strings = environment
+            .iter()
+            .map(|(key, value)| key.capacity() + value.capacity())
+            .sum::<usize>();
+
+        assert_size_of_val_eq!(
+            environment,
+            mem::size_of_val(&environment) + 4 * mem::size_of::<OsString>() + 1 * strings
+        );
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
+use std::mem;
+
+// Closures are opaque: only their captured environment can be measured,
+// and only shallowly, through the vtable.
+//
+// Signatures with references, like `dyn Fn(&str)` which is `dyn for<'a>
+// Fn(&'a str)`, aren't covered: rustc considers their impls to overlap
+// with the generic ones, which is only accepted with the future
+// incompatible `coherence_leak_check` lint. Such fields are measured
+// with `#[loupe(with = "...")]`.
+macro_rules! impl_memory_usage_for_closure {
+    ( $arguments:tt $( $trait:ident ),+ $(,)* ) => {
+        $(
+            impl_memory_usage_for_closure!(@impl $arguments $trait);
+            impl_memory_usage_for_closure!(@impl $arguments $trait + Send);
+            impl_memory_usage_for_closure!(@impl $arguments $trait + Send + Sync);
+        )+
+    };
+
+    (@impl [ $( $arguments:ident ),* ] $trait:ident $( + $marker:ident )* ) => {
+        impl< $( $arguments , )* R > MemoryUsage for dyn $trait( $( $arguments ),* ) -> R $( + $marker )* + '_ {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+            }
+        }
+    };
+}
+
+macro_rules! impl_memory_usage_for_function {
+    () => {
+        impl_memory_usage_for_function!(@impl);
+    };
+
+    ( $first_type:ident $( , $types:ident )* $(,)* ) => {
+        impl_memory_usage_for_function!(@impl $first_type $( , $types )* );
+        impl_memory_usage_for_function!( $( $types ),* );
+    };
+
+    (@impl $( $types:ident ),* ) => {
+        impl< $( $types , )* R > MemoryUsage for fn( $( $types ),* ) -> R {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                POINTER_BYTE_SIZE
+            }
+        }
+
+        impl_memory_usage_for_closure!([ $( $types ),* ] Fn, FnMut, FnOnce);
+    };
+}
+
+impl_memory_usage_for_function!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+#[cfg(test)]
+mod test_function_types {
+    use super::*;
+
+    fn increment(x: u32) -> u32 {
+        x + 1
+    }
+
+    fn noop() {}
+
+    #[test]
+    fn test_function_pointer() {
+        let function: fn(u32) -> u32 = increment;
+        assert_size_of_val_eq!(function, POINTER_BYTE_SIZE);
+
+        let function: Option<fn()> = Some(noop);
+        assert_size_of_val_eq!(function, POINTER_BYTE_SIZE);
+
+        let function: Option<fn()> = None;
+        assert_size_of_val_eq!(function, POINTER_BYTE_SIZE);
+    }
+
+    #[test]
+    fn test_boxed_closure() {
+        let closure: Box<dyn Fn(u32) -> u32> = Box::new(increment);
+        assert_size_of_val_eq!(closure, 2 * POINTER_BYTE_SIZE);
+
+        let table = [1u8; 256];
+        let closure: Box<dyn Fn(usize) -> u8 + Send> = Box::new(move |nth| table[nth]);
+        assert_size_of_val_eq!(closure, 2 * POINTER_BYTE_SIZE + 256);
+
+        let mut counter = 0u64;
+        let closure: Box<dyn FnMut() -> u64 + Send + Sync> = Box::new(move || {
+            counter += 1;
+            counter
+        });
+        assert_size_of_val_eq!(closure, 2 * POINTER_BYTE_SIZE + 8);
+
+        let message = String::from("abc");
+        let closure: Box<dyn FnOnce() -> String> = Box::new(move || message);
+        assert_size_of_val_eq!(closure, 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE);
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
+use std::io::{self, BufReader, BufWriter, Cursor, Write};
+use std::mem;
+
+impl<T> MemoryUsage for Cursor<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + crate::heap_size_of_val_with(self.get_ref(), tracker)
+    }
+}
+
+/// The internal buffer is allocated upfront, so its whole capacity is
+/// counted, whatever is buffered.
+impl<R> MemoryUsage for BufReader<R>
+where
+    R: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self.capacity()
+            + crate::heap_size_of_val_with(self.get_ref(), tracker)
+    }
+}
+
+/// The internal buffer is allocated upfront, so its whole capacity is
+/// counted, whatever is buffered.
+impl<W> MemoryUsage for BufWriter<W>
+where
+    W: Write + MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self.capacity()
+            + crate::heap_size_of_val_with(self.get_ref(), tracker)
+    }
+}
+
+/// A custom error is boxed, and is seen as a trait object: the box
+/// pointer and the shallow size of the error are counted, but not the
+/// heap the error owns.
+impl MemoryUsage for io::Error {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self
+                .get_ref()
+                .map(|error| 2 * POINTER_BYTE_SIZE + mem::size_of_val(error))
+                .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test_io_types {
+    use super::*;
+
+    #[test]
+    fn test_cursor() {
+        let cursor = Cursor::new(vec![1u8, 2, 3]);
+        assert_size_of_val_eq!(cursor, mem::size_of_val(&cursor) + 1 * 3);
+
+        let bytes = [1u8, 2, 3];
+        let cursor = Cursor::new(&bytes[..]);
+        assert_size_of_val_eq!(cursor, mem::size_of_val(&cursor) + 1 * 3);
+    }
+
+    #[test]
+    fn test_bufreader() {
+        let reader = BufReader::with_capacity(1024, Cursor::new(vec![1u8, 2, 3]));
+        assert_size_of_val_eq!(reader, mem::size_of_val(&reader) + 1024 + 1 * 3);
+
+        let reader = BufReader::with_capacity(16, Cursor::new(vec![1u8, 2, 3]));
+        assert_size_of_val_eq!(reader, mem::size_of_val(&reader) + 16 + 1 * 3);
+    }
+
+    #[test]
+    fn test_bufwriter() {
+        let mut writer = BufWriter::with_capacity(1024, Cursor::new(Vec::<u8>::new()));
+        assert_size_of_val_eq!(writer, mem::size_of_val(&writer) + 1024);
+
+        writer.write_all(&[1, 2, 3]).unwrap();
+        writer.flush().unwrap();
+        assert_size_of_val_eq!(writer, mem::size_of_val(&writer) + 1024 + 1 * 3);
+    }
+
+    #[test]
+    fn test_error() {
+        let simple = io::Error::from(io::ErrorKind::NotFound);
+        assert_size_of_val_eq!(simple, mem::size_of_val(&simple));
+
+        let custom = io::Error::new(
+            io::ErrorKind::InvalidData,
+            String::from("a long message describing what went wrong"),
+        );
+        let custom_size = crate::size_of_val(&custom);
+        assert!(custom_size > crate::size_of_val(&simple));
+        assert!(custom_size >= mem::size_of_val(&custom) + 2 * POINTER_BYTE_SIZE);
+    }
+}
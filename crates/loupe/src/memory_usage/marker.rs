@@ -1,8 +1,63 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
 use crate::{MemoryUsage, MemoryUsageTracker};
-use std::marker::PhantomData;
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::fmt::Alignment;
+use std::marker::{PhantomData, PhantomPinned};
+use std::mem;
 
-impl<T> MemoryUsage for PhantomData<T> {
+impl<T: ?Sized> MemoryUsage for PhantomData<T> {
     fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
         0
     }
 }
+
+impl MemoryUsage for PhantomPinned {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        0
+    }
+}
+
+impl MemoryUsage for Infallible {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        match *self {}
+    }
+}
+
+impl MemoryUsage for Ordering {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+impl MemoryUsage for Alignment {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+#[cfg(test)]
+mod test_marker_types {
+    use super::*;
+
+    #[test]
+    fn test_phantom() {
+        assert_size_of_val_eq!(PhantomData::<String>, 0);
+        assert_size_of_val_eq!(PhantomData::<str>, 0);
+        assert_size_of_val_eq!(PhantomData::<dyn Fn()>, 0);
+        assert_size_of_val_eq!(PhantomPinned, 0);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert_size_of_val_eq!(Ordering::Less, 1);
+        assert_size_of_val_eq!(Alignment::Center, 1);
+    }
+
+    #[test]
+    fn test_infallible() {
+        let result: Result<u32, Infallible> = Ok(1);
+        assert_size_of_val_eq!(result, mem::size_of::<Result<u32, Infallible>>() + 4);
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+
+/// A `ManuallyDrop` is always initialized, it's measured like its
+/// value.
+impl<T> MemoryUsage for ManuallyDrop<T>
+where
+    T: MemoryUsage + ?Sized,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        (**self).size_of_val(tracker)
+    }
+}
+
+/// The content may be uninitialized, so it's never read: only the
+/// shallow size is counted.
+impl<T> MemoryUsage for MaybeUninit<T> {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+#[cfg(test)]
+mod test_mem_types {
+    use super::*;
+
+    #[test]
+    fn test_manually_drop() {
+        let value = ManuallyDrop::new(1u32);
+        assert_size_of_val_eq!(value, 4);
+
+        let mut value = ManuallyDrop::new("abc".to_string());
+        assert_size_of_val_eq!(value, mem::size_of::<String>() + 1 * 3);
+
+        unsafe { ManuallyDrop::drop(&mut value) };
+    }
+
+    #[test]
+    fn test_maybe_uninit() {
+        let value: MaybeUninit<u64> = MaybeUninit::uninit();
+        assert_size_of_val_eq!(value, 8);
+
+        let value: MaybeUninit<String> = MaybeUninit::new("abc".to_string());
+        assert_size_of_val_eq!(value, mem::size_of::<String>());
+        drop(unsafe { value.assume_init() });
+    }
+
+    #[test]
+    fn test_vec_of_maybe_uninit() {
+        let buffer: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); 16];
+        assert_size_of_val_eq!(buffer, mem::size_of::<Vec<u8>>() + 1 * 16);
+    }
+}
@@ -1,34 +1,35 @@
 mod alloc;
 mod any;
+mod borrow;
 mod r#box;
 mod cell;
+mod cmp;
 mod collection;
+mod ffi;
+mod function;
+mod io;
 mod marker;
+mod mem;
+mod net;
+mod num;
+mod ops;
 mod option;
 mod path;
+mod pin;
 mod primitive;
+mod process;
 mod ptr;
+mod rc;
 mod remote;
 mod result;
 mod slice;
 mod string;
 mod sync;
+mod task;
+mod time;
+mod wrappers;
 
-pub use alloc::*;
-pub use any::*;
-pub use cell::*;
-pub use collection::*;
-pub use marker::*;
-pub use option::*;
-pub use path::*;
-pub use primitive::*;
-pub use ptr::*;
-pub use r#box::*;
-pub use remote::*;
-pub use result::*;
-pub use slice::*;
-pub use string::*;
-pub use sync::*;
+pub use wrappers::{Ignored, Opaque};
 
 /// Size of a pointer for the compilation target.
 pub const POINTER_BYTE_SIZE: usize = if cfg!(target_pointer_width = "16") {
@@ -44,6 +45,306 @@ pub const POINTER_BYTE_SIZE: usize = if cfg!(target_pointer_width = "16") {
 pub trait MemoryUsageTracker {
     /// When first called on a given address returns true, false otherwise.
     fn track(&mut self, address: *const ()) -> bool;
+
+    /// Same as `track`, with the number of bytes charged to the address:
+    /// the size of the allocation of a `Box`, an `Rc`, an `Arc`, or of
+    /// the buffer of a `Vec`, a `String` or a `HashMap`, without the
+    /// allocations of its content, or the shallow size of the value of
+    /// a reference.
+    ///
+    /// The buffers of collections are uniquely owned, so they are
+    /// counted whatever it returns. They are not tracked when empty,
+    /// since their address is then dangling, and shared.
+    fn track_with_size(&mut self, address: *const (), _bytes: usize) -> bool {
+        self.track(address)
+    }
+
+    /// Called by the values with the `#[loupe(track_self)]` attribute,
+    /// with their address and the name of their type, as given by
+    /// `std::any::type_name`: when first called on a given value returns
+    /// true, false otherwise.
+    ///
+    /// A value has the address of its first field, and of the pointers
+    /// to it, so the values are tracked apart from the addresses given
+    /// to `track`, by address and type. Trackers that don't override it,
+    /// like `BTreeSet` and `HashSet`, track the complement of the
+    /// address with `track` instead: on 64-bit targets, values live in
+    /// the lower half of the address space, and the complement of their
+    /// address in the upper half, reserved to the kernel, so it's never
+    /// given to `track`. These trackers don't know the type of the
+    /// values though: nested `track_self` values at the same address,
+    /// like a value and its first field, must be measured with a tracker
+    /// overriding it, like `DefaultTracker`.
+    fn track_self(&mut self, address: *const (), _type_name: &'static str) -> bool {
+        self.track(address.map_addr(|address| !address))
+    }
+
+    /// Called when a pointer, like a `Box` or an `Arc`, starts
+    /// measuring the value at `address`, right after `track` returned
+    /// true for it. `type_name` is the name of the type of the value,
+    /// as given by `std::any::type_name`.
+    fn enter(&mut self, _address: *const (), _type_name: &'static str) {}
+
+    /// Called when the value given to the last `enter` call that isn't
+    /// left yet has been measured, with the size charged to it.
+    fn leave(&mut self, _size: usize) {}
+
+    /// Called by the values owning an allocation, like a `Vec` or a
+    /// `Box`, with their type name, as given by `std::any::type_name`,
+    /// and the size of the allocation, without the allocations of its
+    /// content. A value only records its allocation when it's counted,
+    /// i.e. once for a shared value.
+    fn record(&mut self, _type_name: &'static str, _bytes: usize) {}
+
+    /// Whether the traversal must stop as soon as possible, e.g.
+    /// because a budget is exhausted. Pointers don't measure their
+    /// value anymore, and collections don't measure their remaining
+    /// elements beyond their shallow size, so that the size is a lower
+    /// bound.
+    fn should_stop(&self) -> bool {
+        false
+    }
+
+    /// How the values are counted, see
+    /// [`SizePolicy`](crate::SizePolicy). Without a policy, `VecDeque`
+    /// counts its capacity and `Vec`, `String`, `HashMap`, `HashSet` and
+    /// `BinaryHeap` their length, as they always did, shared values are
+    /// charged to their first owner, and references are followed.
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        None
+    }
+
+    /// The model rounding up the allocations of `Box`, `Rc`, `Arc`,
+    /// and the buffers of `Vec`, `VecDeque`, `String`, `HashMap`,
+    /// `HashSet` and `BinaryHeap`, see
+    /// [`AllocationModel`](crate::AllocationModel). Without a model,
+    /// allocations are counted by the size they request.
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        None
+    }
+
+    /// Called when a part of a value starts being measured within the
+    /// logical bucket `label`, e.g. a field with the
+    /// `#[loupe(label = "...")]` attribute.
+    fn push_label(&mut self, _label: &'static str) {}
+
+    /// Called when the part of the value given to the last
+    /// `push_label` call that isn't popped yet has been measured, with
+    /// its size that isn't part of its container's shallow size.
+    fn pop_label(&mut self, _size: usize) {}
+}
+
+/// Measure the value at `address`, pointed to by a pointer, if it
+/// hasn't been visited yet, with `size_of_val`. It returns the size of
+/// the value, and notifies the tracker with `enter` and `leave`.
+/// `bytes` is the size charged to the address, see
+/// `MemoryUsageTracker::track_with_size`.
+pub(crate) fn size_of_pointee<T, F>(
+    tracker: &mut dyn MemoryUsageTracker,
+    address: *const T,
+    bytes: usize,
+    size_of_val: F,
+) -> usize
+where
+    T: ?Sized,
+    F: FnOnce(&mut dyn MemoryUsageTracker) -> usize,
+{
+    let address = address.cast::<()>();
+
+    if tracker.should_stop() || !tracker.track_with_size(address, bytes) {
+        return 0;
+    }
+
+    tracker.enter(address, std::any::type_name::<T>());
+    let size = size_of_val(tracker);
+    tracker.leave(size);
+
+    size
+}
+
+/// Notify the tracker of the buffer of a collection, at `address`,
+/// with `MemoryUsageTracker::track_with_size`, unless it's empty.
+pub(crate) fn track_buffer(tracker: &mut dyn MemoryUsageTracker, address: *const (), bytes: usize) {
+    if bytes > 0 {
+        tracker.track_with_size(address, bytes);
+    }
+}
+
+/// Sum the sizes of `values` that are not already counted by their
+/// shallow size, until the tracker asks to stop.
+pub(crate) fn heap_size_of_values<'a, T, I>(
+    tracker: &mut dyn MemoryUsageTracker,
+    values: I,
+) -> usize
+where
+    T: MemoryUsage + ?Sized + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let mut size = 0;
+
+    for value in values {
+        if tracker.should_stop() {
+            break;
+        }
+
+        // An implementation could return less than the shallow size,
+        // like `Ignored`, don't underflow.
+        size += value
+            .size_of_val(tracker)
+            .saturating_sub(std::mem::size_of_val(value));
+    }
+
+    size
+}
+
+/// The tracker used by [`size_of_val`](crate::size_of_val).
+///
+/// A tracker remembers the addresses it has seen, so it can be reused
+/// to measure several values that share data, counting the shared data
+/// once. Between measurements of unrelated values, it must be cleared
+/// with [`DefaultTracker::clear`]: an address seen during a previous
+/// measurement may since have been freed and reused by a new
+/// allocation, which would not be counted. Clearing keeps the memory
+/// of the tracker, so that measuring in a loop doesn't allocate.
+#[derive(Debug, Default)]
+pub struct DefaultTracker {
+    visited: std::collections::BTreeSet<*const ()>,
+    values: std::collections::BTreeSet<(*const (), &'static str)>,
+}
+
+impl DefaultTracker {
+    /// An empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all the addresses seen so far.
+    pub fn clear(&mut self) {
+        self.visited.clear();
+        self.values.clear();
+    }
+
+    /// The number of addresses seen so far.
+    pub fn len(&self) -> usize {
+        self.visited.len()
+    }
+
+    /// Whether no address has been seen so far.
+    pub fn is_empty(&self) -> bool {
+        self.visited.is_empty()
+    }
+}
+
+impl MemoryUsageTracker for DefaultTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+}
+
+/// A tracker can be reborrowed, e.g. to be passed by value to a
+/// wrapper like `StatsTracker`.
+impl<T> MemoryUsageTracker for &mut T
+where
+    T: MemoryUsageTracker + ?Sized,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        (**self).track(address)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        (**self).track_with_size(address, bytes)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        (**self).track_self(address, type_name)
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        (**self).enter(address, type_name)
+    }
+
+    fn leave(&mut self, size: usize) {
+        (**self).leave(size)
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        (**self).record(type_name, bytes)
+    }
+
+    fn should_stop(&self) -> bool {
+        (**self).should_stop()
+    }
+
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        (**self).size_policy()
+    }
+
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        (**self).allocation_model()
+    }
+
+    fn push_label(&mut self, label: &'static str) {
+        (**self).push_label(label)
+    }
+
+    fn pop_label(&mut self, size: usize) {
+        (**self).pop_label(size)
+    }
+}
+
+/// A tracker can be boxed, e.g. to choose it at runtime as a
+/// `Box<dyn MemoryUsageTracker>`.
+impl<T> MemoryUsageTracker for Box<T>
+where
+    T: MemoryUsageTracker + ?Sized,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        (**self).track(address)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        (**self).track_with_size(address, bytes)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        (**self).track_self(address, type_name)
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        (**self).enter(address, type_name)
+    }
+
+    fn leave(&mut self, size: usize) {
+        (**self).leave(size)
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        (**self).record(type_name, bytes)
+    }
+
+    fn should_stop(&self) -> bool {
+        (**self).should_stop()
+    }
+
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        (**self).size_policy()
+    }
+
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        (**self).allocation_model()
+    }
+
+    fn push_label(&mut self, label: &'static str) {
+        (**self).push_label(label)
+    }
+
+    fn pop_label(&mut self, size: usize) {
+        (**self).pop_label(size)
+    }
 }
 
 impl MemoryUsageTracker for std::collections::BTreeSet<*const ()> {
@@ -59,6 +360,33 @@ impl MemoryUsageTracker for std::collections::HashSet<*const ()> {
 }
 
 /// Traverse a value and collect its memory usage.
+///
+/// The trait is object safe: `Box<dyn MemoryUsage>`, `Arc<dyn
+/// MemoryUsage + Send + Sync>` etc. can be measured, the value being
+/// traversed through the vtable.
+///
+/// When it can't be derived, the trait is implemented by adding the
+/// heap size of each field, as given by
+/// [`heap_size_of_val_with`](crate::heap_size_of_val_with), to the
+/// shallow size of the value:
+///
+/// ```rust
+/// use loupe::{heap_size_of_val_with, MemoryUsage, MemoryUsageTracker};
+/// use std::mem;
+///
+/// struct Buffer {
+///     name: String,
+///     data: Vec<u8>,
+/// }
+///
+/// impl MemoryUsage for Buffer {
+///     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+///         mem::size_of_val(self)
+///             + heap_size_of_val_with(&self.name, tracker)
+///             + heap_size_of_val_with(&self.data, tracker)
+///     }
+/// }
+/// ```
 pub trait MemoryUsage {
     /// Returns the size of the referenced value in bytes.
     ///
@@ -67,6 +395,51 @@ pub trait MemoryUsage {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize;
 }
 
+/// Methods to measure a value, implemented for all the types
+/// implementing [`MemoryUsage`].
+///
+/// ```rust
+/// use loupe::MemoryUsageExt;
+///
+/// assert_eq!(vec![0u8; 10].memory_usage(), loupe::size_of_val(&vec![0u8; 10]));
+/// ```
+pub trait MemoryUsageExt: MemoryUsage {
+    /// Same as [`size_of_val`](crate::size_of_val).
+    fn memory_usage(&self) -> usize {
+        self.size_of_val(&mut DefaultTracker::new())
+    }
+
+    /// Same as [`size_of_val_with`](crate::size_of_val_with).
+    fn memory_usage_with(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        self.size_of_val(tracker)
+    }
+}
+
+impl<T> MemoryUsageExt for T where T: MemoryUsage + ?Sized {}
+
+/// The shallow size of a sized type, without a value, e.g. to estimate
+/// the size of the buffer of a collection from its capacity. It's
+/// implemented for all the sized types implementing [`MemoryUsage`].
+///
+/// It's a separate trait, because an associated constant would prevent
+/// `MemoryUsage` from being used as a trait object, e.g. `Box<dyn
+/// MemoryUsage>`.
+///
+/// ```rust
+/// use loupe::MemoryUsageSized;
+///
+/// let capacity = 100;
+///
+/// assert_eq!(capacity * <(u8, u32)>::SHALLOW_SIZE, 800);
+/// ```
+pub trait MemoryUsageSized: MemoryUsage + Sized {
+    /// The size of a value of the type, excluding what it owns, as given
+    /// by `mem::size_of`.
+    const SHALLOW_SIZE: usize = std::mem::size_of::<Self>();
+}
+
+impl<T> MemoryUsageSized for T where T: MemoryUsage {}
+
 /// Alias to `assert_eq!(loupe::MemoryUsage::size_of_val(&$value), $expected)`.
 #[macro_export]
 macro_rules! assert_size_of_val_eq {
@@ -82,12 +455,351 @@ macro_rules! assert_size_of_val_eq {
     };
 }
 
+/// Same as `assert_size_of_val_eq!`, but the size must be between
+/// `$low` and `$high` inclusive, e.g. when it depends on the capacity
+/// chosen by an allocation.
+#[macro_export]
+macro_rules! assert_size_of_val_between {
+    ($value:expr, $low:expr, $high:expr $(,)*) => {
+        $crate::assert_size_of_val_between!(
+            $value,
+            $low,
+            $high,
+            &mut std::collections::BTreeSet::new()
+        )
+    };
+
+    ($value:expr, $low:expr, $high:expr, $tracker:expr $(,)*) => {{
+        let (size, low, high): (usize, usize, usize) = (
+            $crate::MemoryUsage::size_of_val(&$value, $tracker),
+            $low,
+            $high,
+        );
+
+        assert!(
+            low <= size && size <= high,
+            "size_of_val({}) is {} bytes, expected between {} and {} bytes",
+            stringify!($value),
+            size,
+            low,
+            high
+        );
+    }};
+}
+
+/// Same as `assert_size_of_val_eq!`, but the size can be off by
+/// `$tolerance_percent` percent of `$expected`, e.g. when it depends on
+/// the layout of types of `std`.
+#[macro_export]
+macro_rules! assert_size_of_val_approx {
+    ($value:expr, $expected:expr, $tolerance_percent:expr $(,)*) => {
+        $crate::assert_size_of_val_approx!(
+            $value,
+            $expected,
+            $tolerance_percent,
+            &mut std::collections::BTreeSet::new()
+        )
+    };
+
+    ($value:expr, $expected:expr, $tolerance_percent:expr, $tracker:expr $(,)*) => {{
+        let (size, expected): (usize, usize) = (
+            $crate::MemoryUsage::size_of_val(&$value, $tracker),
+            $expected,
+        );
+        let tolerance_percent = $tolerance_percent as f64;
+
+        assert!(
+            (size as f64 - expected as f64).abs() <= expected as f64 * tolerance_percent / 100.0,
+            "size_of_val({}) is {} bytes, expected {} bytes ± {}%",
+            stringify!($value),
+            size,
+            expected,
+            tolerance_percent
+        );
+    }};
+}
+
+/// Assert that running `$workload` doesn't make `$value` grow by more
+/// than `$tolerance` bytes, 0 by default, e.g. to catch leaks in caches.
+///
+/// The value is measured with a fresh tracker before and after the
+/// workload. With the `breakdown` feature, if the type of the value
+/// implements `MemoryUsageBreakdown`, the failure message lists the
+/// parts of the value that grew the most.
+///
+/// ```rust,should_panic
+/// use loupe::{assert_no_growth, MemoryUsage};
+/// use std::collections::HashMap;
+///
+/// #[derive(MemoryUsage, Default)]
+/// struct Service {
+///     sessions: HashMap<u32, String>,
+/// }
+///
+/// let mut service = Service::default();
+///
+/// // Panics: the sessions are never removed.
+/// assert_no_growth!(service, {
+///     for id in 0..1000 {
+///         service.sessions.insert(id, String::from("session"));
+///     }
+/// });
+/// ```
+#[macro_export]
+macro_rules! assert_no_growth {
+    ($value:expr, $workload:expr $(,)*) => {
+        $crate::assert_no_growth!($value, $workload, 0)
+    };
+
+    ($value:expr, $workload:expr, $tolerance:expr $(,)*) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{GrowthLeaf as _, GrowthNested as _};
+
+        let before = (&&$crate::__private::GrowthProbe(&$value)).measure_growth();
+        $workload;
+        let after = (&&$crate::__private::GrowthProbe(&$value)).measure_growth();
+
+        $crate::__private::assert_no_growth(stringify!($value), before, after, $tolerance);
+    }};
+}
+
+#[cfg(test)]
+mod test_assertion_macros {
+    #[test]
+    fn test_assert_size_of_val_between() {
+        let value = vec![1u8; 10];
+        let size = crate::size_of_val(&value);
+
+        assert_size_of_val_between!(value, size, size);
+        assert_size_of_val_between!(value, size - 1, size + 1);
+        assert_size_of_val_between!(value, size, size, &mut super::DefaultTracker::new());
+
+        // In expression position.
+        let check = |value: &Vec<u8>| assert_size_of_val_between!(*value, size, size);
+        check(&value);
+        match value.len() {
+            10 => assert_size_of_val_between!(value, size, size, &mut super::DefaultTracker::new()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "size_of_val(value) is 4 bytes, expected between 5 and 8 bytes")]
+    fn test_assert_size_of_val_between_fails() {
+        let value = 1u32;
+
+        assert_size_of_val_between!(value, 5, 8);
+    }
+
+    #[test]
+    fn test_assert_size_of_val_approx() {
+        let value = [0u8; 100];
+
+        assert_size_of_val_approx!(value, 100, 0);
+        assert_size_of_val_approx!(value, 95, 10);
+        assert_size_of_val_approx!(value, 105, 5);
+        assert_size_of_val_approx!(value, 102, 2.5, &mut super::DefaultTracker::new());
+
+        // In expression position.
+        let check = |value: &[u8; 100]| assert_size_of_val_approx!(*value, 100, 0);
+        check(&value);
+        match value.len() {
+            100 => assert_size_of_val_approx!(value, 100, 0, &mut super::DefaultTracker::new()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_assert_no_growth() {
+        let mut value: Vec<u64> = Vec::new();
+
+        assert_no_growth!(value, {});
+        assert_no_growth!(value, value.push(1), 8);
+        assert_no_growth!(value, {
+            value.push(2);
+            value.clear();
+        },);
+    }
+
+    #[test]
+    #[should_panic(expected = "`value` grew by 16 bytes, from ")]
+    fn test_assert_no_growth_fails() {
+        let mut value = vec![1u64];
+
+        assert_no_growth!(value, value.extend([2, 3].iter()), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "size_of_val(value) is 100 bytes, expected 90 bytes ± 10%")]
+    fn test_assert_size_of_val_approx_fails() {
+        let value = [0u8; 100];
+
+        assert_size_of_val_approx!(value, 90, 10);
+    }
+}
+
+#[cfg(test)]
+mod test_memory_usage_ext {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_memory_usage() {
+        let string = String::from("abc");
+        let map: HashMap<u8, Vec<u8>> = vec![(1, vec![1, 2])].into_iter().collect();
+        let slice: &[u16] = &[1, 2, 3];
+
+        assert_eq!(1u64.memory_usage(), 8);
+        assert_eq!(string.memory_usage(), crate::size_of_val(&string));
+        assert_eq!(map.memory_usage(), crate::size_of_val(&map));
+        // The method takes `&self`: the slice is measured, not the
+        // reference.
+        assert_eq!(slice.memory_usage(), 3 * 2);
+        assert_eq!((&slice).memory_usage(), 2 * POINTER_BYTE_SIZE + 3 * 2);
+    }
+
+    #[test]
+    fn test_memory_usage_with() {
+        let shared = Arc::new(1u64);
+        let mut tracker = DefaultTracker::new();
+
+        assert!(shared.memory_usage_with(&mut tracker) > POINTER_BYTE_SIZE);
+        assert_eq!(shared.memory_usage_with(&mut tracker), POINTER_BYTE_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod test_tracker_types {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_default_tracker_reuse() {
+        let shared = Arc::new(String::from("shared"));
+        let first = vec![shared.clone()];
+        let second = vec![shared];
+
+        let mut tracker = DefaultTracker::new();
+        assert!(tracker.is_empty());
+
+        // The buffer of `first`, the `Arc`, and the buffer of the
+        // `String`.
+        let first_size = first.size_of_val(&mut tracker);
+        assert_eq!(tracker.len(), 3);
+
+        // The shared value is counted once across measurements.
+        let second_size = second.size_of_val(&mut tracker);
+        assert_eq!(first_size, crate::size_of_val(&first));
+        assert!(second_size < crate::size_of_val(&second));
+
+        // Once cleared, measurements are independent and repeatable.
+        for _ in 0..2 {
+            tracker.clear();
+            assert!(tracker.is_empty());
+            assert_eq!(
+                second.size_of_val(&mut tracker),
+                crate::size_of_val(&second)
+            );
+        }
+    }
+
+    #[test]
+    fn test_reborrowed_tracker() {
+        fn measure<T: MemoryUsageTracker>(value: &dyn MemoryUsage, mut tracker: T) -> usize {
+            value.size_of_val(&mut tracker)
+        }
+
+        let value = Box::new(1u8);
+        let mut tracker = DefaultTracker::new();
+
+        assert_eq!(measure(&value, &mut tracker), POINTER_BYTE_SIZE + 1);
+        assert_eq!(measure(&value, &mut tracker), POINTER_BYTE_SIZE);
+        assert_eq!(
+            measure(&value, crate::StatsTracker::new(&mut tracker)),
+            POINTER_BYTE_SIZE
+        );
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_boxed_tracker() {
+        let shared = Arc::new(vec![0u8; 100]);
+        let value = vec![shared.clone(), shared];
+
+        let trackers: Vec<Box<dyn MemoryUsageTracker>> = vec![
+            Box::new(DefaultTracker::new()),
+            Box::new(std::collections::HashSet::new()),
+            Box::new(crate::FastTracker::new()),
+        ];
+
+        for mut tracker in trackers {
+            // A wrapper around a boxed tracker, and around a reference
+            // to it.
+            let mut stats = crate::StatsTracker::new(&mut tracker);
+            assert_eq!(value.size_of_val(&mut stats), crate::size_of_val(&value));
+            assert_eq!(stats.stats().first_seen, 3);
+            assert_eq!(stats.stats().already_seen, 1);
+
+            // Values are deduplicated across the wrappers.
+            let mut stats = crate::StatsTracker::new(tracker);
+            assert_eq!(
+                value.size_of_val(&mut stats),
+                3 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE
+            );
+            assert_eq!(stats.stats().already_seen, 3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_trait_object_types {
+    use super::*;
+    use std::mem;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_boxed_trait_objects() {
+        let values: Vec<Box<dyn MemoryUsage>> = vec![
+            Box::new(1u8),
+            Box::new(1u64),
+            Box::new(String::from("abc")),
+            Box::new(vec![1u32, 2, 3]),
+        ];
+
+        assert_size_of_val_eq!(
+            values,
+            mem::size_of::<Vec<Box<dyn MemoryUsage>>>()
+                + 4 * 2 * POINTER_BYTE_SIZE
+                + 1
+                + 8
+                + (3 * POINTER_BYTE_SIZE + 1 * 3)
+                + (3 * POINTER_BYTE_SIZE + 4 * 3)
+        );
+    }
+
+    #[test]
+    fn test_shared_trait_objects() {
+        let value: Arc<dyn MemoryUsage + Send + Sync> = Arc::new(String::from("abc"));
+
+        assert_size_of_val_eq!(
+            (Arc::clone(&value), value),
+            2 * 2 * POINTER_BYTE_SIZE
+                + 2 * POINTER_BYTE_SIZE /* counters */
+                + 3 * POINTER_BYTE_SIZE
+                + 1 * 3
+        );
+    }
+
+    #[test]
+    fn test_referenced_trait_objects() {
+        let value = vec![1u8, 2, 3];
+        let value: &dyn MemoryUsage = &value;
+
+        assert_size_of_val_eq!(value, 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 1 * 3);
+    }
+}
+
 // TODO:
 //
 // * Cell
-// * Pin (is a Pin always referenceable?)
-// * Rc
-// * Ref
-// * RefCell
-// * RefMut
-// * PhantomPinned
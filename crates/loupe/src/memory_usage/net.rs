@@ -0,0 +1,55 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+macro_rules! impl_memory_usage_for_address {
+    ( $type:ty ) => {
+        impl MemoryUsage for $type {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+            }
+        }
+    };
+
+    ( $( $type:ty ),+ $(,)* ) => {
+        $( impl_memory_usage_for_address!( $type ); )+
+    }
+}
+
+impl_memory_usage_for_address!(
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+);
+
+#[cfg(test)]
+mod test_net_types {
+    use super::*;
+
+    macro_rules! test_memory_usage_for_address {
+        ($test_name:ident: ($value:expr) == $expected:expr) => {
+            #[test]
+            fn $test_name() {
+                assert_size_of_val_eq!($value, $expected);
+            }
+        };
+
+        ( $( $test_name:ident: ($value:expr) == $expected:expr );+ $(;)* ) => {
+            $( test_memory_usage_for_address!( $test_name: ($value) == $expected); )+
+        }
+    }
+
+    test_memory_usage_for_address!(
+        test_ipv4_addr: (Ipv4Addr::LOCALHOST) == mem::size_of::<Ipv4Addr>();
+        test_ipv6_addr: (Ipv6Addr::LOCALHOST) == mem::size_of::<Ipv6Addr>();
+        test_ip_addr: (IpAddr::V4(Ipv4Addr::LOCALHOST)) == mem::size_of::<IpAddr>();
+        test_socket_addr_v4: (SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80)) == mem::size_of::<SocketAddrV4>();
+        test_socket_addr_v6: (SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 0, 0)) == mem::size_of::<SocketAddrV6>();
+        test_socket_addr: (SocketAddr::from(([127, 0, 0, 1], 80))) == mem::size_of::<SocketAddr>();
+    );
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::num::{Saturating, Wrapping};
+
+impl<T> MemoryUsage for Wrapping<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        self.0.size_of_val(tracker)
+    }
+}
+
+impl<T> MemoryUsage for Saturating<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        self.0.size_of_val(tracker)
+    }
+}
+
+#[cfg(test)]
+mod test_num_types {
+    use super::*;
+
+    #[test]
+    fn test_wrapping() {
+        assert_size_of_val_eq!(Wrapping(1u8), 1);
+        assert_size_of_val_eq!(Wrapping(1u64), 8);
+    }
+
+    #[test]
+    fn test_saturating() {
+        assert_size_of_val_eq!(Saturating(1i16), 2);
+        assert_size_of_val_eq!(Saturating(1i64), 8);
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::ops::{
+    Bound, ControlFlow, Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+};
+
+/// Heap owned by a value stored inline in its parent.
+fn heap_size_of_val<T>(value: &T, tracker: &mut dyn MemoryUsageTracker) -> usize
+where
+    T: MemoryUsage,
+{
+    crate::heap_size_of_val_with(value, tracker)
+}
+
+impl<T> MemoryUsage for Range<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + heap_size_of_val(&self.start, tracker)
+            + heap_size_of_val(&self.end, tracker)
+    }
+}
+
+impl<T> MemoryUsage for RangeInclusive<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + heap_size_of_val(self.start(), tracker)
+            + heap_size_of_val(self.end(), tracker)
+    }
+}
+
+impl<T> MemoryUsage for RangeFrom<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + heap_size_of_val(&self.start, tracker)
+    }
+}
+
+impl<T> MemoryUsage for RangeTo<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + heap_size_of_val(&self.end, tracker)
+    }
+}
+
+impl<T> MemoryUsage for RangeToInclusive<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + heap_size_of_val(&self.end, tracker)
+    }
+}
+
+impl MemoryUsage for RangeFull {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        0
+    }
+}
+
+impl<T> MemoryUsage for Bound<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + match self {
+                Bound::Included(value) | Bound::Excluded(value) => heap_size_of_val(value, tracker),
+                Bound::Unbounded => 0,
+            }
+    }
+}
+
+impl<B, C> MemoryUsage for ControlFlow<B, C>
+where
+    B: MemoryUsage,
+    C: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + match self {
+                ControlFlow::Continue(value) => heap_size_of_val(value, tracker),
+                ControlFlow::Break(value) => heap_size_of_val(value, tracker),
+            }
+    }
+}
+
+#[cfg(test)]
+mod test_ops_types {
+    use super::*;
+
+    #[test]
+    fn test_range() {
+        assert_size_of_val_eq!(1u32..2, 4 * 2);
+        assert_size_of_val_eq!(1u32..=2, mem::size_of::<RangeInclusive<u32>>());
+        assert_size_of_val_eq!(1u64.., 8);
+        assert_size_of_val_eq!(..2u64, 8);
+        assert_size_of_val_eq!(..=2u64, 8);
+        assert_size_of_val_eq!(.., 0);
+    }
+
+    #[test]
+    fn test_range_of_strings() {
+        let range = "abc".to_string().."de".to_string();
+        assert_size_of_val_eq!(range, 2 * mem::size_of::<String>() + 1 * 3 + 1 * 2);
+
+        let range = "abc".to_string()..="de".to_string();
+        assert_size_of_val_eq!(range, mem::size_of_val(&range) + 1 * 3 + 1 * 2);
+    }
+
+    #[test]
+    fn test_bound() {
+        let bound: Bound<String> = Bound::Included("abc".to_string());
+        assert_size_of_val_eq!(bound, mem::size_of_val(&bound) + 1 * 3);
+
+        let bound: Bound<String> = Bound::Unbounded;
+        assert_size_of_val_eq!(bound, mem::size_of_val(&bound));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let flow: ControlFlow<String, u8> = ControlFlow::Break("abc".to_string());
+        assert_size_of_val_eq!(flow, mem::size_of_val(&flow) + 1 * 3);
+
+        let flow: ControlFlow<String, u8> = ControlFlow::Continue(1);
+        assert_size_of_val_eq!(flow, mem::size_of_val(&flow));
+    }
+}
@@ -11,7 +11,7 @@ where
         mem::size_of_val(self)
             + self
                 .iter()
-                .map(|value| value.size_of_val(tracker))
+                .map(|value| crate::heap_size_of_val_with(value, tracker))
                 .sum::<usize>()
     }
 }
@@ -26,13 +26,13 @@ mod test_option_types {
         assert_size_of_val_eq!(option, 1 /* variant */ + 1 /* padding */);
 
         let option: Option<i8> = Some(1);
-        assert_size_of_val_eq!(option, 1 /* variant */ + 1 /* padding */ + 1 /* i8 */);
+        assert_size_of_val_eq!(option, 1 /* variant */ + 1 /* i8 */);
 
         let option: Option<i32> = None;
         assert_size_of_val_eq!(option, 1 /* variant */ + 7 /* padding */);
 
         let option: Option<i32> = Some(1);
-        assert_size_of_val_eq!(option, 1 /* variant */ + 7 /* padding */ + 4 /* i32 */);
+        assert_size_of_val_eq!(option, 1 /* variant */ + 3 /* padding */ + 4 /* i32 */);
 
         let option: Option<&str> = None;
         assert_size_of_val_eq!(option, 1 /* variant */ + 15 /* padding */);
@@ -40,7 +40,7 @@ mod test_option_types {
         let option: Option<&str> = Some("abc");
         assert_size_of_val_eq!(
             option,
-            1 /* variant */ + 15 /* padding */ + 2 * POINTER_BYTE_SIZE + 1 * 3, /* &str */
+            2 * POINTER_BYTE_SIZE /* &str */ + 1 * 3 /* str */
         );
     }
 }
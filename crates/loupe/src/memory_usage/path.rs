@@ -1,12 +1,22 @@
 #[cfg(test)]
-use crate::assert_size_of_val_eq;
+use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
 use crate::{MemoryUsage, MemoryUsageTracker};
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+impl MemoryUsage for Path {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        self.as_os_str().size_of_val(tracker)
+    }
+}
+
+/// Counted as the `OsString` it wraps: the bytes of its `OsStr`, and its
+/// spare capacity.
 impl MemoryUsage for PathBuf {
-    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self) + self.capacity()
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let os_str = self.as_os_str();
+
+        mem::size_of_val(self) + os_str.size_of_val(tracker) + (self.capacity() - os_str.len())
     }
 }
 
@@ -24,5 +34,53 @@ mod test_path_types {
 
         path.push("foobar");
         assert_size_of_val_eq!(path, empty_path_size + 16);
+
+        // As much as the `OsString` it wraps.
+        let path_size = crate::size_of_val(&path);
+        assert_size_of_val_eq!(path.into_os_string(), path_size);
+    }
+
+    #[test]
+    fn test_path() {
+        let path: &Path = Path::new("foo/bar");
+        assert_size_of_val_eq!(path, 2 * POINTER_BYTE_SIZE + 1 * 7);
+
+        // The same path is charged once.
+        let mut tracker = std::collections::BTreeSet::new();
+        assert_size_of_val_eq!(path, 2 * POINTER_BYTE_SIZE + 1 * 7, &mut tracker);
+        assert_size_of_val_eq!(path, 2 * POINTER_BYTE_SIZE + 0, &mut tracker);
+    }
+
+    #[test]
+    fn test_boxed_path() {
+        let path: Box<Path> = Path::new("foo/bar").into();
+        assert_size_of_val_eq!(path, 2 * POINTER_BYTE_SIZE + 1 * 7);
+
+        let paths: Vec<Box<Path>> = vec![Path::new("foo").into(), Path::new("foobar").into()];
+        assert_size_of_val_eq!(
+            paths,
+            mem::size_of_val(&paths)
+                + 2 * POINTER_BYTE_SIZE
+                + 1 * 3
+                + 2 * POINTER_BYTE_SIZE
+                + 1 * 6
+        );
+    }
+
+    #[test]
+    fn test_shared_path() {
+        use std::sync::Arc;
+
+        let path: Arc<Path> = Path::new("foo/bar").into();
+        assert_size_of_val_eq!(
+            path,
+            mem::size_of_val(&path) + 2 * POINTER_BYTE_SIZE /* counters */ + 1 * 7
+        );
+
+        let paths = (Arc::clone(&path), path);
+        assert_size_of_val_eq!(
+            paths,
+            2 * 2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 1 * 7
+        );
     }
 }
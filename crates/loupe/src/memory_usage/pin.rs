@@ -0,0 +1,55 @@
+#[cfg(test)]
+use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::ops::Deref;
+use std::pin::Pin;
+use std::ptr;
+
+/// Measuring never moves the pinned value out, so it's fine to
+/// delegate to the pointer, whatever it is (`Box`, `&mut`, `Arc`…).
+impl<P> MemoryUsage for Pin<P>
+where
+    P: Deref + MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        // SAFETY: `Pin<P>` is guaranteed to have the same memory
+        // layout as `P`, and the pointer is only read.
+        let pointer = unsafe { &*ptr::from_ref(self).cast::<P>() };
+
+        pointer.size_of_val(tracker)
+    }
+}
+
+#[cfg(test)]
+mod test_pin_types {
+    use super::*;
+    use std::mem;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_pin_box() {
+        let pin: Pin<Box<i32>> = Box::pin(1);
+        assert_size_of_val_eq!(pin, POINTER_BYTE_SIZE + 4);
+
+        let pin: Pin<Box<String>> = Box::pin(String::from("abc"));
+        assert_size_of_val_eq!(pin, POINTER_BYTE_SIZE + mem::size_of::<String>() + 1 * 3);
+    }
+
+    #[test]
+    fn test_pin_mutable_reference() {
+        let mut value = 1i64;
+        let pin: Pin<&mut i64> = Pin::new(&mut value);
+        assert_size_of_val_eq!(pin, POINTER_BYTE_SIZE + 8);
+    }
+
+    #[test]
+    fn test_pin_arc() {
+        let empty_arc_size = mem::size_of_val(&Arc::new(()));
+
+        let pin: Pin<Arc<i32>> = Arc::pin(1);
+        assert_size_of_val_eq!(
+            pin,
+            empty_arc_size + 2 * POINTER_BYTE_SIZE /* counters */ + 4
+        );
+    }
+}
@@ -2,6 +2,10 @@
 use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
 use crate::{MemoryUsage, MemoryUsageTracker};
 use std::mem;
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
 
 macro_rules! impl_memory_usage_for_numeric {
     ( $type:ty ) => {
@@ -18,9 +22,59 @@ macro_rules! impl_memory_usage_for_numeric {
 }
 
 impl_memory_usage_for_numeric!(
-    bool, char, f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize
+    bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
 );
 
+impl_memory_usage_for_numeric!(
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    NonZeroI128,
+    NonZeroIsize,
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroUsize,
+);
+
+/// Implement `MemoryUsage` for types that own no allocation, like the
+/// primitive types: their size is their shallow size, `mem::size_of_val`.
+///
+/// It saves writing these impls by hand for plain-old-data types, e.g.
+/// types generated by another tool. Because of the orphan rule, the
+/// types must be defined in the crate using the macro: a type of
+/// another crate needs a wrapper. Generic types take their parameters
+/// without bounds.
+///
+/// ```rust
+/// mod ext {
+///     pub struct Vec3(pub f32, pub f32, pub f32);
+///     pub struct Id<T>(pub u64, pub std::marker::PhantomData<T>);
+/// }
+///
+/// loupe::opaque_impl!(ext::Vec3, ext::Id<T>);
+///
+/// assert_eq!(loupe::size_of_val(&ext::Vec3(0., 1., 2.)), 12);
+/// assert_eq!(loupe::size_of_val(&ext::Id::<String>(1, std::marker::PhantomData)), 8);
+/// ```
+#[macro_export]
+macro_rules! opaque_impl {
+    ( $( $( $segment:ident )::+ $( < $( $parameter:ident ),+ $(,)? > )? ),+ $(,)? ) => {
+        $(
+            impl $( < $( $parameter ),+ > )? $crate::MemoryUsage
+                for $( $segment )::+ $( < $( $parameter ),+ > )?
+            {
+                fn size_of_val(&self, _: &mut dyn $crate::MemoryUsageTracker) -> usize {
+                    std::mem::size_of_val(self)
+                }
+            }
+        )+
+    };
+}
+
 #[cfg(test)]
 mod test_numeric_types {
     use super::*;
@@ -47,50 +101,75 @@ mod test_numeric_types {
         test_i16: (1i16) == 2;
         test_i32: (1i32) == 4;
         test_i64: (1i64) == 8;
+        test_i128: (1i128) == 16;
         test_isize: (1isize) == POINTER_BYTE_SIZE;
         test_u8: (1u8) == 1;
         test_u16: (1u16) == 2;
         test_u32: (1u32) == 4;
         test_u64: (1u64) == 8;
+        test_u128: (1u128) == 16;
         test_usize: (1usize) == POINTER_BYTE_SIZE;
+        test_nonzero_i8: (NonZeroI8::new(1).unwrap()) == 1;
+        test_nonzero_i16: (NonZeroI16::new(1).unwrap()) == 2;
+        test_nonzero_i32: (NonZeroI32::new(1).unwrap()) == 4;
+        test_nonzero_i64: (NonZeroI64::new(1).unwrap()) == 8;
+        test_nonzero_i128: (NonZeroI128::new(1).unwrap()) == 16;
+        test_nonzero_isize: (NonZeroIsize::new(1).unwrap()) == POINTER_BYTE_SIZE;
+        test_nonzero_u8: (NonZeroU8::new(1).unwrap()) == 1;
+        test_nonzero_u16: (NonZeroU16::new(1).unwrap()) == 2;
+        test_nonzero_u32: (NonZeroU32::new(1).unwrap()) == 4;
+        test_nonzero_u64: (NonZeroU64::new(1).unwrap()) == 8;
+        test_nonzero_u128: (NonZeroU128::new(1).unwrap()) == 16;
+        test_nonzero_usize: (NonZeroUsize::new(1).unwrap()) == POINTER_BYTE_SIZE;
+        test_option_nonzero_u32: (NonZeroU32::new(1)) == 4;
+        test_option_nonzero_u32_none: (NonZeroU32::new(0)) == 4;
     );
 }
 
-#[rustversion::any(stable(1.51), since(2021-02-01))]
-impl<T, const N: usize> MemoryUsage for [T; N]
-where
-    T: MemoryUsage,
-{
-    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + self
-                .iter()
-                .map(|value| value.size_of_val(tracker) - mem::size_of_val(value))
-                .sum::<usize>()
+#[cfg(test)]
+mod test_opaque_impl {
+    use crate::{assert_size_of_val_eq, MemoryUsage};
+    use std::marker::PhantomData;
+
+    #[allow(dead_code)]
+    mod ext {
+        use std::marker::PhantomData;
+
+        pub struct Color(pub u8, pub u8, pub u8, pub u8);
+
+        pub struct Quat {
+            pub w: f64,
+            pub xyz: [f64; 3],
+        }
+
+        pub struct Pair<K, V>(pub PhantomData<(K, V)>, pub u32);
     }
-}
 
-#[rustversion::any(stable(1.51), since(2021-02-01))]
-#[cfg(test)]
-mod test_array_types {
-    use super::*;
+    crate::opaque_impl!(ext::Color, ext::Quat, ext::Pair<K, V>,);
 
     #[test]
-    fn test_array() {
-        let array: [i16; 0] = [0; 0];
-        assert_size_of_val_eq!(array, 2 * 0);
-
-        let array: [i16; 1] = [0; 1];
-        assert_size_of_val_eq!(array, 2 * 1);
+    fn test_opaque_impl() {
+        assert_size_of_val_eq!(ext::Color(1, 2, 3, 4), 4);
+        assert_size_of_val_eq!(
+            ext::Quat {
+                w: 1.,
+                xyz: [0.; 3]
+            },
+            4 * 8
+        );
+    }
 
-        let array: [i16; 2] = [0; 2];
-        assert_size_of_val_eq!(array, 2 * 2);
+    #[test]
+    fn test_opaque_impl_generic() {
+        // The parameters aren't measured, and don't need to implement
+        // `MemoryUsage`.
+        struct NotMemoryUsage;
 
-        let array: [i16; 3] = [0; 3];
-        assert_size_of_val_eq!(array, 2 * 3);
+        let pair: ext::Pair<Vec<String>, NotMemoryUsage> = ext::Pair(PhantomData, 1);
+        assert_size_of_val_eq!(pair, 4);
 
-        let array: [[i16; 3]; 5] = [[0; 3]; 5];
-        assert_size_of_val_eq!(array, 2 * 3 * 5);
+        let boxed: Box<dyn MemoryUsage> = Box::new(ext::Pair::<u8, u8>(PhantomData, 1));
+        assert_eq!(crate::size_of_val(&boxed), 2 * crate::POINTER_BYTE_SIZE + 4);
     }
 }
 
@@ -101,29 +180,31 @@ impl MemoryUsage for () {
 }
 
 macro_rules! impl_memory_usage_for_tuple {
-    ( $first_type:ident $(,)* ) => {};
+    () => {};
 
-    ( $first_type:ident $( , $types:ident )+ $(,)* ) => {
-        impl< $first_type $( , $types )+ > MemoryUsage for ( $first_type $( , $types )+ )
+    ( $first_type:ident $( , $types:ident )* $(,)* ) => {
+        impl< $first_type $( , $types )* > MemoryUsage for ( $first_type , $( $types ),* )
         where
             $first_type: MemoryUsage,
             $( $types: MemoryUsage ),*
         {
             fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
                 #[allow(non_snake_case)]
-                let ( $first_type $( , $types )+ ) = self;
+                let ( $first_type , $( $types ),* ) = self;
 
                 mem::size_of_val(self)
-                    + $first_type.size_of_val(tracker) - mem::size_of_val($first_type)
-                    $( + $types.size_of_val(tracker) - mem::size_of_val($types) )+
+                    + $crate::heap_size_of_val_with($first_type, tracker)
+                    $( + $crate::heap_size_of_val_with($types, tracker) )*
             }
         }
 
-        impl_memory_usage_for_tuple!( $( $types ),+ );
+        impl_memory_usage_for_tuple!( $( $types ),* );
     };
 }
 
-impl_memory_usage_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+impl_memory_usage_for_tuple!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z
+);
 
 #[cfg(test)]
 mod test_tuple_types {
@@ -135,6 +216,36 @@ mod test_tuple_types {
         assert_size_of_val_eq!(empty, 0);
     }
 
+    #[test]
+    fn test_one_tuple() {
+        let tuple: (i8,) = (1,);
+        assert_size_of_val_eq!(tuple, 1 /* i8 */);
+
+        let tuple: (String,) = (String::from("abc"),);
+        assert_size_of_val_eq!(tuple, 3 * POINTER_BYTE_SIZE + 1 * 3 /* str */);
+    }
+
+    #[test]
+    fn test_large_tuple() {
+        let tuple = (
+            1u8, 2u64, 3u8, 4u64, 5u8, 6u64, 7u8, 8u64, 9u8, 10u64, 11u8, 12u64, 13u8, 14u64, 15u8,
+            16u64,
+        );
+        // Padding depends on how fields are laid out, but is always
+        // counted.
+        assert_size_of_val_eq!(tuple, mem::size_of_val(&tuple));
+        assert!(mem::size_of_val(&tuple) >= 8 * 1 /* u8 */ + 8 * 8 /* u64 */);
+        assert_eq!(mem::size_of_val(&tuple) % mem::align_of::<u64>(), 0);
+
+        #[rustfmt::skip]
+        let tuple = (
+            String::from("abc"),
+            1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8, 9u8, 10u8, 11u8, 12u8, 13u8, 14u8, 15u8, 16u8,
+            17u8, 18u8, 19u8, 20u8, 21u8, 22u8, 23u8, 24u8, 25u8,
+        );
+        assert_size_of_val_eq!(tuple, mem::size_of_val(&tuple) + 1 * 3 /* str */);
+    }
+
     #[test]
     fn test_tuple() {
         let tuple: (i8, i8) = (1, 2);
@@ -164,6 +275,12 @@ mod test_tuple_types {
             2 * POINTER_BYTE_SIZE + 1 * 1 /* str */ + 2 * POINTER_BYTE_SIZE + 1 * 2, /* str */
         );
 
+        let tuple: (u8, u128) = (1, 2);
+        assert_size_of_val_eq!(
+            tuple,
+            1 /* u8 */ + (mem::align_of::<u128>() - 1) /* padding */ + 16, /* u128 */
+        );
+
         let tuple: (&str, (i64, i64, i8)) = ("abc", (1, 2, 3));
         assert_size_of_val_eq!(
             tuple,
@@ -0,0 +1,71 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::process::Command;
+
+/// The layout of a `Command` is platform-specific: this is an estimate
+/// made of the bytes of the program, arguments, environment overrides
+/// and current directory it holds.
+impl MemoryUsage for Command {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self.get_program().size_of_val(tracker)
+            + self
+                .get_args()
+                .map(|argument| argument.size_of_val(tracker))
+                .sum::<usize>()
+            + self
+                .get_envs()
+                .map(|(key, value)| {
+                    key.size_of_val(tracker)
+                        + value.map(|value| value.size_of_val(tracker)).unwrap_or(0)
+                })
+                .sum::<usize>()
+            + self
+                .get_current_dir()
+                .map(|directory| directory.size_of_val(tracker))
+                .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test_process_types {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let command = Command::new("abc");
+        assert_size_of_val_eq!(command, mem::size_of_val(&command) + 1 * 3);
+
+        let mut command = Command::new("abc");
+        command
+            .arg("de")
+            .env("FG", "hij")
+            .env_remove("KL")
+            .current_dir("mno");
+        assert_size_of_val_eq!(
+            command,
+            mem::size_of_val(&command) + 1 * 3 + 1 * 2 + 1 * (2 + 3) + 1 * 2 + 1 * 3
+        );
+    }
+
+    #[test]
+    fn test_command_grows_with_arguments() {
+        let mut command = Command::new("job");
+        let before = crate::size_of_val(&command);
+
+        let argument = "x".repeat(1024);
+
+        for _ in 0..16 {
+            command.arg(&argument);
+        }
+
+        command.env("JOB_CONTEXT", &argument);
+
+        assert_eq!(
+            crate::size_of_val(&command) - before,
+            16 * 1024 + 1 * 11 + 1024
+        );
+    }
+}
@@ -1,8 +1,11 @@
 #[cfg(test)]
 use crate::assert_size_of_val_eq;
-use crate::{MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
+use crate::{
+    memory_usage::size_of_pointee, policy::follows_references, MemoryUsage, MemoryUsageTracker,
+    POINTER_BYTE_SIZE,
+};
 use std::mem;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 
 impl<T> MemoryUsage for *const T {
     fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
@@ -50,29 +53,39 @@ mod test_pointer_types {
 
 impl<T> MemoryUsage for &T
 where
-    T: MemoryUsage,
+    T: MemoryUsage + ?Sized,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + if tracker.track(*self as *const T as *const ()) {
-                (*self).size_of_val(tracker)
-            } else {
-                0
-            }
+        if !follows_references(tracker) {
+            return mem::size_of::<Self>();
+        }
+
+        mem::size_of::<Self>()
+            + size_of_pointee(
+                tracker,
+                ptr::from_ref::<T>(*self),
+                mem::size_of_val(*self),
+                |tracker| (*self).size_of_val(tracker),
+            )
     }
 }
 
 impl<T> MemoryUsage for &mut T
 where
-    T: MemoryUsage,
+    T: MemoryUsage + ?Sized,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + if tracker.track(*self as *const T as *const ()) {
-                MemoryUsage::size_of_val(*self, tracker)
-            } else {
-                0
-            }
+        if !follows_references(tracker) {
+            return mem::size_of::<Self>();
+        }
+
+        mem::size_of::<Self>()
+            + size_of_pointee(
+                tracker,
+                ptr::from_ref::<T>(&**self),
+                mem::size_of_val(&**self),
+                |tracker| MemoryUsage::size_of_val(*self, tracker),
+            )
     }
 }
 
@@ -0,0 +1,100 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{
+    memory_usage::size_of_pointee,
+    policy::{allocation_size, shared_size},
+    MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE,
+};
+use std::any;
+use std::mem;
+use std::rc::{Rc, Weak};
+
+impl<T> MemoryUsage for Rc<T>
+where
+    T: MemoryUsage + ?Sized,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        // The allocation starts with the strong and weak counters,
+        // padded to the alignment of the value.
+        let counters = (2 * POINTER_BYTE_SIZE).next_multiple_of(mem::align_of_val(&**self));
+        let bytes = allocation_size(tracker, counters + mem::size_of_val(&**self));
+
+        let size = size_of_pointee(tracker, Rc::as_ptr(self), bytes, |tracker| {
+            tracker.record(any::type_name::<Self>(), bytes);
+
+            bytes - mem::size_of_val(&**self) + self.as_ref().size_of_val(tracker)
+        });
+
+        mem::size_of_val(self) + shared_size(tracker, size, Rc::strong_count(self))
+    }
+}
+
+/// A `Weak` doesn't own its value: only the handle is counted, the
+/// value is charged to the `Rc`s. It also means cycles made of `Weak`
+/// never loop.
+impl<T> MemoryUsage for Weak<T>
+where
+    T: ?Sized,
+{
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+#[cfg(test)]
+mod test_rc_types {
+    use super::*;
+
+    #[test]
+    fn test_rc() {
+        let rc: Rc<i32> = Rc::new(1);
+        assert_size_of_val_eq!(rc, POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + 4);
+
+        let rc: Rc<Vec<u8>> = Rc::new(vec![1, 2, 3]);
+        assert_size_of_val_eq!(
+            rc,
+            POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + mem::size_of::<Vec<u8>>() + 1 * 3
+        );
+
+        let rc: Rc<[u8]> = Rc::from(vec![1, 2, 3]);
+        assert_size_of_val_eq!(rc, 2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + 1 * 3);
+    }
+
+    #[test]
+    fn test_rc_clones() {
+        let rc: Rc<Vec<u8>> = Rc::new(vec![1, 2, 3]);
+        let clones = (Rc::clone(&rc), rc);
+
+        assert_size_of_val_eq!(
+            clones,
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + mem::size_of::<Vec<u8>>() + 1 * 3 + 0, /* the second clone is not counted */
+        );
+    }
+
+    #[test]
+    fn test_rc_slice_clones() {
+        let rc: Rc<[u16]> = Rc::from(vec![1, 2, 3]);
+        assert_eq!(mem::size_of_val(&rc), 2 * POINTER_BYTE_SIZE);
+
+        let clones = (Rc::clone(&rc), rc);
+        assert_size_of_val_eq!(
+            clones,
+            2 * 2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 2 * 3
+        );
+    }
+
+    #[test]
+    fn test_weak() {
+        let rc: Rc<Vec<u8>> = Rc::new(vec![1, 2, 3]);
+        let weak: Weak<Vec<u8>> = Rc::downgrade(&rc);
+        assert_size_of_val_eq!(weak, POINTER_BYTE_SIZE);
+
+        let dangling: Weak<Vec<u8>> = Weak::new();
+        assert_size_of_val_eq!(dangling, POINTER_BYTE_SIZE);
+
+        assert_size_of_val_eq!(
+            (weak, rc),
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + mem::size_of::<Vec<u8>>() + 1 * 3
+        );
+    }
+}
@@ -1,32 +1,23 @@
 #[cfg(test)]
 use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
-use crate::{MemoryUsage, MemoryUsageTracker};
+use crate::{memory_usage::heap_size_of_values, MemoryUsage, MemoryUsageTracker};
 use std::mem;
 
-impl<T> MemoryUsage for [T]
+impl<T, const N: usize> MemoryUsage for [T; N]
 where
     T: MemoryUsage,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + self
-                .iter()
-                .map(|value| value.size_of_val(tracker) - mem::size_of_val(value))
-                .sum::<usize>()
+        self.as_slice().size_of_val(tracker)
     }
 }
 
-impl<T> MemoryUsage for &[T]
+impl<T> MemoryUsage for [T]
 where
     T: MemoryUsage,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + if tracker.track(*self as *const [T] as *const ()) {
-                MemoryUsage::size_of_val(*self, tracker)
-            } else {
-                0
-            }
+        mem::size_of_val(self) + heap_size_of_values(tracker, self)
     }
 }
 
@@ -34,7 +25,6 @@ where
 mod test_slice_types {
     use super::*;
 
-    #[rustversion::any(stable(1.51), since(2021-02-01))]
     #[test]
     fn test_slice() {
         assert_size_of_val_eq!([1i16], 2 * 1);
@@ -56,4 +46,53 @@ mod test_slice_types {
         let slice: &[i16] = &[1, 2, 3];
         assert_size_of_val_eq!(slice, 2 * POINTER_BYTE_SIZE + 2 * 3);
     }
+
+    #[test]
+    fn test_array() {
+        let array: [i16; 0] = [0; 0];
+        assert_size_of_val_eq!(array, 2 * 0);
+
+        let array: [i16; 1] = [0; 1];
+        assert_size_of_val_eq!(array, 2 * 1);
+
+        let array: [i16; 2] = [0; 2];
+        assert_size_of_val_eq!(array, 2 * 2);
+
+        let array: [i16; 3] = [0; 3];
+        assert_size_of_val_eq!(array, 2 * 3);
+
+        let array: [[i16; 3]; 5] = [[0; 3]; 5];
+        assert_size_of_val_eq!(array, 2 * 3 * 5);
+    }
+
+    #[test]
+    fn test_array_of_strings() {
+        let array: [String; 4] = [
+            String::from("a"),
+            String::from("bc"),
+            String::new(),
+            String::from("def"),
+        ];
+        assert_size_of_val_eq!(
+            array,
+            4 * 3 * POINTER_BYTE_SIZE + 1 * (1 + 2 + 0 + 3) /* str */
+        );
+    }
+
+    #[test]
+    fn test_array_of_arrays_of_vectors() {
+        let array: [[Vec<u8>; 2]; 2] = [[vec![1], vec![2, 3]], [vec![], vec![4, 5, 6]]];
+        assert_size_of_val_eq!(array, 2 * 2 * 3 * POINTER_BYTE_SIZE + 1 * (1 + 2 + 0 + 3));
+    }
+
+    #[test]
+    fn test_array_of_shared_references() {
+        let first = [1u8; 64];
+        let second = [2u8; 64];
+
+        // The first two entries alias: the pointee is counted once, and
+        // every reference is counted exactly once.
+        let array: [&[u8; 64]; 3] = [&first, &first, &second];
+        assert_size_of_val_eq!(array, 3 * POINTER_BYTE_SIZE + 2 * 64);
+    }
 }
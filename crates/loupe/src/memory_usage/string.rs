@@ -1,17 +1,31 @@
 #[cfg(test)]
 use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
-use crate::{MemoryUsage, MemoryUsageTracker};
+use crate::{
+    memory_usage::track_buffer, policy::buffer_size, CollectionPolicy, MemoryUsage,
+    MemoryUsageTracker,
+};
+use std::any;
 use std::mem;
 
-impl MemoryUsage for &str {
+impl MemoryUsage for str {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self) + self.as_bytes().size_of_val(tracker)
+        self.as_bytes().size_of_val(tracker)
     }
 }
 
 impl MemoryUsage for String {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        self.as_str().size_of_val(tracker)
+        let bytes = buffer_size(
+            tracker,
+            CollectionPolicy::Len,
+            self.len(),
+            self.capacity(),
+            1,
+        );
+        tracker.record(any::type_name::<Self>(), bytes);
+        track_buffer(tracker, self.as_ptr().cast(), bytes);
+
+        mem::size_of_val(self) + bytes
     }
 }
 
@@ -40,18 +54,63 @@ mod test_string_types {
     #[test]
     fn test_string() {
         let string: String = "".to_string();
-        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 0);
+        assert_size_of_val_eq!(string, 3 * POINTER_BYTE_SIZE + 1 * 0);
 
         let string: String = "a".to_string();
-        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 1);
+        assert_size_of_val_eq!(string, 3 * POINTER_BYTE_SIZE + 1 * 1);
 
         let string: String = "ab".to_string();
-        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 2);
+        assert_size_of_val_eq!(string, 3 * POINTER_BYTE_SIZE + 1 * 2);
 
         let string: String = "abc".to_string();
-        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3);
+        assert_size_of_val_eq!(string, 3 * POINTER_BYTE_SIZE + 1 * 3);
 
         let string: String = "…".to_string();
+        assert_size_of_val_eq!(string, 3 * POINTER_BYTE_SIZE + 1 * 3);
+    }
+
+    #[test]
+    fn test_boxed_str() {
+        let string: Box<str> = "".into();
+        assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 0);
+
+        let string: Box<str> = "abc".into();
         assert_size_of_val_eq!(string, 2 * POINTER_BYTE_SIZE + 1 * 3);
     }
+
+    #[test]
+    fn test_shared_str() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let string: Rc<str> = "abc".into();
+        assert_size_of_val_eq!(
+            string,
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 1 * 3
+        );
+
+        let string: Arc<str> = "abc".into();
+        let empty_arc_size = mem::size_of_val(&string);
+        assert_size_of_val_eq!(
+            (Arc::clone(&string), string),
+            2 * empty_arc_size + 2 * POINTER_BYTE_SIZE /* counters */ + 1 * 3 + 0, /* the second clone is not counted */
+        );
+    }
+
+    #[test]
+    fn test_cow_str() {
+        use std::borrow::Cow;
+
+        let string: Cow<str> = Cow::Borrowed("abc");
+        assert_size_of_val_eq!(string, mem::size_of_val(&string) + 1 * 3);
+
+        // The capacity is counted with the `Capacity` policy, like the
+        // capacity of a `String`.
+        let string: Cow<str> = Cow::Owned(String::with_capacity(64));
+        assert_size_of_val_eq!(string, mem::size_of_val(&string) + 1 * 0);
+        assert_eq!(
+            crate::measure_with(&string, crate::MeasureOptions::default()).heap,
+            1 * 64
+        );
+    }
 }
@@ -1,13 +1,21 @@
 #[cfg(test)]
-use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
-use crate::{MemoryUsage, MemoryUsageTracker};
+use crate::assert_size_of_val_eq;
+use crate::{
+    memory_usage::size_of_pointee,
+    policy::{allocation_size, shared_size},
+    MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE,
+};
+use std::any;
 use std::mem;
+#[rustversion::since(1.94)]
+use std::sync::LazyLock;
 use std::sync::{
     atomic::{
-        AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32,
-        AtomicU64, AtomicU8, AtomicUsize,
+        AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16,
+        AtomicU32, AtomicU64, AtomicU8, AtomicUsize,
     },
-    Arc, Mutex, RwLock, Weak,
+    mpsc::{Receiver, Sender, SyncSender},
+    Arc, Mutex, OnceLock, RwLock, Weak,
 };
 
 macro_rules! impl_memory_usage_for_numeric {
@@ -38,33 +46,42 @@ impl_memory_usage_for_numeric!(
     AtomicUsize,
 );
 
+/// The pointer may be dangling, so it's never dereferenced.
+impl<T> MemoryUsage for AtomicPtr<T> {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        POINTER_BYTE_SIZE
+    }
+}
+
 impl<T> MemoryUsage for Arc<T>
 where
     T: MemoryUsage + ?Sized,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self)
-            + if tracker.track(Arc::as_ptr(self) as *const ()) {
-                self.as_ref().size_of_val(tracker)
-            } else {
-                0
-            }
+        // The allocation starts with the strong and weak counters,
+        // padded to the alignment of the value.
+        let counters = (2 * POINTER_BYTE_SIZE).next_multiple_of(mem::align_of_val(&**self));
+        let bytes = allocation_size(tracker, counters + mem::size_of_val(&**self));
+
+        let size = size_of_pointee(tracker, Arc::as_ptr(self), bytes, |tracker| {
+            tracker.record(any::type_name::<Self>(), bytes);
+
+            bytes - mem::size_of_val(&**self) + self.as_ref().size_of_val(tracker)
+        });
+
+        mem::size_of_val(self) + shared_size(tracker, size, Arc::strong_count(self))
     }
 }
 
+/// A `Weak` doesn't own its value: only the handle is counted, the
+/// value is charged to the `Arc`s. It also means cycles made of `Weak`
+/// never loop.
 impl<T> MemoryUsage for Weak<T>
 where
-    T: MemoryUsage + ?Sized,
+    T: ?Sized,
 {
-    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
         mem::size_of_val(self)
-            + if tracker.track(Weak::as_ptr(self) as *const ()) {
-                Weak::upgrade(self)
-                    .map(|arc| arc.as_ref().size_of_val(tracker))
-                    .unwrap_or(0)
-            } else {
-                0
-            }
     }
 }
 
@@ -73,7 +90,7 @@ where
     T: MemoryUsage + ?Sized,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self) + self.lock().unwrap().size_of_val(tracker)
+        mem::size_of_val(self) + crate::heap_size_of_val_with(&*self.lock().unwrap(), tracker)
     }
 }
 
@@ -82,10 +99,56 @@ where
     T: MemoryUsage + ?Sized,
 {
     fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
-        mem::size_of_val(self) + self.read().unwrap().size_of_val(tracker)
+        mem::size_of_val(self) + crate::heap_size_of_val_with(&*self.read().unwrap(), tracker)
     }
 }
 
+/// The value is read with `get`, so measuring never blocks nor
+/// initializes the lock.
+impl<T> MemoryUsage for OnceLock<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + self
+                .get()
+                .map(|value| crate::heap_size_of_val_with(value, tracker))
+                .unwrap_or(0)
+    }
+}
+
+/// Measuring never forces the evaluation: the value is only counted
+/// once it has been materialized.
+#[rustversion::since(1.94)]
+impl<T, F> MemoryUsage for LazyLock<T, F>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + LazyLock::get(self)
+                .map(|value| crate::heap_size_of_val_with(value, tracker))
+                .unwrap_or(0)
+    }
+}
+
+macro_rules! impl_memory_usage_for_channel {
+    ( $( $type:ident ),+ $(,)* ) => {
+        $(
+            /// The channel's queue can't be inspected from a handle: only
+            /// the handle is counted, messages in flight are not.
+            impl<T> MemoryUsage for $type<T> {
+                fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                    mem::size_of_val(self)
+                }
+            }
+        )+
+    };
+}
+
+impl_memory_usage_for_channel!(Sender, SyncSender, Receiver);
+
 #[cfg(test)]
 mod test_sync_types {
     use super::*;
@@ -115,6 +178,8 @@ mod test_sync_types {
         test_atomic_u32: (AtomicU32::new(1u32)) == 4;
         test_atomic_u64: (AtomicU64::new(1u64)) == 8;
         test_atomic_usize: (AtomicUsize::new(1usize)) == POINTER_BYTE_SIZE;
+        test_atomic_ptr: (AtomicPtr::new(&mut 1i64 as *mut _)) == POINTER_BYTE_SIZE;
+        test_atomic_ptr_null: (AtomicPtr::<i64>::new(std::ptr::null_mut())) == POINTER_BYTE_SIZE;
     );
 
     #[test]
@@ -122,10 +187,32 @@ mod test_sync_types {
         let empty_arc_size = mem::size_of_val(&Arc::new(()));
 
         let arc: Arc<i32> = Arc::new(1);
-        assert_size_of_val_eq!(arc, empty_arc_size + 4);
+        assert_size_of_val_eq!(
+            arc,
+            empty_arc_size + 2 * POINTER_BYTE_SIZE /* counters */ + 4
+        );
 
         let arc: Arc<Option<i32>> = Arc::new(Some(1));
-        assert_size_of_val_eq!(arc, empty_arc_size + POINTER_BYTE_SIZE + 4);
+        assert_size_of_val_eq!(
+            arc,
+            empty_arc_size + 2 * POINTER_BYTE_SIZE /* counters */ + 4 /* variant and padding */ + 4
+        );
+    }
+
+    #[test]
+    fn test_arc_slice() {
+        let arc: Arc<[u16]> = Arc::from(vec![1, 2, 3]);
+        assert_eq!(mem::size_of_val(&arc), 2 * POINTER_BYTE_SIZE);
+        assert_size_of_val_eq!(
+            arc,
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 2 * 3
+        );
+
+        let clones = (Arc::clone(&arc), arc);
+        assert_size_of_val_eq!(
+            clones,
+            2 * 2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 2 * 3
+        );
     }
 
     #[test]
@@ -134,11 +221,15 @@ mod test_sync_types {
 
         let arc: Arc<i32> = Arc::new(1);
         let weak: Weak<i32> = Arc::downgrade(&arc);
-        assert_size_of_val_eq!(weak, empty_weak_size + 4);
+        assert_size_of_val_eq!(weak, empty_weak_size);
 
         let arc: Arc<Option<i32>> = Arc::new(Some(1));
         let weak: Weak<Option<i32>> = Arc::downgrade(&arc);
-        assert_size_of_val_eq!(weak, empty_weak_size + POINTER_BYTE_SIZE + 4);
+        assert_size_of_val_eq!(weak, empty_weak_size);
+        assert_size_of_val_eq!(
+            (weak, arc),
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 4 /* variant and padding */ + 4
+        );
 
         let weak: Weak<i32> = {
             let arc: Arc<i32> = Arc::new(5);
@@ -149,23 +240,60 @@ mod test_sync_types {
 
     #[test]
     fn test_mutex() {
-        let empty_mutex_size = mem::size_of_val(&Mutex::new(()));
-
         let mutex: Mutex<i32> = Mutex::new(1);
-        assert_size_of_val_eq!(mutex, empty_mutex_size + 4);
+        assert_size_of_val_eq!(mutex, mem::size_of_val(&mutex));
 
         let mutex: Mutex<Option<i32>> = Mutex::new(Some(1));
-        assert_size_of_val_eq!(mutex, empty_mutex_size + 2 * POINTER_BYTE_SIZE + 4);
+        assert_size_of_val_eq!(mutex, mem::size_of_val(&mutex));
+
+        let mutex: Mutex<Vec<u8>> = Mutex::new(vec![1, 2, 3]);
+        assert_size_of_val_eq!(mutex, mem::size_of_val(&mutex) + 1 * 3);
     }
 
     #[test]
     fn test_rwlock() {
-        let empty_rwlock_size = mem::size_of_val(&RwLock::new(()));
-
         let rwlock: RwLock<i32> = RwLock::new(1);
-        assert_size_of_val_eq!(rwlock, empty_rwlock_size + 4);
+        assert_size_of_val_eq!(rwlock, mem::size_of_val(&rwlock));
 
         let rwlock: RwLock<Option<i32>> = RwLock::new(Some(1));
-        assert_size_of_val_eq!(rwlock, empty_rwlock_size + 2 * POINTER_BYTE_SIZE + 4);
+        assert_size_of_val_eq!(rwlock, mem::size_of_val(&rwlock));
+
+        let rwlock: RwLock<Vec<u8>> = RwLock::new(vec![1, 2, 3]);
+        assert_size_of_val_eq!(rwlock, mem::size_of_val(&rwlock) + 1 * 3);
+    }
+
+    #[test]
+    fn test_oncelock() {
+        let lock: OnceLock<Vec<u8>> = OnceLock::new();
+        assert_size_of_val_eq!(lock, mem::size_of_val(&lock));
+
+        lock.set(vec![1, 2, 3]).unwrap();
+        assert_size_of_val_eq!(lock, mem::size_of_val(&lock) + 1 * 3);
+    }
+
+    #[rustversion::since(1.94)]
+    #[test]
+    fn test_lazylock() {
+        let lock: LazyLock<Vec<u8>> = LazyLock::new(|| vec![1, 2, 3]);
+        assert_size_of_val_eq!(lock, mem::size_of_val(&lock));
+        assert!(LazyLock::get(&lock).is_none());
+
+        LazyLock::force(&lock);
+        assert_size_of_val_eq!(lock, mem::size_of_val(&lock) + 1 * 3);
+    }
+
+    #[test]
+    fn test_channel() {
+        use std::sync::mpsc;
+
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        sender.send(vec![0; 1024]).unwrap();
+        assert_size_of_val_eq!(sender, mem::size_of_val(&sender));
+        assert_size_of_val_eq!(receiver, mem::size_of_val(&receiver));
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(1);
+        sender.send(vec![0; 1024]).unwrap();
+        assert_size_of_val_eq!(sender, mem::size_of_val(&sender));
+        assert_size_of_val_eq!(receiver, mem::size_of_val(&receiver));
     }
 }
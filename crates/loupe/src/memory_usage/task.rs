@@ -0,0 +1,71 @@
+#[cfg(test)]
+use crate::{assert_size_of_val_eq, POINTER_BYTE_SIZE};
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::task::{Poll, RawWaker, Waker};
+
+impl<T> MemoryUsage for Poll<T>
+where
+    T: MemoryUsage,
+{
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+            + match self {
+                Poll::Ready(value) => crate::heap_size_of_val_with(value, tracker),
+                Poll::Pending => 0,
+            }
+    }
+}
+
+/// The data of a waker is only known by its vtable: only the handle is
+/// counted.
+impl MemoryUsage for Waker {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+/// The data of a waker is only known by its vtable: only the handle is
+/// counted.
+impl MemoryUsage for RawWaker {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+#[cfg(test)]
+mod test_task_types {
+    use super::*;
+    use std::ptr;
+    use std::task::RawWakerVTable;
+
+    #[test]
+    fn test_poll() {
+        let poll: Poll<i8> = Poll::Pending;
+        assert_size_of_val_eq!(poll, 1 /* variant */ + 1 /* padding */);
+
+        let poll: Poll<i8> = Poll::Ready(1);
+        assert_size_of_val_eq!(poll, 1 /* variant */ + 1 /* i8 */);
+
+        let poll: Poll<Vec<u8>> = Poll::Pending;
+        assert_size_of_val_eq!(poll, mem::size_of::<Poll<Vec<u8>>>());
+
+        let poll: Poll<Vec<u8>> = Poll::Ready(vec![1, 2, 3]);
+        assert_size_of_val_eq!(poll, mem::size_of::<Poll<Vec<u8>>>() + 1 * 3);
+    }
+
+    #[test]
+    fn test_waker() {
+        let waker = Waker::noop().clone();
+        assert_size_of_val_eq!(waker, 2 * POINTER_BYTE_SIZE);
+    }
+
+    #[test]
+    fn test_raw_waker() {
+        const VTABLE: RawWakerVTable =
+            RawWakerVTable::new(|data| RawWaker::new(data, &VTABLE), |_| {}, |_| {}, |_| {});
+
+        let raw_waker = RawWaker::new(ptr::null(), &VTABLE);
+        assert_size_of_val_eq!(raw_waker, 2 * POINTER_BYTE_SIZE);
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::time::{Duration, Instant, SystemTime, SystemTimeError};
+
+macro_rules! impl_memory_usage_for_time {
+    ( $type:ty ) => {
+        impl MemoryUsage for $type {
+            fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+            }
+        }
+    };
+
+    ( $( $type:ty ),+ $(,)* ) => {
+        $( impl_memory_usage_for_time!( $type ); )+
+    }
+}
+
+impl_memory_usage_for_time!(Duration, Instant, SystemTime, SystemTimeError);
+
+#[cfg(test)]
+mod test_time_types {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    macro_rules! test_memory_usage_for_time {
+        ($test_name:ident: ($value:expr) == $expected:expr) => {
+            #[test]
+            fn $test_name() {
+                assert_size_of_val_eq!($value, $expected);
+            }
+        };
+
+        ( $( $test_name:ident: ($value:expr) == $expected:expr );+ $(;)* ) => {
+            $( test_memory_usage_for_time!( $test_name: ($value) == $expected); )+
+        }
+    }
+
+    test_memory_usage_for_time!(
+        test_duration: (Duration::from_millis(42)) == mem::size_of::<Duration>();
+        test_instant: (Instant::now()) == mem::size_of::<Instant>();
+        test_system_time: (UNIX_EPOCH + Duration::from_secs(42)) == mem::size_of::<SystemTime>();
+        test_system_time_error: (
+            UNIX_EPOCH.duration_since(UNIX_EPOCH + Duration::from_secs(42)).unwrap_err()
+        ) == mem::size_of::<SystemTimeError>();
+    );
+}
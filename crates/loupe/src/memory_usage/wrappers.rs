@@ -0,0 +1,155 @@
+#[cfg(test)]
+use crate::assert_size_of_val_eq;
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// A value measured by its shallow size only, `mem::size_of::<T>()`,
+/// whatever it owns.
+///
+/// It's the `loupe(opaque)` attribute for a field of a type that can't
+/// be annotated, e.g. a type of another crate: the field is wrapped when
+/// the value is constructed instead. It doesn't even require `T` to
+/// implement `MemoryUsage`.
+///
+/// ```rust
+/// use loupe::{MemoryUsage, Opaque};
+///
+/// #[derive(MemoryUsage)]
+/// struct Parser {
+///     // The cache is shared with other parsers, it's measured elsewhere.
+///     cache: Opaque<Vec<u8>>,
+/// }
+///
+/// let parser = Parser {
+///     cache: Opaque::from(vec![0; 1024]),
+/// };
+///
+/// assert_eq!(parser.cache.len(), 1024);
+/// assert_eq!(loupe::size_of_val(&parser), std::mem::size_of::<Parser>());
+/// ```
+#[derive(Clone, Debug)]
+#[repr(transparent)]
+pub struct Opaque<T>(pub T);
+
+impl<T> Opaque<T> {
+    /// Unwrap the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> MemoryUsage for Opaque<T> {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of::<T>()
+    }
+}
+
+/// A value that isn't measured at all: its size is zero.
+///
+/// As a field, its inline bytes are still part of the size of the
+/// container, like with the `loupe(skip)` attribute, but nothing it owns
+/// is measured. It doesn't require `T` to implement `MemoryUsage`.
+///
+/// ```rust
+/// use loupe::{Ignored, MemoryUsage};
+///
+/// #[derive(MemoryUsage)]
+/// struct Connection {
+///     id: u64,
+///     socket: Ignored<std::net::TcpListener>,
+/// }
+///
+/// # fn connect() -> std::io::Result<()> {
+/// let connection = Connection {
+///     id: 1,
+///     socket: Ignored::from(std::net::TcpListener::bind("127.0.0.1:0")?),
+/// };
+///
+/// assert_eq!(loupe::size_of_val(&connection), std::mem::size_of::<Connection>());
+/// assert_eq!(loupe::size_of_val(&connection.socket), 0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[repr(transparent)]
+pub struct Ignored<T>(pub T);
+
+impl<T> Ignored<T> {
+    /// Unwrap the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> MemoryUsage for Ignored<T> {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        0
+    }
+}
+
+macro_rules! impl_wrapper {
+    ( $( $wrapper:ident ),+ ) => {
+        $(
+            impl<T> From<T> for $wrapper<T> {
+                fn from(value: T) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl<T> Deref for $wrapper<T> {
+                type Target = T;
+
+                fn deref(&self) -> &T {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $wrapper<T> {
+                fn deref_mut(&mut self) -> &mut T {
+                    &mut self.0
+                }
+            }
+        )+
+    };
+}
+
+impl_wrapper!(Opaque, Ignored);
+
+#[cfg(test)]
+mod test_wrapper_types {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_ignored_in_hashmap() {
+        // The ignored values count as if they owned nothing.
+        let mut ignored: HashMap<u32, Ignored<String>> = HashMap::new();
+        ignored.insert(1, Ignored::from("abc".to_string()));
+
+        let mut opaque: HashMap<u32, Opaque<String>> = HashMap::new();
+        opaque.insert(1, Opaque::from("abc".to_string()));
+
+        assert_eq!(crate::size_of_val(&ignored), crate::size_of_val(&opaque));
+    }
+
+    #[test]
+    fn test_ignored_in_btreemap() {
+        let mut ignored: BTreeMap<u32, Ignored<String>> = BTreeMap::new();
+        ignored.insert(1, Ignored::from("abc".to_string()));
+
+        let mut opaque: BTreeMap<u32, Opaque<String>> = BTreeMap::new();
+        opaque.insert(1, Opaque::from("abc".to_string()));
+
+        assert_eq!(crate::size_of_val(&ignored), crate::size_of_val(&opaque));
+    }
+
+    #[test]
+    fn test_ignored_in_option() {
+        let option: Option<Ignored<String>> = Some(Ignored::from("abc".to_string()));
+        assert_size_of_val_eq!(option, mem::size_of_val(&option));
+
+        let option: Option<Ignored<String>> = None;
+        assert_size_of_val_eq!(option, mem::size_of_val(&option));
+    }
+}
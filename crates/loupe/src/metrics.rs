@@ -0,0 +1,82 @@
+//! Export memory usage as gauges of the [`metrics`](https://docs.rs/metrics)
+//! facade, e.g. to be scraped by Prometheus. It must be enabled with the
+//! `metrics` feature.
+//!
+//! All the sizes are exported as the [`GAUGE`] gauge, in bytes, with a
+//! `root` label, the name of the measured value, and a `field` label,
+//! the path of a part of the value below the root, like `cache` or
+//! `cache.entries`. The size of the whole value has no `field` label.
+//!
+//! ```rust
+//! use loupe::{MemoryUsage, MemoryUsageBreakdown};
+//!
+//! #[derive(MemoryUsage, MemoryUsageBreakdown)]
+//! struct Cache {
+//!     entries: Vec<String>,
+//! }
+//!
+//! let cache = Cache { entries: vec![String::from("a")] };
+//!
+//! // Sets `loupe_memory_usage_bytes{root="cache"}` and
+//! // `loupe_memory_usage_bytes{root="cache", field="entries"}`.
+//! let tree = cache.breakdown("cache".into(), &mut loupe::DefaultTracker::new());
+//! loupe::metrics::export_breakdown(&tree, 1);
+//! ```
+
+use crate::BreakdownNode;
+
+/// The name of the gauge.
+pub const GAUGE: &str = "loupe_memory_usage_bytes";
+
+fn describe() {
+    ::metrics::describe_gauge!(
+        GAUGE,
+        ::metrics::Unit::Bytes,
+        "Memory used by a value, or by a part of it, as measured by loupe"
+    );
+}
+
+/// Export the sizes of a breakdown tree: the root, named after the
+/// name of the root node, and its parts down to `max_depth`, e.g. only
+/// the fields of the root with a `max_depth` of 1, so that the number
+/// of gauges stays bounded.
+pub fn export_breakdown(tree: &BreakdownNode, max_depth: usize) {
+    describe();
+
+    let root = tree.name.to_string();
+
+    ::metrics::gauge!(GAUGE, "root" => root.clone()).set(tree.total() as f64);
+
+    for child in &tree.children {
+        export_node(&root, child, String::new(), 1, max_depth);
+    }
+}
+
+fn export_node(root: &str, node: &BreakdownNode, mut path: String, depth: usize, max_depth: usize) {
+    if depth > max_depth {
+        return;
+    }
+
+    if !path.is_empty() {
+        path.push('.');
+    }
+
+    path.push_str(&node.name);
+
+    for child in &node.children {
+        export_node(root, child, path.clone(), depth + 1, max_depth);
+    }
+
+    ::metrics::gauge!(GAUGE, "root" => root.to_string(), "field" => path).set(node.total() as f64);
+}
+
+/// Export the sizes of the values of the [`registry`](crate::registry),
+/// as given by its `snapshot`. It requires the `registry` feature too.
+#[cfg(feature = "registry")]
+pub fn export_registry() {
+    describe();
+
+    for (root, size) in crate::registry::snapshot() {
+        ::metrics::gauge!(GAUGE, "root" => root).set(size as f64);
+    }
+}
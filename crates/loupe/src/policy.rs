@@ -0,0 +1,258 @@
+//! Choose how the allocations are counted, see
+//! [`measure_with`](crate::measure_with).
+
+use crate::{MemoryUsageTracker, POINTER_BYTE_SIZE};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// How the buffer of a collection is counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollectionPolicy {
+    /// The whole allocated buffer, including the unused capacity: the
+    /// memory actually held by the collection.
+    #[default]
+    Capacity,
+
+    /// The live elements only, as if the buffer were shrunk to fit.
+    Len,
+}
+
+/// How the values shared by several `Rc`s or `Arc`s are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SharedPolicy {
+    /// The whole value is charged to the first owner measured, and
+    /// the other owners reached by the same tracker are charged
+    /// nothing.
+    #[default]
+    FirstOwner,
+
+    /// Each owner is charged its share of the value, i.e. its size
+    /// divided by the strong count, so that owners measured separately
+    /// add up to the whole value, e.g. for capacity planning.
+    ///
+    /// A value holding several clones of an `Rc` or an `Arc` is charged
+    /// one share only. The shares are rounded down: the sum over the
+    /// owners is at most `strong_count - 1` bytes below the size of the
+    /// shared value.
+    Amortized,
+}
+
+/// How the allocations are counted. The buffers are those of `Vec`,
+/// `VecDeque`, `String`, `HashMap`, `HashSet` and `BinaryHeap`, the
+/// allocations those of the buffers, and of `Box`, `Rc` and `Arc`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizePolicy {
+    /// How the buffers of collections are counted.
+    pub collections: CollectionPolicy,
+
+    /// How the values of `Rc` and `Arc` are counted.
+    pub shared: SharedPolicy,
+
+    /// Whether only the owned data is counted: references, like `&T`,
+    /// `&[T]`, `&str`, `Cow::Borrowed` or `Ref`, count their pointer
+    /// and not their value, e.g. so that the tables or the
+    /// configuration borrowed by a value aren't charged to it.
+    pub owned_only: bool,
+
+    /// Whether the allocations are rounded up with the
+    /// [`AllocationModel`] of the options, to account for the overhead
+    /// of the allocator.
+    pub include_allocator_overhead: bool,
+}
+
+/// The memory an allocator actually uses for an allocation: allocators
+/// round allocations up to a size class, and may add headers, so that
+/// the sizes of the values undershoot the memory of the process.
+///
+/// Models are estimates: the actual overhead depends on the allocator,
+/// its version and its configuration.
+pub trait AllocationModel: fmt::Debug + Sync {
+    /// The memory used to allocate `bytes` bytes, at least `bytes`.
+    /// Zero-sized allocations don't allocate: they use 0 bytes.
+    fn allocation_size(&self, bytes: usize) -> usize;
+}
+
+/// The model of the system allocators, which round allocations up to a
+/// multiple of their alignment, `2 * POINTER_BYTE_SIZE`, i.e. 16 bytes
+/// on 64-bit platforms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemMallocModel;
+
+impl AllocationModel for SystemMallocModel {
+    fn allocation_size(&self, bytes: usize) -> usize {
+        bytes.next_multiple_of(2 * POINTER_BYTE_SIZE)
+    }
+}
+
+/// The model of jemalloc, whose size classes are 8 bytes, the multiples
+/// of 16 bytes up to 128 bytes, and then 4 classes per doubling: 160,
+/// 192, 224, 256, 320, 384, etc.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JemallocModel;
+
+impl AllocationModel for JemallocModel {
+    fn allocation_size(&self, bytes: usize) -> usize {
+        match bytes {
+            0 => 0,
+            1..=8 => 8,
+            9..=128 => bytes.next_multiple_of(16),
+            _ => bytes.next_multiple_of(1 << ((bytes - 1).ilog2() - 2)),
+        }
+    }
+}
+
+/// The options of [`measure_with`](crate::measure_with).
+#[derive(Clone, Copy, Debug)]
+pub struct MeasureOptions {
+    /// How the allocations are counted.
+    pub policy: SizePolicy,
+
+    /// The model rounding up the allocations, when
+    /// `policy.include_allocator_overhead` is set. The default model
+    /// is [`SystemMallocModel`].
+    pub allocation_model: &'static dyn AllocationModel,
+}
+
+impl Default for MeasureOptions {
+    fn default() -> Self {
+        Self {
+            policy: SizePolicy::default(),
+            allocation_model: &SystemMallocModel,
+        }
+    }
+}
+
+/// A tracker with measurement options.
+pub(crate) struct PolicyTracker {
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    options: MeasureOptions,
+}
+
+impl PolicyTracker {
+    pub(crate) fn new(options: MeasureOptions) -> Self {
+        Self {
+            visited: BTreeSet::new(),
+            values: BTreeSet::new(),
+            options,
+        }
+    }
+}
+
+impl MemoryUsageTracker for PolicyTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn size_policy(&self) -> Option<SizePolicy> {
+        Some(self.options.policy)
+    }
+
+    fn allocation_model(&self) -> Option<&dyn AllocationModel> {
+        if self.options.policy.include_allocator_overhead {
+            Some(self.options.allocation_model)
+        } else {
+            None
+        }
+    }
+}
+
+/// The memory used to allocate `bytes` bytes, with the allocation model
+/// of the tracker, if any.
+pub(crate) fn allocation_size(tracker: &dyn MemoryUsageTracker, bytes: usize) -> usize {
+    match tracker.allocation_model() {
+        Some(model) => model.allocation_size(bytes),
+        None => bytes,
+    }
+}
+
+/// Whether the value of a reference is counted, with the policy of the
+/// tracker.
+pub(crate) fn follows_references(tracker: &dyn MemoryUsageTracker) -> bool {
+    !tracker
+        .size_policy()
+        .is_some_and(|policy| policy.owned_only)
+}
+
+/// The size charged to one of the `owners` of a shared value of `size`
+/// bytes, with the policy of the tracker.
+pub(crate) fn shared_size(tracker: &dyn MemoryUsageTracker, size: usize, owners: usize) -> usize {
+    match tracker.size_policy() {
+        Some(SizePolicy {
+            shared: SharedPolicy::Amortized,
+            ..
+        }) => size / owners.max(1),
+        _ => size,
+    }
+}
+
+/// The size of the buffer of a collection, holding `len` elements of
+/// `element_size` bytes out of `capacity`, with the policy of the
+/// tracker. Without one, it's counted with `fallback`, the historical
+/// accounting of the collection.
+pub(crate) fn buffer_size(
+    tracker: &dyn MemoryUsageTracker,
+    fallback: CollectionPolicy,
+    len: usize,
+    capacity: usize,
+    element_size: usize,
+) -> usize {
+    let collections = tracker
+        .size_policy()
+        .map_or(fallback, |policy| policy.collections);
+
+    let bytes = match collections {
+        CollectionPolicy::Capacity => capacity * element_size,
+        CollectionPolicy::Len => len * element_size,
+    };
+
+    allocation_size(tracker, bytes)
+}
+
+#[cfg(test)]
+mod test_allocation_models {
+    use super::*;
+
+    #[test]
+    fn test_system_malloc_model() {
+        let model = SystemMallocModel;
+        let granularity = 2 * POINTER_BYTE_SIZE;
+
+        assert_eq!(model.allocation_size(0), 0);
+        assert_eq!(model.allocation_size(1), granularity);
+        assert_eq!(model.allocation_size(granularity), granularity);
+        assert_eq!(model.allocation_size(granularity + 1), 2 * granularity);
+    }
+
+    #[test]
+    fn test_jemalloc_model() {
+        let model = JemallocModel;
+        let classes = [
+            (0, 0),
+            (1, 8),
+            (8, 8),
+            (9, 16),
+            (17, 32),
+            (100, 112),
+            (128, 128),
+            (129, 160),
+            (200, 224),
+            (256, 256),
+            (257, 320),
+            (513, 640),
+            (4000, 4096),
+            (14_000, 14_336),
+            (16_385, 20_480),
+            (1 << 20, 1 << 20),
+            ((1 << 20) + 1, (1 << 20) + (1 << 18)),
+        ];
+
+        for &(bytes, class) in classes.iter() {
+            assert_eq!(model.allocation_size(bytes), class, "{} bytes", bytes);
+        }
+    }
+}
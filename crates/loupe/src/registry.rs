@@ -0,0 +1,73 @@
+//! A global registry of named values, e.g. the caches of a server, to
+//! measure them all from anywhere, like a metrics endpoint. It must be
+//! enabled with the `registry` feature.
+//!
+//! The registry holds weak references: registering a value doesn't
+//! keep it alive, and values dropped since are skipped.
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! let module_cache = Arc::new(vec![0u8; 1024]);
+//! loupe::registry::register("module_cache", Arc::downgrade(&module_cache));
+//!
+//! for (name, size) in loupe::registry::snapshot() {
+//!     println!("{}: {}", name, loupe::HumanSize(size));
+//! }
+//! ```
+
+use crate::{DefaultTracker, MemoryUsage, MemoryUsageTracker};
+use std::sync::{Arc, RwLock, Weak};
+
+type Root = Weak<dyn MemoryUsage + Send + Sync>;
+
+static REGISTRY: RwLock<Vec<(String, Root)>> = RwLock::new(Vec::new());
+
+/// Register the value `root`, named `name`. The same name can be used
+/// several times.
+pub fn register<T>(name: impl Into<String>, root: Weak<T>)
+where
+    T: MemoryUsage + Send + Sync + 'static,
+{
+    let root: Root = root;
+    let mut registry = REGISTRY.write().unwrap_or_else(|error| error.into_inner());
+
+    // Forget the values dropped since, so that the registry doesn't grow
+    // forever when short-lived values are registered.
+    registry.retain(|(_, root)| root.strong_count() > 0);
+    registry.push((name.into(), root));
+}
+
+/// Returns the name and the size of the registered values still alive,
+/// in the order of registration.
+///
+/// The values are measured with the same tracker, so that data shared
+/// between them is counted once, in the size of the first value
+/// reaching it. A value registered twice has a size of 0 the second
+/// time.
+pub fn snapshot() -> Vec<(String, usize)> {
+    // The values are upgraded first, so that they live until all of
+    // them are measured: a value dropped in the meantime could have its
+    // address reused by another one.
+    let roots = REGISTRY
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .iter()
+        .filter_map(|(name, root)| Some((name.clone(), root.upgrade()?)))
+        .collect::<Vec<_>>();
+
+    let mut tracker = DefaultTracker::new();
+
+    roots
+        .iter()
+        .map(|(name, root)| {
+            let size = if tracker.track(Arc::as_ptr(root).cast()) {
+                (**root).size_of_val(&mut tracker)
+            } else {
+                0
+            };
+
+            (name.clone(), size)
+        })
+        .collect()
+}
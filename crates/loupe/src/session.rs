@@ -0,0 +1,237 @@
+//! Measure several values at once, with their shared data counted once.
+
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The size of a value of a [`MemoryUsageSession`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootSize {
+    /// The name given to [`MemoryUsageSession::add`].
+    pub name: Cow<'static, str>,
+
+    /// The size of the value, without the allocations shared with other
+    /// values of the session.
+    pub bytes: usize,
+}
+
+/// The sizes of the values of a [`MemoryUsageSession`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionReport {
+    /// The size of each value, in the order of the
+    /// [`MemoryUsageSession::add`] calls.
+    pub roots: Vec<RootSize>,
+
+    /// The size of the allocations reachable from more than one value.
+    pub shared: usize,
+}
+
+impl SessionReport {
+    /// The size of all the values, with their shared allocations
+    /// counted once.
+    pub fn total(&self) -> usize {
+        self.roots.iter().map(|root| root.bytes).sum::<usize>() + self.shared
+    }
+}
+
+/// Measure several values, e.g. the caches of a process, which share
+/// data with `Arc`s or `Rc`s.
+///
+/// Measuring each value with `size_of_val` counts the shared data
+/// several times, and measuring them with the same tracker charges it
+/// to the first value measured. A session charges each value with the
+/// allocations only it reaches, and puts the allocations reachable from
+/// several values in a separate `shared` bucket, whatever the order of
+/// the values.
+///
+/// ```rust
+/// use loupe::MemoryUsageSession;
+/// use std::sync::Arc;
+///
+/// let interned = Arc::new(vec![0u8; 1024]);
+/// let rules = vec![Arc::clone(&interned)];
+/// let sessions = vec![interned, Arc::new(vec![0u8; 16])];
+///
+/// let mut session = MemoryUsageSession::new();
+/// session.add("rules_cache", &rules);
+/// session.add("session_table", &sessions);
+///
+/// let report = session.report();
+///
+/// assert!(report.shared > 1024);
+/// assert!(report.roots[1].bytes > report.roots[0].bytes);
+/// ```
+#[derive(Default)]
+pub struct MemoryUsageSession<'a> {
+    roots: Vec<(Cow<'static, str>, &'a dyn MemoryUsage)>,
+}
+
+impl<'a> MemoryUsageSession<'a> {
+    /// A session without values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value to measure, named `name`.
+    pub fn add(&mut self, name: impl Into<Cow<'static, str>>, value: &'a dyn MemoryUsage) {
+        self.roots.push((name.into(), value));
+    }
+
+    /// Measure the values.
+    ///
+    /// The values are traversed twice: first to find the allocations
+    /// reachable from more than one value, then to measure each value
+    /// without them.
+    pub fn report(&self) -> SessionReport {
+        let mut reached_by = BTreeMap::<*const (), usize>::new();
+
+        for &(_, value) in &self.roots {
+            let mut visited = BTreeSet::new();
+            value.size_of_val(&mut visited);
+
+            for address in visited {
+                *reached_by.entry(address).or_default() += 1;
+            }
+        }
+
+        let shared = reached_by
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(address, _)| address)
+            .collect::<BTreeSet<_>>();
+
+        let roots = self
+            .roots
+            .iter()
+            .map(|&(ref name, value)| RootSize {
+                name: name.clone(),
+                bytes: value.size_of_val(&mut ExclusiveTracker {
+                    shared: &shared,
+                    visited: BTreeSet::new(),
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        // An allocation under a shared allocation is reached by the same
+        // values, so it's shared too: the values measured together, minus
+        // their own allocations, are the shared allocations.
+        let mut visited = BTreeSet::new();
+        let total = self
+            .roots
+            .iter()
+            .map(|&(_, value)| value.size_of_val(&mut visited))
+            .sum::<usize>();
+        let shared = total - roots.iter().map(|root| root.bytes).sum::<usize>();
+
+        SessionReport { roots, shared }
+    }
+}
+
+/// A tracker skipping the shared allocations.
+struct ExclusiveTracker<'a> {
+    shared: &'a BTreeSet<*const ()>,
+    visited: BTreeSet<*const ()>,
+}
+
+impl MemoryUsageTracker for ExclusiveTracker<'_> {
+    fn track(&mut self, address: *const ()) -> bool {
+        !self.shared.contains(&address) && self.visited.insert(address)
+    }
+}
+
+#[cfg(test)]
+mod test_session_types {
+    use super::*;
+    use crate::{size_of_val, POINTER_BYTE_SIZE};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_shared_allocation() {
+        let large = Arc::new(vec![7u8; 4096]);
+        let rules = vec![Arc::clone(&large), Arc::new(vec![1u8; 10])];
+        let sessions = (Arc::clone(&large), String::from("abc"));
+
+        let shared_bytes = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 4096;
+
+        for order in 0..2 {
+            let mut session = MemoryUsageSession::new();
+
+            if order == 0 {
+                session.add("rules_cache", &rules);
+                session.add("session_table", &sessions);
+            } else {
+                session.add("session_table", &sessions);
+                session.add("rules_cache", &rules);
+            }
+
+            let report = session.report();
+            let bytes = |name: &str| {
+                report
+                    .roots
+                    .iter()
+                    .find(|root| root.name == name)
+                    .map(|root| root.bytes)
+                    .unwrap()
+            };
+
+            assert_eq!(report.shared, shared_bytes);
+            assert_eq!(bytes("rules_cache"), size_of_val(&rules) - shared_bytes);
+            assert_eq!(
+                bytes("session_table"),
+                size_of_val(&sessions) - shared_bytes
+            );
+            assert_eq!(
+                report.total(),
+                size_of_val(&rules) + size_of_val(&sessions) - shared_bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_nested_shared_allocations() {
+        // The allocations of the shared `Vec<String>` are shared too.
+        let shared = Arc::new(vec![String::from("interned")]);
+        let first = Box::new(Arc::clone(&shared));
+        let second = vec![shared];
+
+        let mut session = MemoryUsageSession::new();
+        session.add("first", &first);
+        session.add("second", &second);
+
+        let report = session.report();
+
+        assert_eq!(
+            report.shared,
+            2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 8
+        );
+        assert_eq!(
+            report.roots,
+            [
+                RootSize {
+                    name: Cow::Borrowed("first"),
+                    bytes: POINTER_BYTE_SIZE + POINTER_BYTE_SIZE,
+                },
+                RootSize {
+                    name: Cow::Borrowed("second"),
+                    bytes: 3 * POINTER_BYTE_SIZE + POINTER_BYTE_SIZE,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_without_shared_allocations() {
+        let first = vec![1u32, 2];
+        let second = String::from("abc");
+
+        let mut session = MemoryUsageSession::new();
+        session.add(String::from("first"), &first);
+        session.add("second", &second);
+
+        let report = session.report();
+
+        assert_eq!(report.shared, 0);
+        assert_eq!(report.roots[0].bytes, size_of_val(&first));
+        assert_eq!(report.roots[1].bytes, size_of_val(&second));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// The memory usage of a value, split between its shallow size and the
+/// memory it owns, as returned by [`measure`](crate::measure) and
+/// [`measure_with`](crate::measure_with).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemorySize {
+    /// The shallow size of the value, as given by
+    /// [`std::mem::size_of_val`], i.e. the bytes stored inline, on the
+    /// stack or in a parent value.
+    pub stack: usize,
+
+    /// The size of everything else the value owns, e.g. the bytes
+    /// behind a `Box` or a `Vec`.
+    pub heap: usize,
+}
+
+impl MemorySize {
+    /// The whole size, as given by [`size_of_val`](crate::size_of_val).
+    pub fn total(&self) -> usize {
+        self.stack + self.heap
+    }
+}
+
+impl Add for MemorySize {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            stack: self.stack + other.stack,
+            heap: self.heap + other.heap,
+        }
+    }
+}
+
+impl AddAssign for MemorySize {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for MemorySize {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a MemorySize> for MemorySize {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+/// A size in bytes, displayed with binary units and one decimal, like
+/// `1.4 MiB`. Sizes below 1 KiB are displayed exactly, like `1023 B`.
+///
+/// The alternate form, `{:#}`, displays the exact number of bytes too,
+/// like `1.5 KiB (1536 bytes)`.
+///
+/// ```rust
+/// use loupe::HumanSize;
+///
+/// assert_eq!(HumanSize(1536).to_string(), "1.5 KiB");
+/// assert_eq!(format!("{:#}", HumanSize(1536)), "1.5 KiB (1536 bytes)");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanSize(pub usize);
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+        let bytes = self.0;
+
+        if bytes < 1024 {
+            return formatter.pad(&format!("{} B", bytes));
+        }
+
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+
+        // Pick the unit after rounding, so that 1048575 bytes is
+        // `1.0 MiB`, not `1024.0 KiB`.
+        while (value * 10.0).round() >= 1024.0 * 10.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+
+        let human = if formatter.alternate() {
+            format!("{:.1} {} ({} bytes)", value, UNITS[unit], bytes)
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        };
+
+        formatter.pad(&human)
+    }
+}
+
+/// Format a size in bytes with [`HumanSize`], e.g. `1.4 MiB`.
+pub fn format_size(bytes: usize) -> String {
+    HumanSize(bytes).to_string()
+}
+
+#[cfg(test)]
+mod test_memory_size_types {
+    use super::*;
+
+    #[test]
+    fn test_memory_size_arithmetic() {
+        let mut size = MemorySize { stack: 8, heap: 3 };
+        assert_eq!(size.total(), 11);
+
+        size += MemorySize { stack: 2, heap: 1 };
+        assert_eq!(size, MemorySize { stack: 10, heap: 4 });
+
+        let sizes = vec![size, MemorySize { stack: 1, heap: 0 }];
+        assert_eq!(
+            sizes.iter().sum::<MemorySize>(),
+            MemorySize { stack: 11, heap: 4 }
+        );
+        assert_eq!(
+            sizes.into_iter().sum::<MemorySize>(),
+            MemorySize { stack: 11, heap: 4 }
+        );
+        assert_eq!(
+            std::iter::empty::<MemorySize>().sum::<MemorySize>(),
+            MemorySize::default()
+        );
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(format_size(1024 * 1024 + 1024 * 1024 * 4 / 10), "1.4 MiB");
+        assert_eq!(format_size(3 << 30), "3.0 GiB");
+        assert_eq!(format_size((5 << 30) + (1 << 29)), "5.5 GiB");
+        assert_eq!(
+            format_size(usize::MAX),
+            if cfg!(target_pointer_width = "64") {
+                "16.0 EiB"
+            } else {
+                "4.0 GiB"
+            }
+        );
+    }
+
+    #[test]
+    fn test_human_size_alternate() {
+        assert_eq!(format!("{:#}", HumanSize(1023)), "1023 B");
+        assert_eq!(format!("{:#}", HumanSize(1536)), "1.5 KiB (1536 bytes)");
+        assert_eq!(
+            format!("{:#}", HumanSize(3 << 30)),
+            "3.0 GiB (3221225472 bytes)"
+        );
+        assert_eq!(format!("{:>9}", HumanSize(1536)), "  1.5 KiB");
+    }
+}
@@ -0,0 +1,171 @@
+//! Record the sizes of a breakdown tree, to compare them later. It must
+//! be enabled with the `breakdown` feature.
+
+use crate::{breakdown, BreakdownNode, MemoryUsageBreakdown};
+use std::collections::BTreeMap;
+
+/// The sizes of the nodes of a [`BreakdownNode`] tree, by path, e.g.
+/// `Session.history`: the names of the nodes from the root, separated by
+/// `.`.
+///
+/// Only the numbers are kept, so that a snapshot of a value can be
+/// compared with a later one with [`Snapshot::diff`], without keeping
+/// the value as it was.
+///
+/// ```rust
+/// use loupe::{MemoryUsage, MemoryUsageBreakdown, Snapshot};
+///
+/// #[derive(MemoryUsage, MemoryUsageBreakdown)]
+/// struct Session {
+///     user: String,
+///     history: Vec<u64>,
+/// }
+///
+/// let mut session = Session {
+///     user: String::from("root"),
+///     history: Vec::new(),
+/// };
+///
+/// let before = Snapshot::capture(&session);
+/// session.history.extend(0..100);
+/// let after = Snapshot::capture(&session);
+///
+/// let diff = after.diff(&before);
+/// assert!(diff[0].path.ends_with("history.elements"));
+/// assert_eq!(diff[0].delta, 800);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    sizes: BTreeMap<String, usize>,
+}
+
+/// The change of the size of a path between two [`Snapshot`]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeltaEntry {
+    /// The path of the node, see [`Snapshot`].
+    pub path: String,
+
+    /// The size in the older snapshot, `None` if the path was added
+    /// since, e.g. when an enum changed variants.
+    pub old: Option<usize>,
+
+    /// The size in the newer snapshot, `None` if the path was removed.
+    pub new: Option<usize>,
+
+    /// The growth, `new - old`, negative when the node shrank. A missing
+    /// size counts as 0.
+    pub delta: isize,
+}
+
+impl Snapshot {
+    /// Break a value down with [`breakdown`], and record the sizes of
+    /// the tree.
+    pub fn capture<T: MemoryUsageBreakdown + ?Sized>(value: &T) -> Self {
+        Self::from_breakdown(&breakdown(value))
+    }
+
+    /// Record the sizes of a tree: the total size of each node.
+    pub fn from_breakdown(root: &BreakdownNode) -> Self {
+        let mut snapshot = Self::default();
+        snapshot.record(root, String::new());
+
+        snapshot
+    }
+
+    fn record(&mut self, node: &BreakdownNode, mut path: String) {
+        if !path.is_empty() {
+            path.push('.');
+        }
+
+        path.push_str(&node.name);
+
+        for child in &node.children {
+            self.record(child, path.clone());
+        }
+
+        // Nodes with the same path, e.g. variants of an enum named like
+        // a field, are merged.
+        *self.sizes.entry(path).or_default() += node.total();
+    }
+
+    /// The total size of the node at `path`.
+    pub fn get(&self, path: &str) -> Option<usize> {
+        self.sizes.get(path).copied()
+    }
+
+    /// The paths and their sizes, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.sizes.iter().map(|(path, &size)| (path.as_str(), size))
+    }
+
+    /// Returns the paths whose size changed since the `older` snapshot,
+    /// the largest changes first, growing or shrinking. On equal
+    /// changes, the deepest path comes first, since it's the most
+    /// precise one: when a field grows, all its ancestors grow as much.
+    pub fn diff(&self, older: &Snapshot) -> Vec<DeltaEntry> {
+        let mut paths = self.sizes.keys().collect::<Vec<_>>();
+        paths.extend(older.sizes.keys());
+        paths.sort_unstable();
+        paths.dedup();
+
+        let mut diff = paths
+            .into_iter()
+            .filter_map(|path| {
+                let old = older.get(path);
+                let new = self.get(path);
+
+                if old == new {
+                    return None;
+                }
+
+                Some(DeltaEntry {
+                    path: path.clone(),
+                    old,
+                    new,
+                    delta: new.unwrap_or(0) as isize - old.unwrap_or(0) as isize,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        diff.sort_by(|a, b| {
+            b.delta
+                .unsigned_abs()
+                .cmp(&a.delta.unsigned_abs())
+                .then_with(|| depth(&b.path).cmp(&depth(&a.path)))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        diff
+    }
+}
+
+/// Returns the changes of sizes between two values of the same type,
+/// by comparing their breakdown trees: nodes with the same path, i.e.
+/// the same fields and variants from the root, are compared, and nodes
+/// on one side only, e.g. the fields of a variant of an enum, are added
+/// or removed. The changes are sorted like with [`Snapshot::diff`].
+///
+/// ```rust
+/// use loupe::{MemoryUsage, MemoryUsageBreakdown};
+///
+/// #[derive(MemoryUsage, MemoryUsageBreakdown)]
+/// struct Cache {
+///     entries: Vec<u64>,
+/// }
+///
+/// let before = Cache { entries: Vec::new() };
+/// let after = Cache { entries: vec![1, 2, 3] };
+///
+/// let diff = loupe::diff(&before, &after);
+///
+/// assert!(diff[0].path.ends_with("entries.elements"));
+/// assert_eq!(diff[0].delta, 24);
+/// ```
+pub fn diff<T: MemoryUsageBreakdown + ?Sized>(before: &T, after: &T) -> Vec<DeltaEntry> {
+    Snapshot::capture(after).diff(&Snapshot::capture(before))
+}
+
+/// The number of nodes above the node at `path`.
+fn depth(path: &str) -> usize {
+    path.matches('.').count()
+}
@@ -0,0 +1,952 @@
+//! Trackers collecting statistics about a measurement.
+
+use crate::{HumanSize, MemoryUsage, MemoryUsageTracker};
+use std::any;
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
+use std::fmt;
+use std::mem;
+use std::ptr;
+
+/// The memory used by the values of a type, as recorded by
+/// [`TypeStatsTracker`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeStats {
+    /// The name of the type, as given by `std::any::type_name`.
+    pub type_name: &'static str,
+
+    /// The size of the allocations owned by the values of the type.
+    pub bytes: usize,
+
+    /// The number of values.
+    pub count: usize,
+}
+
+/// `alloc::vec::Vec<u8>: 1.4 MiB across 3400 values`.
+impl fmt::Display for TypeStats {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}: {} across {} values",
+            self.type_name,
+            HumanSize(self.bytes),
+            self.count
+        )
+    }
+}
+
+/// A tracker aggregating the memory usage per type, with the
+/// `MemoryUsageTracker::record` hook.
+///
+/// Each value owning an allocation, like a `Vec`, a `String`, a
+/// `HashMap`, a `Box`, an `Rc` or an `Arc`, records the size of its
+/// allocation under its type. With [`TypeStatsTracker::measure`], the
+/// measured value itself is recorded too, so that the totals of the
+/// report add up to the size of the value, as long as all the
+/// allocations are owned by these types.
+///
+/// ```rust
+/// use loupe::TypeStatsTracker;
+///
+/// let value = vec![String::from("a"), String::from("bc")];
+///
+/// let mut tracker = TypeStatsTracker::new();
+/// let size = tracker.measure(&value);
+///
+/// for stats in tracker.report() {
+///     println!("{}", stats);
+/// }
+///
+/// assert_eq!(tracker.report().iter().map(|stats| stats.bytes).sum::<usize>(), size);
+/// ```
+#[derive(Default)]
+pub struct TypeStatsTracker {
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    types: BTreeMap<&'static str, (usize, usize)>,
+}
+
+impl TypeStatsTracker {
+    /// A tracker without statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure a value, like `size_of_val`, and record it under its
+    /// type with its shallow size.
+    pub fn measure<T: MemoryUsage + ?Sized>(&mut self, value: &T) -> usize {
+        self.record(any::type_name::<T>(), mem::size_of_val(value));
+
+        value.size_of_val(self)
+    }
+
+    /// The statistics per type, the largest types first.
+    pub fn report(&self) -> Vec<TypeStats> {
+        let mut report = self
+            .types
+            .iter()
+            .map(|(&type_name, &(bytes, count))| TypeStats {
+                type_name,
+                bytes,
+                count,
+            })
+            .collect::<Vec<_>>();
+
+        report.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.type_name.cmp(b.type_name)));
+
+        report
+    }
+}
+
+impl MemoryUsageTracker for TypeStatsTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        let (total, count) = self.types.entry(type_name).or_default();
+
+        *total += bytes;
+        *count += 1;
+    }
+}
+
+/// Statistics about the traversal of a measurement, as collected by
+/// [`StatsTracker`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrackerStats {
+    /// The number of `track` calls.
+    pub track_calls: usize,
+
+    /// The number of addresses seen for the first time, i.e. of values
+    /// that are counted.
+    pub first_seen: usize,
+
+    /// The number of addresses already seen, i.e. of values that are
+    /// not counted again.
+    pub already_seen: usize,
+
+    /// The maximum number of nested pointers followed at once.
+    pub max_depth: usize,
+}
+
+/// A tracker delegating to another tracker, while collecting
+/// statistics about the traversal, see [`TrackerStats`].
+///
+/// ```rust
+/// use loupe::{MemoryUsage, StatsTracker};
+/// use std::collections::BTreeSet;
+/// use std::sync::Arc;
+///
+/// let shared = Arc::new(1u64);
+/// let value = (Arc::clone(&shared), shared);
+///
+/// let mut tracker = StatsTracker::new(BTreeSet::new());
+/// value.size_of_val(&mut tracker);
+///
+/// assert_eq!(tracker.stats().first_seen, 1);
+/// assert_eq!(tracker.stats().already_seen, 1);
+/// ```
+pub struct StatsTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    inner: T,
+    stats: TrackerStats,
+    depth: usize,
+}
+
+impl<T> StatsTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    /// Wrap `inner`, without statistics.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            stats: TrackerStats::default(),
+            depth: 0,
+        }
+    }
+
+    /// The statistics collected so far.
+    pub fn stats(&self) -> TrackerStats {
+        self.stats
+    }
+
+    /// The wrapped tracker.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Count a `track` or `track_with_size` call, returning
+    /// `first_seen`.
+    fn count_track(&mut self, first_seen: bool) -> bool {
+        self.stats.track_calls += 1;
+
+        if first_seen {
+            self.stats.first_seen += 1;
+        } else {
+            self.stats.already_seen += 1;
+        }
+
+        first_seen
+    }
+}
+
+impl<T> MemoryUsageTracker for StatsTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        let first_seen = self.inner.track(address);
+
+        self.count_track(first_seen)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        let first_seen = self.inner.track_with_size(address, bytes);
+
+        self.count_track(first_seen)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        let first_seen = self.inner.track_self(address, type_name);
+
+        self.count_track(first_seen)
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+
+        self.inner.enter(address, type_name)
+    }
+
+    fn leave(&mut self, size: usize) {
+        self.depth = self.depth.saturating_sub(1);
+
+        self.inner.leave(size)
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        self.inner.record(type_name, bytes)
+    }
+
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop()
+    }
+
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        self.inner.size_policy()
+    }
+
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        self.inner.allocation_model()
+    }
+
+    fn push_label(&mut self, label: &'static str) {
+        self.inner.push_label(label)
+    }
+
+    fn pop_label(&mut self, size: usize) {
+        self.inner.pop_label(size)
+    }
+}
+
+/// The allocations of sizes between `min` and `max` inclusive, as
+/// recorded by [`AllocationHistogram`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// The smallest size of the bucket, a power of two.
+    pub min: usize,
+
+    /// The largest size of the bucket, `2 * min - 1`.
+    pub max: usize,
+
+    /// The number of allocations.
+    pub count: usize,
+
+    /// The total size of the allocations.
+    pub bytes: usize,
+}
+
+/// A tracker building the distribution of the sizes of allocations,
+/// with the `MemoryUsageTracker::record` hook, in buckets of powers of
+/// two: 1 byte, 2 to 3 bytes, 4 to 7 bytes, etc.
+///
+/// Empty allocations, e.g. of an empty `Vec`, aren't counted.
+///
+/// ```rust
+/// use loupe::{AllocationHistogram, MemoryUsage};
+///
+/// let value = vec![vec![0u8; 40]; 1000];
+///
+/// let mut histogram = AllocationHistogram::new();
+/// value.size_of_val(&mut histogram);
+///
+/// // Prints an ASCII bar chart.
+/// println!("{}", histogram);
+///
+/// assert_eq!(histogram.buckets()[0].count, 1000);
+/// ```
+#[derive(Default)]
+pub struct AllocationHistogram {
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    /// The count and total size of the allocations, by bucket, indexed
+    /// by the base 2 logarithm of the smallest size of the bucket.
+    buckets: Vec<(usize, usize)>,
+}
+
+impl AllocationHistogram {
+    /// A tracker without allocations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The buckets, from the bucket of the smallest allocations to the
+    /// bucket of the largest one, including the empty buckets in
+    /// between.
+    pub fn buckets(&self) -> Vec<HistogramBucket> {
+        let first = self
+            .buckets
+            .iter()
+            .position(|&(count, _)| count > 0)
+            .unwrap_or(self.buckets.len());
+
+        self.buckets
+            .iter()
+            .enumerate()
+            .skip(first)
+            .map(|(log2, &(count, bytes))| HistogramBucket {
+                min: 1 << log2,
+                max: (1 << log2) + ((1 << log2) - 1),
+                count,
+                bytes,
+            })
+            .collect()
+    }
+}
+
+impl MemoryUsageTracker for AllocationHistogram {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn record(&mut self, _type_name: &'static str, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+
+        let log2 = bytes.ilog2() as usize;
+
+        if self.buckets.len() <= log2 {
+            self.buckets.resize(log2 + 1, (0, 0));
+        }
+
+        let (count, total) = &mut self.buckets[log2];
+        *count += 1;
+        *total += bytes;
+    }
+}
+
+/// One line per bucket, with a bar proportional to its number of
+/// allocations:
+///
+/// ```text
+///      32 B ..      63 B | ######################################## 1000 (39.1 KiB)
+///      64 B ..     127 B |                                          0 (0 B)
+///     128 B ..     255 B | #                                        10 (1.3 KiB)
+/// ```
+impl fmt::Display for AllocationHistogram {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        const WIDTH: usize = 40;
+
+        let buckets = self.buckets();
+        let max_count = buckets.iter().map(|bucket| bucket.count).max();
+
+        for bucket in &buckets {
+            // A non-empty bucket has a bar, however small.
+            let bar = match max_count {
+                Some(max_count) if bucket.count > 0 => (bucket.count * WIDTH / max_count).max(1),
+                _ => 0,
+            };
+
+            writeln!(
+                formatter,
+                "{:>9} .. {:>9} | {:<width$} {} ({})",
+                HumanSize(bucket.min).to_string(),
+                HumanSize(bucket.max).to_string(),
+                "#".repeat(bar),
+                bucket.count,
+                HumanSize(bucket.bytes),
+                width = WIDTH
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A tracker recording the bytes charged to each address, with the
+/// `MemoryUsageTracker::track_with_size` hook, e.g. to find which
+/// allocations retain the most memory.
+///
+/// Each allocation of a `Box`, an `Rc`, an `Arc`, or buffer of a
+/// `Vec`, a `String` or a `HashMap` is charged its size, without the
+/// allocations of its content, and the value of a reference its shallow
+/// size. With [`AccountingTracker::measure`], the measured value itself
+/// is charged its shallow size too, so that the charges add up to the
+/// size of the value, as long as all the allocations are owned by these
+/// types.
+///
+/// ```rust
+/// use loupe::AccountingTracker;
+/// use std::ptr;
+///
+/// let value = (Box::new(1u64), String::from("abc"));
+///
+/// let mut tracker = AccountingTracker::new();
+/// let size = tracker.measure(&value);
+///
+/// assert_eq!(tracker.bytes_of(ptr::from_ref(&*value.0).cast()), Some(8));
+/// assert_eq!(tracker.total(), size);
+/// ```
+#[derive(Default)]
+pub struct AccountingTracker {
+    charges: BTreeMap<*const (), usize>,
+    values: BTreeSet<(*const (), &'static str)>,
+}
+
+impl AccountingTracker {
+    /// A tracker without charges.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure a value, like `size_of_val`, and charge its address with
+    /// its shallow size.
+    pub fn measure<T: MemoryUsage + ?Sized>(&mut self, value: &T) -> usize {
+        self.track_with_size(ptr::from_ref::<T>(value).cast(), mem::size_of_val(value));
+
+        value.size_of_val(self)
+    }
+
+    /// The bytes charged to `address`, if it has been seen. An address
+    /// seen with `track` only is charged 0 bytes.
+    pub fn bytes_of(&self, address: *const ()) -> Option<usize> {
+        self.charges.get(&address).copied()
+    }
+
+    /// The charged addresses with their bytes, by address.
+    pub fn iter(&self) -> impl Iterator<Item = (*const (), usize)> + '_ {
+        self.charges
+            .iter()
+            .map(|(&address, &bytes)| (address, bytes))
+    }
+
+    /// The sum of the charges.
+    pub fn total(&self) -> usize {
+        self.charges.values().sum()
+    }
+}
+
+impl MemoryUsageTracker for AccountingTracker {
+    fn track(&mut self, address: *const ()) -> bool {
+        self.track_with_size(address, 0)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        match self.charges.entry(address) {
+            Entry::Vacant(entry) => {
+                entry.insert(bytes);
+
+                true
+            }
+            Entry::Occupied(_) => false,
+        }
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+}
+
+/// The sizes attributed to the labels by a [`LabelTracker`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelReport {
+    /// The size measured within each label, without the size of the
+    /// labels nested in it.
+    pub labels: BTreeMap<&'static str, usize>,
+
+    /// The size measured outside of any label.
+    pub unlabeled: usize,
+}
+
+impl LabelReport {
+    /// The size of the measured values, labelled or not.
+    pub fn total(&self) -> usize {
+        self.labels.values().sum::<usize>() + self.unlabeled
+    }
+}
+
+/// A tracker delegating to another tracker, while attributing the
+/// sizes to logical buckets, e.g. code versus metadata, with the
+/// `MemoryUsageTracker::push_label` and `pop_label` hooks.
+///
+/// Labels are set on fields with the `#[loupe(label = "...")]`
+/// attribute, and can be nested: a size is attributed to the innermost
+/// label. The values measured with [`LabelTracker::measure`] are
+/// partitioned between the labels and the unlabeled remainder.
+///
+/// ```rust
+/// use loupe::{LabelTracker, MemoryUsage};
+/// use std::collections::BTreeSet;
+///
+/// #[derive(MemoryUsage)]
+/// struct Module {
+///     #[loupe(label = "code")]
+///     code: Vec<u8>,
+///     #[loupe(label = "metadata")]
+///     names: Vec<String>,
+///     id: u64,
+/// }
+///
+/// let module = Module {
+///     code: vec![0; 1024],
+///     names: vec![String::from("main")],
+///     id: 1,
+/// };
+///
+/// let mut tracker = LabelTracker::new(BTreeSet::new());
+/// let size = tracker.measure(&module);
+/// let report = tracker.report();
+///
+/// assert_eq!(report.labels["code"], 1024);
+/// assert_eq!(report.total(), size);
+/// ```
+pub struct LabelTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    inner: T,
+    /// The labels being measured, with the size of their nested
+    /// labels.
+    stack: Vec<(&'static str, usize)>,
+    labels: BTreeMap<&'static str, usize>,
+    total: usize,
+}
+
+impl<T> LabelTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    /// Wrap `inner`, without labels.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            stack: Vec::new(),
+            labels: BTreeMap::new(),
+            total: 0,
+        }
+    }
+
+    /// Measure a value, like `size_of_val`, adding its size to the
+    /// total of the report.
+    pub fn measure<V: MemoryUsage + ?Sized>(&mut self, value: &V) -> usize {
+        let size = value.size_of_val(self);
+        self.total += size;
+
+        size
+    }
+
+    /// The sizes attributed so far.
+    pub fn report(&self) -> LabelReport {
+        let labels = self.labels.clone();
+        let unlabeled = self.total.saturating_sub(labels.values().sum::<usize>());
+
+        LabelReport { labels, unlabeled }
+    }
+
+    /// The wrapped tracker.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> MemoryUsageTracker for LabelTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        self.inner.track(address)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        self.inner.track_with_size(address, bytes)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.inner.track_self(address, type_name)
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        self.inner.enter(address, type_name)
+    }
+
+    fn leave(&mut self, size: usize) {
+        self.inner.leave(size)
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        self.inner.record(type_name, bytes)
+    }
+
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop()
+    }
+
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        self.inner.size_policy()
+    }
+
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        self.inner.allocation_model()
+    }
+
+    fn push_label(&mut self, label: &'static str) {
+        self.stack.push((label, 0));
+
+        self.inner.push_label(label)
+    }
+
+    fn pop_label(&mut self, size: usize) {
+        if let Some((label, nested)) = self.stack.pop() {
+            *self.labels.entry(label).or_default() += size.saturating_sub(nested);
+
+            if let Some((_, parent_nested)) = self.stack.last_mut() {
+                *parent_nested += size;
+            }
+        }
+
+        self.inner.pop_label(size)
+    }
+}
+
+#[cfg(test)]
+mod test_stats_types {
+    use super::*;
+    use crate::{size_of_val, POINTER_BYTE_SIZE};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    struct Index {
+        names: Vec<String>,
+        shared: Arc<Vec<u64>>,
+        aliases: HashMap<u32, Box<u16>>,
+    }
+
+    impl MemoryUsage for Index {
+        fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+            mem::size_of_val(self)
+                + crate::heap_size_of_val_with(&self.names, tracker)
+                + crate::heap_size_of_val_with(&self.shared, tracker)
+                + crate::heap_size_of_val_with(&self.aliases, tracker)
+        }
+    }
+
+    #[test]
+    fn test_type_stats_tracker() {
+        let shared = Arc::new(vec![1, 2, 3]);
+        let mut aliases = HashMap::new();
+        aliases.insert(1, Box::new(1));
+        aliases.insert(2, Box::new(2));
+
+        let index = (
+            Index {
+                names: vec![String::from("abc"), String::from("de")],
+                shared: shared.clone(),
+                aliases,
+            },
+            shared,
+        );
+
+        let mut tracker = TypeStatsTracker::new();
+        let size = tracker.measure(&index);
+        let report = tracker.report();
+
+        assert_eq!(size, size_of_val(&index));
+        assert_eq!(report.iter().map(|stats| stats.bytes).sum::<usize>(), size);
+
+        let stats = |name: &str| {
+            report
+                .iter()
+                .find(|stats| stats.type_name == name)
+                .cloned()
+                .unwrap_or_else(|| panic!("no stats for `{}` in {:?}", name, report))
+        };
+
+        assert_eq!(
+            stats("alloc::string::String"),
+            TypeStats {
+                type_name: "alloc::string::String",
+                bytes: 5,
+                count: 2
+            }
+        );
+        assert_eq!(
+            stats("alloc::vec::Vec<alloc::string::String>").bytes,
+            2 * 3 * POINTER_BYTE_SIZE
+        );
+        assert_eq!(stats("alloc::vec::Vec<u64>").bytes, 3 * 8);
+        assert_eq!(
+            stats("alloc::sync::Arc<alloc::vec::Vec<u64>>"),
+            TypeStats {
+                type_name: "alloc::sync::Arc<alloc::vec::Vec<u64>>",
+                bytes: 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE,
+                count: 1
+            }
+        );
+        assert_eq!(stats("alloc::boxed::Box<u16>").count, 2);
+
+        // Sorted by size.
+        assert!(report.windows(2).all(|pair| pair[0].bytes >= pair[1].bytes));
+    }
+
+    #[test]
+    fn test_type_stats_display() {
+        let stats = TypeStats {
+            type_name: "alloc::vec::Vec<u8>",
+            bytes: 1536,
+            count: 3400,
+        };
+
+        assert_eq!(
+            stats.to_string(),
+            "alloc::vec::Vec<u8>: 1.5 KiB across 3400 values"
+        );
+    }
+
+    #[test]
+    fn test_stats_tracker() {
+        let leaf = Arc::new(String::from("leaf"));
+        let nested: Box<Box<Arc<String>>> = Box::new(Box::new(leaf.clone()));
+        let value = (nested, vec![leaf.clone(), leaf]);
+
+        let mut tracker = StatsTracker::new(BTreeSet::new());
+        let size = value.size_of_val(&mut tracker);
+
+        assert_eq!(size, size_of_val(&value));
+        assert_eq!(
+            tracker.stats(),
+            TrackerStats {
+                // 2 `Box`es, 3 `Arc`s, the buffers of the `Vec` and of
+                // the `String`.
+                track_calls: 7,
+                first_seen: 5,
+                already_seen: 2,
+                // `Box` -> `Box` -> `Arc`.
+                max_depth: 3,
+            }
+        );
+        assert_eq!(tracker.into_inner().len(), 5);
+    }
+
+    #[test]
+    fn test_stats_tracker_delegates() {
+        let value = vec![String::from("abc")];
+
+        let mut tracker = StatsTracker::new(TypeStatsTracker::new());
+        let size = value.size_of_val(&mut tracker);
+        assert_eq!(size, size_of_val(&value));
+
+        // `Vec<String>` and `String` are recorded by the inner tracker.
+        let report = tracker.into_inner().report();
+        assert_eq!(
+            report.iter().map(|stats| stats.bytes).sum::<usize>(),
+            size - mem::size_of_val(&value)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_histogram_types {
+    use super::*;
+
+    fn bucket(min: usize, count: usize, bytes: usize) -> HistogramBucket {
+        HistogramBucket {
+            min,
+            max: 2 * min - 1,
+            count,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_buckets() {
+        let mut histogram = AllocationHistogram::new();
+        assert_eq!(histogram.buckets(), []);
+        assert_eq!(histogram.to_string(), "");
+
+        for bytes in [0, 40, 40, 63, 64, 300, 1, usize::MAX].iter() {
+            histogram.record("", *bytes);
+        }
+
+        let buckets = histogram.buckets();
+
+        assert_eq!(buckets.len(), usize::BITS as usize);
+        assert_eq!(buckets[0], bucket(1, 1, 1));
+        assert_eq!(buckets[5], bucket(32, 3, 40 + 40 + 63));
+        assert_eq!(buckets[6], bucket(64, 1, 64));
+        assert_eq!(buckets[7], bucket(128, 0, 0));
+        assert_eq!(buckets[8], bucket(256, 1, 300));
+        assert_eq!(
+            buckets.last(),
+            Some(&HistogramBucket {
+                min: 1 << (usize::BITS - 1),
+                max: usize::MAX,
+                count: 1,
+                bytes: usize::MAX,
+            })
+        );
+    }
+
+    #[test]
+    fn test_measure() {
+        let value = (
+            vec![vec![0u8; 40]; 100],
+            String::from("abc"),
+            Box::new(0u64),
+        );
+
+        let mut histogram = AllocationHistogram::new();
+        value.size_of_val(&mut histogram);
+
+        let buckets = histogram.buckets();
+        let bucket_of = |bytes: usize| {
+            *buckets
+                .iter()
+                .find(|bucket| bucket.min <= bytes && bytes <= bucket.max)
+                .unwrap()
+        };
+
+        // The `String` and the `u64`.
+        assert_eq!(buckets[0], bucket(2, 1, 3));
+        assert_eq!(bucket_of(8), bucket(8, 1, 8));
+        // The inner `Vec`s.
+        assert_eq!(bucket_of(40), bucket(32, 100, 100 * 40));
+        // The outer `Vec`.
+        let outer = 100 * mem::size_of::<Vec<u8>>();
+        assert_eq!(bucket_of(outer).count, 1);
+        assert_eq!(bucket_of(outer).bytes, outer);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut histogram = AllocationHistogram::new();
+
+        for _ in 0..1000 {
+            histogram.record("", 40);
+        }
+
+        for _ in 0..10 {
+            histogram.record("", 130);
+        }
+
+        assert_eq!(
+            histogram.to_string(),
+            "     32 B ..      63 B | ######################################## 1000 (39.1 KiB)
+     64 B ..     127 B |                                          0 (0 B)
+    128 B ..     255 B | #                                        10 (1.3 KiB)
+"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_accounting_types {
+    use super::*;
+    use crate::{size_of_val, POINTER_BYTE_SIZE};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_measure() {
+        struct Index {
+            names: Vec<String>,
+            id: Box<u64>,
+            data: Arc<Vec<u8>>,
+        }
+
+        impl MemoryUsage for Index {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+                    + crate::heap_size_of_val_with(&self.names, tracker)
+                    + crate::heap_size_of_val_with(&self.id, tracker)
+                    + crate::heap_size_of_val_with(&self.data, tracker)
+            }
+        }
+
+        let index = Index {
+            names: vec![String::from("a"), String::from("bcd")],
+            id: Box::new(7),
+            data: Arc::new(vec![0; 10]),
+        };
+
+        let mut tracker = AccountingTracker::new();
+        let size = tracker.measure(&index);
+
+        assert_eq!(size, size_of_val(&index));
+        assert_eq!(tracker.total(), size);
+
+        let bytes_of = |address: *const u8| tracker.bytes_of(address.cast());
+
+        assert_eq!(
+            bytes_of(ptr::from_ref(&index).cast()),
+            Some(mem::size_of::<Index>())
+        );
+        assert_eq!(
+            bytes_of(index.names.as_ptr().cast()),
+            Some(2 * 3 * POINTER_BYTE_SIZE)
+        );
+        assert_eq!(bytes_of(index.names[0].as_ptr()), Some(1));
+        assert_eq!(bytes_of(index.names[1].as_ptr()), Some(3));
+        assert_eq!(bytes_of(ptr::from_ref(&*index.id).cast()), Some(8));
+        assert_eq!(
+            bytes_of(Arc::as_ptr(&index.data).cast()),
+            Some(2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE)
+        );
+        assert_eq!(bytes_of(index.data.as_ptr()), Some(10));
+        assert_eq!(tracker.iter().count(), 7);
+    }
+
+    #[test]
+    fn test_shared_allocation() {
+        let shared = Arc::new(String::from("shared"));
+        let value = vec![Arc::clone(&shared), shared];
+
+        let mut tracker = AccountingTracker::new();
+        let size = tracker.measure(&value);
+
+        assert_eq!(size, size_of_val(&value));
+        assert_eq!(tracker.total(), size);
+    }
+
+    #[test]
+    fn test_track() {
+        let mut tracker = AccountingTracker::new();
+        let address = ptr::from_ref(&tracker).cast();
+
+        assert!(tracker.track(address));
+        assert!(!tracker.track_with_size(address, 8));
+        assert_eq!(tracker.bytes_of(address), Some(0));
+        assert_eq!(tracker.bytes_of(ptr::null()), None);
+    }
+}
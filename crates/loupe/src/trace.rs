@@ -0,0 +1,115 @@
+//! Emit `tracing` events about large parts of the measured values. It
+//! must be enabled with the `tracing` feature.
+
+use crate::MemoryUsageTracker;
+
+/// A tracker delegating to another tracker, while emitting a `DEBUG`
+/// event for each part of the measured value exceeding a threshold:
+///
+/// * an allocation, with the `record` hook, e.g. the buffer of a large
+///   `Vec`, as a `loupe: large allocation` event,
+/// * a value behind a pointer, with the `enter` and `leave` hooks, e.g.
+///   a `Box<T>` whose `T` is large with everything it owns, as a
+///   `loupe: large subtree` event.
+///
+/// Events have `type_name` and `bytes` fields, so that expensive
+/// measurements can be correlated with the traces of a request.
+///
+/// ```rust
+/// use loupe::{DefaultTracker, TracingTracker};
+///
+/// let value = vec![0u8; 1 << 20];
+/// let mut tracker = TracingTracker::new(DefaultTracker::new(), 1 << 16);
+///
+/// // Emits a `loupe: large allocation` event for the buffer.
+/// loupe::size_of_val_with(&value, &mut tracker);
+/// ```
+pub struct TracingTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    inner: T,
+    threshold: usize,
+    type_names: Vec<&'static str>,
+}
+
+impl<T> TracingTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    /// Wrap `inner`, emitting events for the parts of at least
+    /// `threshold` bytes.
+    pub fn new(inner: T, threshold: usize) -> Self {
+        Self {
+            inner,
+            threshold,
+            type_names: Vec::new(),
+        }
+    }
+
+    /// The wrapped tracker.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> MemoryUsageTracker for TracingTracker<T>
+where
+    T: MemoryUsageTracker,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        self.inner.track(address)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        self.inner.track_with_size(address, bytes)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.inner.track_self(address, type_name)
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        self.type_names.push(type_name);
+
+        self.inner.enter(address, type_name)
+    }
+
+    fn leave(&mut self, size: usize) {
+        if let Some(type_name) = self.type_names.pop() {
+            if size >= self.threshold {
+                tracing::debug!(type_name, bytes = size, "loupe: large subtree");
+            }
+        }
+
+        self.inner.leave(size)
+    }
+
+    fn record(&mut self, type_name: &'static str, bytes: usize) {
+        if bytes >= self.threshold {
+            tracing::debug!(type_name, bytes, "loupe: large allocation");
+        }
+
+        self.inner.record(type_name, bytes)
+    }
+
+    fn should_stop(&self) -> bool {
+        self.inner.should_stop()
+    }
+
+    fn size_policy(&self) -> Option<crate::SizePolicy> {
+        self.inner.size_policy()
+    }
+
+    fn allocation_model(&self) -> Option<&dyn crate::AllocationModel> {
+        self.inner.allocation_model()
+    }
+
+    fn push_label(&mut self, label: &'static str) {
+        self.inner.push_label(label)
+    }
+
+    fn pop_label(&mut self, size: usize) {
+        self.inner.pop_label(size)
+    }
+}
@@ -0,0 +1,239 @@
+//! Walk the values owned by a value, see [`visit`](crate::visit).
+
+use crate::{MemoryUsage, MemoryUsageTracker};
+use std::collections::BTreeSet;
+use std::{any, mem, ptr};
+
+/// The callbacks of [`visit`](crate::visit), e.g. to dump the
+/// addresses of the values, or to draw a flamegraph.
+///
+/// The visited values are the measured value and the values it points
+/// to, like the value of a `Box` or of an `Arc`. They are entered and
+/// left as a tree: the values entered between the `enter` and the
+/// `leave` of a value are owned by it. A value reached from several
+/// pointers is visited from the first one only.
+pub trait MemoryVisitor {
+    /// Called when the value at `address`, of type `type_name`, as
+    /// given by `std::any::type_name`, starts being measured.
+    /// `shallow_size` is the size of the value itself, or of its
+    /// allocation when it's pointed to, e.g. with the counters of an
+    /// `Rc`.
+    fn enter(&mut self, _type_name: &'static str, _address: *const (), _shallow_size: usize) {}
+
+    /// Called when the last entered value that isn't left yet has been
+    /// measured, with its size, including the values it owns.
+    fn leave(&mut self, _deep_size: usize) {}
+}
+
+/// Walk the values owned by a value, notifying the visitor as they are
+/// measured, and returns the size of the value, as [`size_of_val`]
+/// does.
+///
+/// ```rust
+/// use loupe::MemoryVisitor;
+///
+/// #[derive(Default)]
+/// struct Depth {
+///     current: usize,
+///     max: usize,
+/// }
+///
+/// impl MemoryVisitor for Depth {
+///     fn enter(&mut self, _: &'static str, _: *const (), _: usize) {
+///         self.current += 1;
+///         self.max = self.max.max(self.current);
+///     }
+///
+///     fn leave(&mut self, _: usize) {
+///         self.current -= 1;
+///     }
+/// }
+///
+/// let value: Box<Box<u8>> = Box::new(Box::new(1));
+/// let mut depth = Depth::default();
+///
+/// assert_eq!(loupe::visit(&value, &mut depth), loupe::size_of_val(&value));
+/// assert_eq!(depth.max, 3);
+/// ```
+///
+/// [`size_of_val`]: crate::size_of_val
+pub fn visit<T, V>(value: &T, visitor: &mut V) -> usize
+where
+    T: MemoryUsage + ?Sized,
+    V: MemoryVisitor + ?Sized,
+{
+    let mut tracker = VisitorTracker {
+        visitor,
+        visited: BTreeSet::new(),
+        values: BTreeSet::new(),
+        pending: 0,
+    };
+
+    // The root isn't tracked, so that it's measured as with
+    // `size_of_val`.
+    tracker.visitor.enter(
+        any::type_name::<T>(),
+        ptr::from_ref::<T>(value).cast(),
+        mem::size_of_val(value),
+    );
+    let size = value.size_of_val(&mut tracker);
+    tracker.visitor.leave(size);
+
+    size
+}
+
+/// A tracker notifying a visitor.
+struct VisitorTracker<'a, V>
+where
+    V: MemoryVisitor + ?Sized,
+{
+    visitor: &'a mut V,
+    visited: BTreeSet<*const ()>,
+    values: BTreeSet<(*const (), &'static str)>,
+    /// The bytes given to the last `track_with_size` call, for the
+    /// `enter` call following it.
+    pending: usize,
+}
+
+impl<V> MemoryUsageTracker for VisitorTracker<'_, V>
+where
+    V: MemoryVisitor + ?Sized,
+{
+    fn track(&mut self, address: *const ()) -> bool {
+        self.track_with_size(address, 0)
+    }
+
+    fn track_with_size(&mut self, address: *const (), bytes: usize) -> bool {
+        self.pending = bytes;
+
+        self.visited.insert(address)
+    }
+
+    fn track_self(&mut self, address: *const (), type_name: &'static str) -> bool {
+        self.values.insert((address, type_name))
+    }
+
+    fn enter(&mut self, address: *const (), type_name: &'static str) {
+        self.visitor.enter(type_name, address, self.pending);
+    }
+
+    fn leave(&mut self, size: usize) {
+        self.visitor.leave(size);
+    }
+}
+
+#[cfg(test)]
+mod test_visit_types {
+    use super::*;
+    use crate::{size_of_val, POINTER_BYTE_SIZE};
+    use std::sync::Arc;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Enter(&'static str, usize),
+        Leave(usize),
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl MemoryVisitor for Recorder {
+        fn enter(&mut self, type_name: &'static str, _address: *const (), shallow_size: usize) {
+            self.0.push(Event::Enter(type_name, shallow_size));
+        }
+
+        fn leave(&mut self, deep_size: usize) {
+            self.0.push(Event::Leave(deep_size));
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        struct Inner {
+            values: Vec<u32>,
+            boxed: Box<u64>,
+        }
+
+        impl MemoryUsage for Inner {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+                    + crate::heap_size_of_val_with(&self.values, tracker)
+                    + crate::heap_size_of_val_with(&self.boxed, tracker)
+            }
+        }
+
+        struct Outer {
+            inner: Box<Inner>,
+            shared: (Arc<u8>, Arc<u8>),
+        }
+
+        impl MemoryUsage for Outer {
+            fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+                mem::size_of_val(self)
+                    + crate::heap_size_of_val_with(&self.inner, tracker)
+                    + crate::heap_size_of_val_with(&self.shared, tracker)
+            }
+        }
+
+        let shared = Arc::new(1);
+        let value = Outer {
+            inner: Box::new(Inner {
+                values: vec![1, 2, 3],
+                boxed: Box::new(4),
+            }),
+            shared: (Arc::clone(&shared), shared),
+        };
+
+        let mut recorder = Recorder::default();
+        let size = visit(&value, &mut recorder);
+        let inner_size = mem::size_of::<Inner>() + 3 * 4 + 8;
+        let arc_size = 2 * POINTER_BYTE_SIZE + 1;
+
+        assert_eq!(size, size_of_val(&value));
+        assert_eq!(
+            recorder.0,
+            [
+                Event::Enter(any::type_name::<Outer>(), mem::size_of::<Outer>()),
+                Event::Enter(any::type_name::<Inner>(), mem::size_of::<Inner>()),
+                Event::Enter("u64", 8),
+                Event::Leave(8),
+                Event::Leave(inner_size),
+                // The second `Arc` isn't entered.
+                Event::Enter("u8", arc_size),
+                Event::Leave(arc_size),
+                Event::Leave(size),
+            ]
+        );
+        assert_eq!(size, mem::size_of::<Outer>() + inner_size + arc_size);
+    }
+
+    #[test]
+    fn test_nesting() {
+        let value = vec![Box::new(Box::new(String::from("abc"))); 3];
+
+        let mut recorder = Recorder::default();
+        let size = visit(&value, &mut recorder);
+
+        // Each value is left with at least the sizes of its children.
+        let mut stack = Vec::<(usize, usize)>::new();
+
+        for event in &recorder.0 {
+            match *event {
+                Event::Enter(_, shallow_size) => stack.push((shallow_size, 0)),
+                Event::Leave(deep_size) => {
+                    let (shallow_size, children) = stack.pop().unwrap();
+                    assert!(deep_size >= shallow_size + children);
+
+                    if let Some((_, parent_children)) = stack.last_mut() {
+                        *parent_children += deep_size;
+                    }
+                }
+            }
+        }
+
+        assert!(stack.is_empty());
+        assert_eq!(recorder.0.len(), 2 * (1 + 3 + 3));
+        assert_eq!(recorder.0.last(), Some(&Event::Leave(size)));
+        assert_eq!(size, size_of_val(&value));
+    }
+}
@@ -0,0 +1,480 @@
+#![cfg(feature = "breakdown")]
+
+use loupe::{
+    breakdown, size_of_val, BreakdownNode, MemoryUsage, MemoryUsageBreakdown, POINTER_BYTE_SIZE,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Assert that the tree adds up to `size_of_val`, at every level.
+fn assert_consistent<T: MemoryUsageBreakdown>(value: &T) -> BreakdownNode {
+    let tree = breakdown(value);
+    assert_eq!(tree.total(), size_of_val(value));
+
+    tree
+}
+
+fn child<'a>(node: &'a BreakdownNode, name: &str) -> &'a BreakdownNode {
+    node.children
+        .iter()
+        .find(|child| child.name == name)
+        .unwrap_or_else(|| panic!("no child named `{}` in {:?}", name, node))
+}
+
+#[test]
+fn test_struct() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Cache {
+        entries: HashMap<u32, String>,
+        order: Vec<u32>,
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Session {
+        id: u64,
+        user: String,
+        history: Vec<u64>,
+        cache: Box<Cache>,
+    }
+
+    let mut entries = HashMap::new();
+    entries.insert(1, String::from("one"));
+
+    let session = Session {
+        id: 1,
+        user: String::from("root"),
+        history: vec![1, 2, 3, 4],
+        cache: Box::new(Cache {
+            entries,
+            order: vec![1],
+        }),
+    };
+
+    let mut tree = assert_consistent(&session);
+
+    assert_eq!(tree.self_size, 0);
+    assert_eq!(child(&tree, "id"), &BreakdownNode::new("id", 8));
+    assert_eq!(
+        child(&tree, "user"),
+        &BreakdownNode::new("user", 3 * POINTER_BYTE_SIZE + 4)
+    );
+    assert_eq!(
+        child(child(&tree, "history"), "elements"),
+        &BreakdownNode::new("elements", 4 * 8)
+    );
+
+    // `Box<Cache>` is broken down, down to the fields of `Cache`.
+    let cache = child(child(&tree, "cache"), "boxed");
+    assert_eq!(child(child(cache, "order"), "elements").self_size, 4);
+    assert_eq!(
+        child(child(cache, "entries"), "values").self_size,
+        3 * POINTER_BYTE_SIZE + 3
+    );
+
+    // The top-level fields sorted by size.
+    tree.children
+        .sort_by_key(|child| std::cmp::Reverse(child.total()));
+    let names = tree
+        .children
+        .iter()
+        .map(|child| child.name.as_ref())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["cache", "history", "user", "id"]);
+}
+
+#[test]
+fn test_tuple_struct_and_padding() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Pair(u8, u32);
+
+    let tree = assert_consistent(&Pair(1, 2));
+
+    assert_eq!(tree.self_size, 3);
+    assert_eq!(
+        tree.children,
+        [BreakdownNode::new("0", 1), BreakdownNode::new("1", 4)]
+    );
+}
+
+#[test]
+fn test_enum() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    #[allow(dead_code)]
+    enum Message {
+        Ping,
+        Text {
+            body: String,
+        },
+        Binary(Vec<u8>, u32),
+        #[loupe(skip)]
+        Raw(Vec<u8>),
+    }
+
+    let tree = assert_consistent(&Message::Ping);
+    assert_eq!(tree.self_size, std::mem::size_of::<Message>());
+    assert_eq!(tree.children, [BreakdownNode::new("Ping", 0)]);
+
+    let tree = assert_consistent(&Message::Text {
+        body: String::from("abc"),
+    });
+    assert_eq!(
+        tree.children,
+        [BreakdownNode {
+            name: "Text".into(),
+            self_size: 0,
+            children: vec![BreakdownNode::new("body", 3 * POINTER_BYTE_SIZE + 3)],
+        }]
+    );
+
+    let tree = assert_consistent(&Message::Binary(vec![1, 2], 7));
+    let variant = child(&tree, "Binary");
+    assert_eq!(child(child(variant, "0"), "elements").self_size, 2);
+    assert_eq!(child(variant, "1"), &BreakdownNode::new("1", 4));
+
+    let tree = assert_consistent(&Message::Raw(vec![1, 2]));
+    assert_eq!(tree.children, [BreakdownNode::new("Raw", 0)]);
+}
+
+#[test]
+fn test_generics() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Wrapper<T> {
+        inner: T,
+        names: Vec<String>,
+    }
+
+    let wrapper = Wrapper {
+        inner: Box::new(String::from("abc")),
+        names: vec![String::from("x")],
+    };
+    let tree = assert_consistent(&wrapper);
+
+    // A generic field is a leaf.
+    assert_eq!(
+        child(&tree, "inner"),
+        &BreakdownNode::new("inner", POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 3)
+    );
+    assert_eq!(
+        child(child(&tree, "names"), "elements").self_size,
+        3 * POINTER_BYTE_SIZE + 1
+    );
+}
+
+#[test]
+fn test_field_attributes() {
+    struct Handle;
+
+    fn size_of_buffer(buffer: &Vec<u8>, _: &mut dyn loupe::MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(buffer) + buffer.capacity()
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Resource {
+        #[loupe(skip)]
+        handle: Handle,
+        #[loupe(with = "size_of_buffer")]
+        buffer: Vec<u8>,
+        #[loupe(estimate = "self.length")]
+        device: u64,
+        length: usize,
+        #[loupe(shallow)]
+        parent: Arc<String>,
+        #[cfg(not(test))]
+        missing: u8,
+    }
+
+    let mut buffer = Vec::with_capacity(16);
+    buffer.push(1);
+
+    let resource = Resource {
+        handle: Handle,
+        buffer,
+        device: 0,
+        length: 100,
+        parent: Arc::new(String::from("parent")),
+    };
+    let tree = assert_consistent(&resource);
+
+    assert_eq!(child(&tree, "handle"), &BreakdownNode::new("handle", 0));
+    assert_eq!(
+        child(&tree, "buffer"),
+        &BreakdownNode::new("buffer", 3 * POINTER_BYTE_SIZE + 16)
+    );
+    assert_eq!(
+        child(&tree, "device"),
+        &BreakdownNode::new("device", 8 + 100)
+    );
+    assert_eq!(
+        child(&tree, "parent"),
+        &BreakdownNode::new("parent", POINTER_BYTE_SIZE)
+    );
+    assert_eq!(tree.children.len(), 5);
+}
+
+#[test]
+fn test_shared_values() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Views {
+        first: Arc<Vec<u8>>,
+        second: Arc<Vec<u8>>,
+    }
+
+    let shared = Arc::new(vec![0u8; 10]);
+    let views = Views {
+        first: shared.clone(),
+        second: shared,
+    };
+    let tree = assert_consistent(&views);
+
+    assert!(child(&tree, "first").total() > child(&tree, "second").total());
+    assert_eq!(child(&tree, "second").total(), POINTER_BYTE_SIZE);
+}
+
+#[test]
+fn test_leaves() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    #[repr(C)]
+    union Word {
+        integer: u64,
+        bytes: [u8; 8],
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    #[loupe(opaque)]
+    struct Opaque {
+        _data: Vec<u8>,
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    #[loupe(transparent)]
+    struct Name(String);
+
+    let tree = assert_consistent(&Word { integer: 1 });
+    assert!(tree.children.is_empty());
+
+    let tree = assert_consistent(&Opaque {
+        _data: vec![1, 2, 3],
+    });
+    assert_eq!(tree.self_size, 3 * POINTER_BYTE_SIZE);
+    assert!(tree.children.is_empty());
+
+    let tree = assert_consistent(&Name(String::from("abc")));
+    assert_eq!(tree.self_size, 3 * POINTER_BYTE_SIZE + 3);
+}
+
+#[test]
+fn test_track_self() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    #[loupe(track_self)]
+    #[repr(C)]
+    struct Node {
+        id: u64,
+        name: String,
+    }
+
+    let node = Node {
+        id: 1,
+        name: String::from("abc"),
+    };
+    let tree = assert_consistent(&node);
+    assert_eq!(child(&tree, "name").total(), 3 * POINTER_BYTE_SIZE + 3);
+
+    // Already visited, only counted by its shallow size.
+    let mut tracker = std::collections::BTreeSet::new();
+    let first = node.breakdown("first".into(), &mut tracker);
+    let second = node.breakdown("second".into(), &mut tracker);
+    assert_eq!(first.total(), size_of_val(&node));
+    assert_eq!(
+        second,
+        BreakdownNode::new("second", 8 + 3 * POINTER_BYTE_SIZE)
+    );
+}
+
+// Sizes in the golden file are the ones of 64-bit targets.
+#[cfg(all(feature = "serde", target_pointer_width = "64"))]
+#[test]
+fn test_json() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Config {
+        id: u32,
+        name: String,
+        ports: Vec<u16>,
+    }
+
+    let config = Config {
+        id: 1,
+        name: String::from("server"),
+        ports: vec![80, 443],
+    };
+    let tree = config.breakdown("config".into(), &mut std::collections::BTreeSet::new());
+
+    assert_eq!(
+        tree.to_json_string(),
+        include_str!("golden/breakdown.json").trim_end()
+    );
+}
+
+#[test]
+fn test_snapshot_diff() {
+    use loupe::{DeltaEntry, Snapshot};
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Metrics {
+        name: String,
+        samples: Vec<u64>,
+        labels: HashMap<u32, String>,
+    }
+
+    let mut metrics = Metrics {
+        name: String::from("requests"),
+        samples: Vec::new(),
+        labels: HashMap::new(),
+    };
+    metrics.labels.insert(1, String::from("a long label"));
+
+    let root = std::any::type_name::<Metrics>();
+    let path = |suffix: &str| format!("{}.{}", root, suffix);
+
+    let before = Snapshot::capture(&metrics);
+    assert_eq!(before.get(root), Some(size_of_val(&metrics)));
+    assert_eq!(before.get(&path("name")), Some(3 * POINTER_BYTE_SIZE + 8));
+    assert_eq!(before.diff(&before), []);
+
+    metrics.samples.extend(0..1000);
+    metrics.labels.insert(1, String::from("short"));
+
+    let after = Snapshot::capture(&metrics);
+
+    // The value isn't needed anymore to compare the snapshots.
+    drop(metrics);
+
+    let diff = after.diff(&before);
+
+    // The elements of `samples` grew the most, then `samples` and the
+    // root as much.
+    assert_eq!(
+        diff[0],
+        DeltaEntry {
+            path: path("samples.elements"),
+            old: Some(0),
+            new: Some(1000 * 8),
+            delta: 1000 * 8,
+        }
+    );
+    assert_eq!(diff[1].path, path("samples"));
+    assert_eq!(diff[1].delta, 1000 * 8);
+    assert_eq!(diff[2].path, root);
+    assert_eq!(diff[2].delta, 1000 * 8 - 7);
+
+    // The label shrank.
+    assert_eq!(
+        diff.iter()
+            .find(|entry| entry.path == path("labels.values"))
+            .map(|entry| entry.delta),
+        Some(-7)
+    );
+    assert!(diff.iter().all(|entry| !entry.path.contains("name")));
+}
+
+#[test]
+fn test_diff() {
+    use loupe::DeltaEntry;
+
+    #[allow(dead_code)]
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    enum State {
+        Idle,
+        Loading { url: String },
+        Ready(Vec<u8>),
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Page {
+        title: String,
+        links: Vec<u32>,
+        state: State,
+    }
+
+    let before = Page {
+        title: String::from("a long title"),
+        links: vec![1],
+        state: State::Loading {
+            url: String::from("https://example.com"),
+        },
+    };
+    let after = Page {
+        title: String::from("short"),
+        links: vec![1, 2, 3, 4, 5],
+        state: State::Ready(vec![0; 100]),
+    };
+
+    let root = std::any::type_name::<Page>();
+    let path = |suffix: &str| format!("{}.{}", root, suffix);
+    let diff = loupe::diff(&before, &after);
+    let entry = |suffix: &str| {
+        diff.iter()
+            .find(|entry| entry.path == path(suffix))
+            .cloned()
+            .unwrap_or_else(|| panic!("no `{}` in {:?}", suffix, diff))
+    };
+
+    // The new variant comes first, its field before the variant itself.
+    assert_eq!(
+        diff[0],
+        DeltaEntry {
+            path: path("state.Ready.0"),
+            old: None,
+            new: Some(3 * POINTER_BYTE_SIZE + 100),
+            delta: (3 * POINTER_BYTE_SIZE + 100) as isize,
+        }
+    );
+    assert_eq!(diff[1].path, path("state.Ready"));
+    assert_eq!(entry("state.Ready.0.elements").new, Some(100));
+
+    // The `Vec` grew.
+    assert_eq!(entry("links.elements").old, Some(4));
+    assert_eq!(entry("links.elements").delta, 4 * 4);
+
+    // The `String` shrank.
+    assert_eq!(entry("title").delta, 5 - 12);
+
+    // The enum changed variants.
+    assert_eq!(
+        entry("state.Loading.url").old,
+        Some(3 * POINTER_BYTE_SIZE + 19)
+    );
+    assert_eq!(entry("state.Loading.url").new, None);
+    assert_eq!(
+        entry("state.Loading").delta,
+        -((3 * POINTER_BYTE_SIZE + 19) as isize)
+    );
+
+    // The changes add up.
+    assert_eq!(
+        diff.iter().find(|entry| entry.path == root).unwrap().delta,
+        size_of_val(&after) as isize - size_of_val(&before) as isize
+    );
+    assert!(diff
+        .windows(2)
+        .all(|pair| pair[0].delta.abs() >= pair[1].delta.abs()));
+}
+
+#[test]
+#[should_panic(expected = "top growing parts:
+    breakdown::test_assert_no_growth::Cache.entries.elements: +80 bytes
+    breakdown::test_assert_no_growth::Cache.entries: +80 bytes
+    breakdown::test_assert_no_growth::Cache: +80 bytes")]
+fn test_assert_no_growth() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Cache {
+        name: String,
+        entries: Vec<u64>,
+    }
+
+    let mut cache = Cache {
+        name: String::from("cache"),
+        entries: Vec::new(),
+    };
+
+    loupe::assert_no_growth!(cache, cache.entries.extend(0..10));
+}
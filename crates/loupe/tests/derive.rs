@@ -6,6 +6,27 @@ macro_rules! assert_size_of_val_eq {
     };
 }
 
+// A facade re-exporting `loupe`, as a crate depending on `loupe`
+// through another crate would see it.
+mod facade {
+    pub use ::loupe;
+}
+
+mod renamed {
+    use ::loupe::MemoryUsage;
+
+    // Shadows the `loupe` crate, so that the generated code can't use
+    // it.
+    #[allow(dead_code)]
+    mod loupe {}
+
+    #[derive(MemoryUsage)]
+    #[loupe(crate = "crate::facade::loupe")]
+    pub struct Wrapper<T> {
+        pub inner: Vec<T>,
+    }
+}
+
 #[test]
 fn test_struct_flat() {
     #[derive(MemoryUsage)]
@@ -97,137 +118,1945 @@ fn test_struct_empty() {
 }
 
 #[test]
-fn test_struct_padding() {
-    // This struct is packed in order <x, z, y> because 'y: i32' requires 32-bit
-    // alignment but x and z do not. It starts with bytes 'x...yyyy' then adds 'z' in
-    // the first place it fits producing 'xz..yyyy' and not 12 bytes 'x...yyyyz...'.
+fn test_foreign_field_skipped() {
+    // Doesn't implement `MemoryUsage`.
+    struct Handle(#[allow(unused)] u64);
+
     #[derive(MemoryUsage)]
-    struct Padding {
-        x: i8,
-        y: i32,
-        z: i8,
+    #[allow(unused)]
+    struct Named {
+        name: String,
+        #[loupe(skip)]
+        handle: Handle,
     }
 
-    assert_size_of_val_eq!(8, Padding { x: 1, y: 2, z: 3 });
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    struct Unnamed(String, #[loupe(skip)] Handle);
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum E {
+        Named {
+            name: String,
+            #[loupe(skip)]
+            handle: Handle,
+        },
+        Unnamed(#[loupe(skip)] Handle, String),
+    }
+
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 8 + 3,
+        Named {
+            name: "abc".to_string(),
+            handle: Handle(1),
+        }
+    );
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 8 + 3,
+        Unnamed("abc".to_string(), Handle(1))
+    );
+
+    let e = E::Named {
+        name: "abc".to_string(),
+        handle: Handle(1),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&e) + 3, e);
+
+    let e = E::Unnamed(Handle(1), "abc".to_string());
+    assert_size_of_val_eq!(std::mem::size_of_val(&e) + 3, e);
 }
 
 #[test]
-fn test_enum() {
-    #[derive(MemoryUsage)]
-    struct Point {
-        x: i32,
-        y: i32,
+fn test_field_measured_with() {
+    use loupe::MemoryUsageTracker;
+
+    // Doesn't implement `MemoryUsage`.
+    struct Mapping {
+        length: usize,
+    }
+
+    fn size_of_mapping(mapping: &Mapping, _: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(mapping) + mapping.length
+    }
+
+    mod sizes {
+        use loupe::MemoryUsageTracker;
+
+        pub fn size_of_reserved(buffer: &Vec<u8>, _: &mut dyn MemoryUsageTracker) -> usize {
+            std::mem::size_of_val(buffer) + buffer.capacity()
+        }
     }
 
     #[derive(MemoryUsage)]
-    enum Things {
-        A,
-        B(),
-        C(i32),
-        D { x: i32 },
-        E(i32, i32),
-        F { x: i32, y: i32 },
-        Points(Vec<Point>),
+    struct File {
+        #[loupe(with = "size_of_mapping")]
+        mapping: Mapping,
+        #[loupe(with = "sizes::size_of_reserved")]
+        buffer: Vec<u8>,
     }
 
-    assert_size_of_val_eq!(24, Things::A);
-    assert_size_of_val_eq!(24, Things::B());
-    assert_size_of_val_eq!(24, Things::C(1));
-    assert_size_of_val_eq!(24, Things::D { x: 1 });
-    assert_size_of_val_eq!(24, Things::E(1, 2));
-    assert_size_of_val_eq!(24, Things::F { x: 1, y: 2 });
+    #[derive(MemoryUsage)]
+    enum Source {
+        Mapped(#[loupe(with = "size_of_mapping")] Mapping),
+        Buffered {
+            #[loupe(with = "sizes::size_of_reserved")]
+            buffer: Vec<u8>,
+        },
+    }
 
-    assert_size_of_val_eq!(8, Point { x: 1, y: 2 });
-    assert_size_of_val_eq!(40, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
     assert_size_of_val_eq!(
-        40,
-        Things::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+        POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 4096 + 64,
+        File {
+            mapping: Mapping { length: 4096 },
+            buffer: Vec::with_capacity(64),
+        }
     );
+
+    let source = Source::Mapped(Mapping { length: 4096 });
+    assert_size_of_val_eq!(std::mem::size_of_val(&source) + 4096, source);
+
+    let source = Source::Buffered {
+        buffer: Vec::with_capacity(64),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&source) + 64, source);
 }
 
 #[test]
-fn test_enum_with_generic() {
+fn test_field_estimate() {
+    // Doesn't implement `MemoryUsage`.
+    struct Artifact;
+
     #[derive(MemoryUsage)]
-    enum Generic<T>
-    where
-        T: MemoryUsage,
-    {
-        A(T),
-        B(T),
+    #[allow(unused)]
+    struct Compiled {
+        #[loupe(estimate = "1024")]
+        artifact: Artifact,
     }
 
-    assert_size_of_val_eq!(16, Generic::<i64>::A(1));
-    assert_size_of_val_eq!(16, Generic::<i64>::B(2));
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    struct GpuBuffer {
+        #[loupe(estimate = "self.byte_len")]
+        handle: u64,
+        byte_len: usize,
+    }
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Texture {
+        Gpu {
+            #[loupe(estimate = "*width * *height * 4")]
+            handle: u64,
+            width: usize,
+            height: usize,
+        },
+        Cpu(Vec<u8>),
+    }
+
+    assert_size_of_val_eq!(1024, Compiled { artifact: Artifact });
+
+    assert_size_of_val_eq!(
+        8 + POINTER_BYTE_SIZE + 4096,
+        GpuBuffer {
+            handle: 1,
+            byte_len: 4096,
+        }
+    );
+
+    let texture = Texture::Gpu {
+        handle: 1,
+        width: 16,
+        height: 8,
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&texture) + 16 * 8 * 4, texture);
+
+    let texture = Texture::Cpu(vec![0; 3]);
+    assert_size_of_val_eq!(std::mem::size_of_val(&texture) + 3, texture);
 }
 
 #[test]
-fn test_enum_with_inlined_generic() {
+fn test_field_shallow() {
+    use loupe::MemoryUsage as _;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    // Doesn't implement `MemoryUsage`.
+    struct Handle(#[allow(unused)] u64);
+
     #[derive(MemoryUsage)]
-    enum Generic<T: MemoryUsage> {
-        A(T),
-        B(T),
+    struct Parent {
+        name: String,
     }
 
-    assert_size_of_val_eq!(16, Generic::<i64>::A(1));
-    assert_size_of_val_eq!(16, Generic::<i64>::B(2));
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    struct Child {
+        #[loupe(shallow)]
+        parent: Arc<Parent>,
+        #[loupe(shallow)]
+        handle: Handle,
+        data: Vec<u8>,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Tree {
+        root: Arc<Parent>,
+        children: Vec<Child>,
+    }
+
+    let root = Arc::new(Parent {
+        name: String::from("root"),
+    });
+    let child = |length| Child {
+        parent: root.clone(),
+        handle: Handle(1),
+        data: vec![0; length],
+    };
+    let child_size = POINTER_BYTE_SIZE + 8 + 3 * POINTER_BYTE_SIZE;
+    let parent_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 4;
+
+    // The parent isn't charged to its children.
+    let tree = Tree {
+        root: root.clone(),
+        children: vec![child(1), child(2)],
+    };
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE + parent_size + 3 * POINTER_BYTE_SIZE + 2 * child_size + 1 + 2,
+        tree
+    );
+
+    // Once a child has been measured, the parent isn't counted when
+    // reached from another path.
+    let tracker = &mut BTreeSet::new();
+    assert_eq!(child(3).size_of_val(tracker), child_size + 3);
+    assert_eq!(root.size_of_val(tracker), POINTER_BYTE_SIZE);
 }
 
 #[test]
-fn test_enum_variant_ignored() {
+fn test_transparent() {
+    use std::marker::PhantomData;
+
     #[derive(MemoryUsage)]
-    struct Point {
-        x: i32,
-        y: i32,
+    #[loupe(transparent)]
+    struct Name(String);
+
+    #[derive(MemoryUsage)]
+    #[loupe(transparent)]
+    struct Bytes {
+        inner: Vec<u8>,
     }
 
     #[derive(MemoryUsage)]
-    enum E {
-        A,
-        Points(Vec<Point>),
+    #[loupe(transparent)]
+    struct Typed<T> {
+        _type: PhantomData<T>,
+        bytes: Bytes,
+        _nothing: (),
     }
 
-    assert_size_of_val_eq!(24, E::A);
+    let name = String::from("abc");
+    assert_eq!(size_of_val(&Name(name.clone())), size_of_val(&name));
+
+    let mut inner = Vec::with_capacity(8);
+    inner.extend_from_slice(&[1, 2, 3]);
+    assert_eq!(
+        size_of_val(&Bytes {
+            inner: inner.clone()
+        }),
+        size_of_val(&inner)
+    );
+
     assert_size_of_val_eq!(
-        40,
-        E::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+        3 * POINTER_BYTE_SIZE + 3,
+        Typed::<String> {
+            _type: PhantomData,
+            bytes: Bytes {
+                inner: vec![1, 2, 3]
+            },
+            _nothing: (),
+        }
     );
+}
+
+#[test]
+fn test_track_self() {
+    use loupe::MemoryUsage as _;
+    use std::collections::BTreeSet;
 
     #[derive(MemoryUsage)]
-    #[allow(unused)]
-    enum F {
-        A,
-        #[loupe(skip)]
-        Points(Vec<Point>),
+    #[loupe(track_self)]
+    struct ModuleInfo {
+        name: String,
     }
 
-    assert_size_of_val_eq!(24, F::A);
-    assert_size_of_val_eq!(
-        24,
-        F::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+    #[derive(MemoryUsage)]
+    #[repr(C)]
+    struct Owner {
+        id: u64,
+        info: ModuleInfo,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Borrower<'a> {
+        info: &'a ModuleInfo,
+    }
+
+    let owner = Owner {
+        id: 1,
+        info: ModuleInfo {
+            name: String::from("module"),
+        },
+    };
+    let borrower = Borrower { info: &owner.info };
+
+    // The heap of the info is charged to the first parent only. Its
+    // shallow size is still counted by the reference.
+    let tracker = &mut BTreeSet::new();
+    assert_eq!(owner.size_of_val(tracker), 8 + 3 * POINTER_BYTE_SIZE + 6);
+    assert_eq!(
+        borrower.size_of_val(tracker),
+        POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE
+    );
+
+    let tracker = &mut BTreeSet::new();
+    assert_eq!(
+        borrower.size_of_val(tracker),
+        POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 6
     );
+    assert_eq!(owner.size_of_val(tracker), 8 + 3 * POINTER_BYTE_SIZE);
 }
 
 #[test]
-fn test_ptr() {
+fn test_track_self_boxed() {
+    use loupe::MemoryUsage as _;
+
+    #[derive(MemoryUsage)]
+    #[loupe(track_self)]
+    struct ModuleInfo {
+        name: String,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Instance {
+        info: Box<ModuleInfo>,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Export<'a> {
+        info: &'a ModuleInfo,
+    }
+
+    let instance = Instance {
+        info: Box::new(ModuleInfo {
+            name: String::from("module"),
+        }),
+    };
+    let export = Export {
+        info: &instance.info,
+    };
+    let info_size = 3 * POINTER_BYTE_SIZE + 6;
+
+    // The boxed info is charged to the first parent only, in both
+    // orders.
+    let tracker = &mut loupe::DefaultTracker::new();
+    assert_eq!(instance.size_of_val(tracker), POINTER_BYTE_SIZE + info_size);
+    assert_eq!(export.size_of_val(tracker), POINTER_BYTE_SIZE);
+
+    let tracker = &mut loupe::DefaultTracker::new();
+    assert_eq!(export.size_of_val(tracker), POINTER_BYTE_SIZE + info_size);
+    assert_eq!(instance.size_of_val(tracker), POINTER_BYTE_SIZE);
+}
+
+#[test]
+fn test_track_self_nested() {
     #[derive(MemoryUsage)]
+    #[loupe(track_self)]
     #[repr(C)]
-    struct X(u8);
+    struct Inner {
+        tag: [u8; 8],
+        name: String,
+    }
 
     #[derive(MemoryUsage)]
-    #[repr(transparent)]
-    struct P(*const X);
+    #[loupe(track_self)]
+    struct Outer {
+        inner: Inner,
+    }
+
+    // The inner value has the address of the outer one.
+    let outer = Outer {
+        inner: Inner {
+            tag: [0; 8],
+            name: String::from("module_info"),
+        },
+    };
+    let outer_size = 8 + 3 * POINTER_BYTE_SIZE + 11;
+    assert_eq!(size_of_val(&outer), outer_size);
+
+    // Nor does a reference to the second byte of the value alias it.
+    let value = (&outer.inner.tag[1], &outer);
+    assert_eq!(size_of_val(&value), 2 * POINTER_BYTE_SIZE + 1 + outer_size);
+}
+
+#[test]
+fn test_track_self_adjacent() {
+    use loupe::MemoryUsage as _;
+    use std::collections::{BTreeSet, HashSet};
 
     #[derive(MemoryUsage)]
-    struct Q {
-        ptr: *const X,
+    #[loupe(track_self)]
+    struct Flag(u8);
+
+    // The key of the first flag isn't the address of the second one.
+    let flags = [Flag(1), Flag(2)];
+    let value = (&flags, &flags[1].0);
+    let size = 2 * POINTER_BYTE_SIZE + 2 + 1;
+
+    assert_eq!(value.size_of_val(&mut BTreeSet::new()), size);
+    assert_eq!(value.size_of_val(&mut HashSet::new()), size);
+    assert_eq!(size_of_val(&value), size);
+}
+
+#[test]
+fn test_cfg_fields() {
+    // Integration tests are always compiled with `cfg(test)`.
+    #[derive(MemoryUsage)]
+    struct Stats {
+        name: String,
+        #[cfg(test)]
+        enabled: Vec<u8>,
+        #[cfg(not(test))]
+        disabled: Vec<u8>,
+        #[cfg(feature = "enable-indexmap")]
+        counters: indexmap::IndexMap<String, u64>,
     }
 
-    let x = X(42);
-    let ptr = P(&x as *const _);
-    assert_size_of_val_eq!(POINTER_BYTE_SIZE, ptr);
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Event {
+        Named {
+            name: String,
+            #[cfg(test)]
+            enabled: Vec<u8>,
+            #[cfg(not(test))]
+            #[loupe(skip)]
+            disabled: Vec<u8>,
+        },
+        Unnamed(String, #[cfg(test)] Vec<u8>, #[cfg(not(test))] Vec<u8>),
+    }
 
-    let ptr = Q {
-        ptr: &x as *const _,
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Stats>() + 3 + 2,
+        Stats {
+            name: String::from("abc"),
+            enabled: vec![1, 2],
+            #[cfg(feature = "enable-indexmap")]
+            counters: indexmap::IndexMap::new(),
+        }
+    );
+
+    let event = Event::Named {
+        name: String::from("abc"),
+        enabled: vec![1, 2],
     };
-    assert_size_of_val_eq!(POINTER_BYTE_SIZE, ptr);
+    assert_size_of_val_eq!(std::mem::size_of_val(&event) + 3 + 2, event);
+
+    let event = Event::Unnamed(String::from("abc"), vec![1, 2]);
+    assert_size_of_val_eq!(std::mem::size_of_val(&event) + 3 + 2, event);
+}
+
+#[test]
+fn test_crate_path() {
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 3,
+        renamed::Wrapper {
+            inner: vec![1u8, 2, 3]
+        }
+    );
+}
+
+#[test]
+fn test_opaque() {
+    // Doesn't implement `MemoryUsage`.
+    struct Foreign(#[allow(unused)] u64);
+
+    #[derive(MemoryUsage)]
+    #[loupe(opaque)]
+    #[allow(unused)]
+    struct Dense<T> {
+        values: [f64; 16],
+        foreign: Foreign,
+        heap: Vec<T>,
+    }
+
+    #[derive(MemoryUsage)]
+    #[loupe(opaque)]
+    #[allow(unused)]
+    enum Either {
+        Left(Foreign),
+        Right(String),
+    }
+
+    assert_size_of_val_eq!(
+        16 * 8 + 8 + 3 * POINTER_BYTE_SIZE,
+        Dense {
+            values: [0.; 16],
+            foreign: Foreign(1),
+            heap: vec![Foreign(2)],
+        }
+    );
+
+    let either = Either::Right(String::from("abc"));
+    assert_size_of_val_eq!(std::mem::size_of_val(&either), either);
+}
+
+// Types from a crate that doesn't know about `loupe`.
+mod third_party {
+    pub struct Config {
+        pub name: String,
+        pub values: Vec<f64>,
+    }
+
+    pub struct Pair<T> {
+        pub left: T,
+        pub right: T,
+    }
+
+    pub enum Source {
+        Inline(String),
+        File { path: String, offset: u64 },
+        Stdin,
+    }
+
+    #[non_exhaustive]
+    #[allow(dead_code)]
+    pub enum Level {
+        Low,
+        High(Vec<u8>),
+    }
+}
+
+#[allow(dead_code)]
+mod ext {
+    pub struct Point {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    pub struct Buffer {
+        pub data: *const u8,
+        pub length: usize,
+        pub name: String,
+    }
+
+    pub struct Pair(pub String, pub Vec<u8>);
+}
+
+fn ext_buffer_bytes(buffer: &*const u8, _: &mut dyn loupe::MemoryUsageTracker) -> usize {
+    std::mem::size_of_val(buffer) + 64
+}
+
+loupe::impl_memory_usage!(ext::Point { x, y });
+loupe::impl_memory_usage!(ext::Buffer {
+    data: with = "ext_buffer_bytes",
+    name,
+});
+loupe::impl_memory_usage!(ext::Pair { 0, 1: skip });
+
+#[test]
+fn test_impl_memory_usage() {
+    assert_size_of_val_eq!(8, ext::Point { x: 1, y: 2 });
+
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE + 8 + 3 * POINTER_BYTE_SIZE + 64 + 3,
+        ext::Buffer {
+            data: std::ptr::null(),
+            length: 64,
+            name: String::from("abc"),
+        }
+    );
+
+    assert_size_of_val_eq!(
+        2 * 3 * POINTER_BYTE_SIZE + 3,
+        ext::Pair(String::from("abc"), vec![1, 2, 3])
+    );
+}
+
+#[test]
+fn test_remote() {
+    #[derive(MemoryUsage)]
+    #[loupe(remote = "third_party::Config")]
+    #[allow(dead_code)]
+    struct ConfigDef {
+        name: String,
+        values: Vec<f64>,
+    }
+
+    #[derive(MemoryUsage)]
+    #[loupe(remote = "third_party::Pair")]
+    #[allow(dead_code)]
+    struct PairDef<T> {
+        left: T,
+        right: T,
+    }
+
+    #[derive(MemoryUsage)]
+    #[loupe(remote = "third_party::Source")]
+    #[allow(dead_code)]
+    enum SourceDef {
+        Inline(String),
+        File { path: String, offset: u64 },
+        Stdin,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Settings {
+        #[loupe(with = "ConfigDef::size_of_val")]
+        config: third_party::Config,
+
+        #[loupe(with = "PairDef::size_of_val")]
+        bounds: third_party::Pair<Vec<u8>>,
+
+        #[loupe(with = "SourceDef::size_of_val")]
+        source: third_party::Source,
+    }
+
+    let config = third_party::Config {
+        name: String::from("abc"),
+        values: vec![1., 2.],
+    };
+    assert_eq!(
+        ConfigDef::size_of_val(&config, &mut std::collections::BTreeSet::new()),
+        2 * 3 * POINTER_BYTE_SIZE + 3 + 2 * 8
+    );
+
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Settings>() + 3 + 2 * 8 + 4 + 5 + 6,
+        Settings {
+            config,
+            bounds: third_party::Pair {
+                left: vec![0; 4],
+                right: vec![0; 5],
+            },
+            source: third_party::Source::File {
+                path: String::from("abcdef"),
+                offset: 0,
+            },
+        }
+    );
+
+    let sources = [
+        third_party::Source::Inline(String::from("ab")),
+        third_party::Source::Stdin,
+    ];
+
+    for (source, heap) in sources.iter().zip([2, 0]) {
+        assert_eq!(
+            SourceDef::size_of_val(source, &mut std::collections::BTreeSet::new()),
+            std::mem::size_of::<third_party::Source>() + heap
+        );
+    }
+}
+
+#[test]
+fn test_struct_padding() {
+    // This struct is packed in order <x, z, y> because 'y: i32' requires 32-bit
+    // alignment but x and z do not. It starts with bytes 'x...yyyy' then adds 'z' in
+    // the first place it fits producing 'xz..yyyy' and not 12 bytes 'x...yyyyz...'.
+    #[derive(MemoryUsage)]
+    struct Padding {
+        x: i8,
+        y: i32,
+        z: i8,
+    }
+
+    assert_size_of_val_eq!(8, Padding { x: 1, y: 2, z: 3 });
+}
+
+#[test]
+fn test_enum() {
+    #[derive(MemoryUsage)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(MemoryUsage)]
+    enum Things {
+        A,
+        B(),
+        C(i32),
+        D { x: i32 },
+        E(i32, i32),
+        F { x: i32, y: i32 },
+        Points(Vec<Point>),
+    }
+
+    assert_size_of_val_eq!(24, Things::A);
+    assert_size_of_val_eq!(24, Things::B());
+    assert_size_of_val_eq!(24, Things::C(1));
+    assert_size_of_val_eq!(24, Things::D { x: 1 });
+    assert_size_of_val_eq!(24, Things::E(1, 2));
+    assert_size_of_val_eq!(24, Things::F { x: 1, y: 2 });
+
+    assert_size_of_val_eq!(8, Point { x: 1, y: 2 });
+    assert_size_of_val_eq!(40, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+    assert_size_of_val_eq!(
+        40,
+        Things::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+    );
+}
+
+#[test]
+fn test_enum_with_generic() {
+    #[derive(MemoryUsage)]
+    enum Generic<T>
+    where
+        T: MemoryUsage,
+    {
+        A(T),
+        B(T),
+    }
+
+    assert_size_of_val_eq!(16, Generic::<i64>::A(1));
+    assert_size_of_val_eq!(16, Generic::<i64>::B(2));
+}
+
+#[test]
+fn test_enum_with_inlined_generic() {
+    #[derive(MemoryUsage)]
+    enum Generic<T: MemoryUsage> {
+        A(T),
+        B(T),
+    }
+
+    assert_size_of_val_eq!(16, Generic::<i64>::A(1));
+    assert_size_of_val_eq!(16, Generic::<i64>::B(2));
+}
+
+#[test]
+fn test_inferred_bounds() {
+    use std::marker::PhantomData;
+
+    // Doesn't implement `MemoryUsage`.
+    struct Unit;
+
+    #[derive(MemoryUsage)]
+    struct Wrapper<T> {
+        inner: Vec<T>,
+    }
+
+    #[derive(MemoryUsage)]
+    enum Either<L, R> {
+        Left(L),
+        Right { right: Box<R> },
+    }
+
+    #[derive(MemoryUsage)]
+    struct Tagged<T, U> {
+        value: T,
+        _tag: PhantomData<U>,
+    }
+
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 2 * 3,
+        Wrapper {
+            inner: vec![1u16, 2, 3]
+        }
+    );
+
+    let either: Either<u8, Vec<u8>> = Either::Left(1);
+    assert_size_of_val_eq!(std::mem::size_of_val(&either), either);
+
+    let either: Either<u8, Vec<u8>> = Either::Right {
+        right: Box::new(vec![1, 2, 3]),
+    };
+    assert_size_of_val_eq!(
+        std::mem::size_of_val(&either) + 3 * POINTER_BYTE_SIZE + 3,
+        either
+    );
+
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 3,
+        Tagged::<_, Unit> {
+            value: String::from("abc"),
+            _tag: PhantomData,
+        }
+    );
+}
+
+#[test]
+fn test_inferred_bounds_on_associated_types() {
+    trait Storage {
+        type Buffer;
+    }
+
+    // Doesn't implement `MemoryUsage`.
+    struct Heap;
+
+    impl Storage for Heap {
+        type Buffer = Vec<u8>;
+    }
+
+    #[derive(MemoryUsage)]
+    struct Stored<S: Storage> {
+        buffer: S::Buffer,
+    }
+
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 3,
+        Stored::<Heap> {
+            buffer: vec![1, 2, 3]
+        }
+    );
+
+    trait Graph {
+        type EdgeId;
+        type Weight;
+    }
+
+    // Doesn't implement `MemoryUsage`.
+    struct Network;
+
+    impl Graph for Network {
+        type EdgeId = u32;
+        type Weight = String;
+    }
+
+    #[derive(MemoryUsage)]
+    struct Node<T: Graph> {
+        edges: Vec<T::EdgeId>,
+        weight: T::Weight,
+    }
+
+    #[derive(MemoryUsage)]
+    enum Change<T: Graph> {
+        Added(<T as Graph>::EdgeId),
+        Weighted { weight: Box<<T as Graph>::Weight> },
+    }
+
+    assert_size_of_val_eq!(
+        2 * 3 * POINTER_BYTE_SIZE + 2 * 4 + 5,
+        Node::<Network> {
+            edges: vec![1, 2],
+            weight: String::from("heavy"),
+        }
+    );
+
+    let change = Change::<Network>::Weighted {
+        weight: Box::new(String::from("light")),
+    };
+    assert_size_of_val_eq!(
+        std::mem::size_of_val(&change) + 3 * POINTER_BYTE_SIZE + 5,
+        change
+    );
+
+    let change = Change::<Network>::Added(1);
+    assert_size_of_val_eq!(std::mem::size_of_val(&change), change);
+}
+
+#[test]
+fn test_phantom_data() {
+    use std::marker::PhantomData;
+
+    // Doesn't implement `MemoryUsage`.
+    struct NonMemoryUsageType;
+
+    #[derive(MemoryUsage)]
+    struct Id<T> {
+        raw: u64,
+        _marker: PhantomData<T>,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Handle<T, U: ?Sized> {
+        raw: u64,
+        _covariant: PhantomData<fn() -> T>,
+        _unsized: std::marker::PhantomData<U>,
+    }
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Slot<T> {
+        Free,
+        Taken(Option<PhantomData<(T, T)>>),
+    }
+
+    assert_size_of_val_eq!(
+        8,
+        Id::<NonMemoryUsageType> {
+            raw: 1,
+            _marker: PhantomData,
+        }
+    );
+
+    assert_size_of_val_eq!(
+        8,
+        Handle::<NonMemoryUsageType, str> {
+            raw: 1,
+            _covariant: PhantomData,
+            _unsized: PhantomData,
+        }
+    );
+
+    let slot = Slot::<NonMemoryUsageType>::Taken(Some(PhantomData));
+    assert_size_of_val_eq!(std::mem::size_of_val(&slot), slot);
+}
+
+#[test]
+fn test_skip_bound() {
+    use std::rc::{Rc, Weak};
+
+    // Doesn't implement `MemoryUsage`.
+    struct NonMemoryUsageType;
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    struct Skipped<T> {
+        #[loupe(skip)]
+        value: T,
+        length: usize,
+    }
+
+    // `Weak<T>` implements `MemoryUsage` for any `T`.
+    #[derive(MemoryUsage)]
+    #[loupe(skip_bound(P))]
+    struct Child<P, T> {
+        parent: Weak<P>,
+        value: T,
+    }
+
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Skipped<NonMemoryUsageType>>(),
+        Skipped {
+            value: NonMemoryUsageType,
+            length: 1,
+        }
+    );
+
+    let parent = Rc::new(NonMemoryUsageType);
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 3,
+        Child {
+            parent: Rc::downgrade(&parent),
+            value: String::from("abc"),
+        }
+    );
+}
+
+#[test]
+fn test_const_generics() {
+    #[derive(MemoryUsage)]
+    struct Buffer<const N: usize> {
+        data: [u8; N],
+        len: usize,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Chunks<T, const N: usize>
+    where
+        [T; N]: Default,
+    {
+        chunks: Vec<[T; N]>,
+    }
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Samples<const N: usize> {
+        Empty,
+        Frame([f32; N]),
+        Boxed(Box<[u8; N]>),
+    }
+
+    assert_size_of_val_eq!(
+        16 + POINTER_BYTE_SIZE,
+        Buffer::<16> {
+            data: [0; 16],
+            len: 0,
+        }
+    );
+
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 2 * 2 * 3 * POINTER_BYTE_SIZE + 3 + 4,
+        Chunks::<String, 2> {
+            chunks: vec![
+                [String::from("abc"), String::new()],
+                [String::new(), String::from("defg")],
+            ],
+        }
+    );
+
+    let frame = Samples::<4>::Frame([0.; 4]);
+    assert_size_of_val_eq!(std::mem::size_of_val(&frame), frame);
+
+    let boxed = Samples::<4>::Boxed(Box::new([0; 4]));
+    assert_size_of_val_eq!(std::mem::size_of_val(&boxed) + 4, boxed);
+}
+
+#[test]
+fn test_enum_repr_and_discriminants() {
+    #[derive(MemoryUsage)]
+    #[repr(u8)]
+    #[allow(unused)]
+    enum Message {
+        Ping,
+        Byte(u8),
+        Text(String) = 7,
+        Pair { left: u16, right: Box<u64> } = 200,
+    }
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Code {
+        Ok = 0,
+        NotFound = 404,
+        Internal = 500,
+    }
+
+    #[derive(MemoryUsage)]
+    #[repr(i64)]
+    #[allow(unused)]
+    enum Wide {
+        Small = -1,
+        Large = 1 << 40,
+    }
+
+    #[derive(MemoryUsage)]
+    #[non_exhaustive]
+    #[allow(unused)]
+    enum Event {
+        Started,
+        Data(Vec<u8>),
+    }
+
+    // Has no values, only needs to compile.
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Never {}
+
+    let message = Message::Ping;
+    assert_size_of_val_eq!(std::mem::size_of_val(&message), message);
+
+    let message = Message::Byte(1);
+    assert_size_of_val_eq!(std::mem::size_of_val(&message), message);
+
+    let message = Message::Text(String::from("abc"));
+    assert_size_of_val_eq!(std::mem::size_of_val(&message) + 3, message);
+
+    let message = Message::Pair {
+        left: 1,
+        right: Box::new(2),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&message) + 8, message);
+
+    assert_size_of_val_eq!(2, Code::NotFound);
+    assert_size_of_val_eq!(8, Wide::Large);
+
+    assert_size_of_val_eq!(std::mem::size_of::<Event>() + 3, Event::Data(vec![1, 2, 3]));
+}
+
+#[test]
+fn test_remote_non_exhaustive() {
+    #[derive(MemoryUsage)]
+    #[loupe(remote = "third_party::Level")]
+    #[non_exhaustive]
+    #[allow(dead_code)]
+    enum LevelDef {
+        Low,
+        High(Vec<u8>),
+    }
+
+    let level = third_party::Level::High(vec![1, 2, 3]);
+    assert_eq!(
+        LevelDef::size_of_val(&level, &mut std::collections::BTreeSet::new()),
+        std::mem::size_of_val(&level) + 3
+    );
+}
+
+#[test]
+fn test_enum_variant_ignored() {
+    #[derive(MemoryUsage)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(MemoryUsage)]
+    enum E {
+        A,
+        Points(Vec<Point>),
+    }
+
+    assert_size_of_val_eq!(24, E::A);
+    assert_size_of_val_eq!(
+        40,
+        E::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+    );
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum F {
+        A,
+        #[loupe(skip)]
+        Points(Vec<Point>),
+    }
+
+    assert_size_of_val_eq!(24, F::A);
+    assert_size_of_val_eq!(
+        24,
+        F::Points(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }])
+    );
+}
+
+#[test]
+fn test_packed_struct() {
+    #[derive(MemoryUsage)]
+    #[repr(C, packed)]
+    struct Header {
+        tag: u8,
+        length: u32,
+        flags: u8,
+    }
+
+    #[derive(MemoryUsage)]
+    #[repr(C, packed(2))]
+    struct Pair(u8, u32);
+
+    #[derive(MemoryUsage)]
+    #[repr(C, packed)]
+    #[allow(unused)]
+    struct Message {
+        tag: u8,
+        #[loupe(skip)]
+        payload: Vec<u8>,
+    }
+
+    assert_size_of_val_eq!(
+        6,
+        Header {
+            tag: 1,
+            length: 2,
+            flags: 3
+        }
+    );
+    assert_size_of_val_eq!(6, Pair(1, 2));
+    assert_size_of_val_eq!(
+        1 + 3 * POINTER_BYTE_SIZE,
+        Message {
+            tag: 1,
+            payload: vec![1, 2, 3],
+        }
+    );
+}
+
+#[test]
+fn test_union() {
+    #[derive(MemoryUsage)]
+    #[repr(C)]
+    union Word {
+        integer: u64,
+        bytes: [u8; 8],
+        halves: (u32, u32),
+        pointer: *const u8,
+    }
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    union Tagged {
+        integer: u64,
+        #[loupe(skip)]
+        string: std::mem::ManuallyDrop<String>,
+    }
+
+    assert_size_of_val_eq!(8, Word { integer: 1 });
+    assert_size_of_val_eq!(8, Word { bytes: [1; 8] });
+    assert_size_of_val_eq!(3 * POINTER_BYTE_SIZE, Tagged { integer: 1 });
+}
+
+#[test]
+fn test_union_with() {
+    use loupe::MemoryUsageTracker;
+
+    const KEY: u32 = 0;
+    const TEXT: u32 = 1;
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct KeyEvent {
+        kind: u32,
+        code: u32,
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct TextEvent {
+        kind: u32,
+        length: usize,
+        text: *const u8,
+    }
+
+    #[derive(MemoryUsage)]
+    #[loupe(union_with = "self.size_of_active")]
+    #[repr(C)]
+    union Event {
+        kind: u32,
+        key: KeyEvent,
+        text: TextEvent,
+    }
+
+    impl Event {
+        fn size_of_active(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+            // SAFETY: all the variants start with `kind`.
+            match unsafe { self.kind } {
+                TEXT => unsafe { self.text.length },
+                _ => 0,
+            }
+        }
+    }
+
+    fn size_of_wrapper(wrapper: &Wrapper, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        unsafe { wrapper.event.size_of_active(tracker) }
+    }
+
+    #[derive(MemoryUsage)]
+    #[loupe(union_with = "size_of_wrapper")]
+    union Wrapper {
+        event: std::mem::ManuallyDrop<Event>,
+    }
+
+    let key = Event {
+        key: KeyEvent { kind: KEY, code: 7 },
+    };
+    let text = Event {
+        text: TextEvent {
+            kind: TEXT,
+            length: 5,
+            text: b"hello".as_ptr(),
+        },
+    };
+
+    assert_size_of_val_eq!(3 * POINTER_BYTE_SIZE, key);
+    assert_size_of_val_eq!(3 * POINTER_BYTE_SIZE + 5, text);
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 5,
+        Wrapper {
+            event: std::mem::ManuallyDrop::new(text)
+        }
+    );
+}
+
+#[test]
+fn test_enum_variant_skipped() {
+    // Doesn't implement `MemoryUsage`.
+    struct ExternalHandle(#[allow(unused)] Vec<u8>);
+
+    #[derive(MemoryUsage)]
+    #[allow(unused)]
+    enum Backend {
+        Memory(Vec<u8>),
+        Disk {
+            path: String,
+        },
+        #[loupe(skip)]
+        Custom(ExternalHandle),
+        #[loupe(skip)]
+        Remote {
+            handle: ExternalHandle,
+            url: String,
+        },
+    }
+
+    let backend = Backend::Memory(vec![1, 2, 3]);
+    assert_size_of_val_eq!(std::mem::size_of_val(&backend) + 3, backend);
+
+    let backend = Backend::Disk {
+        path: String::from("/tmp"),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&backend) + 4, backend);
+
+    let backend = Backend::Custom(ExternalHandle(vec![1, 2, 3]));
+    assert_size_of_val_eq!(std::mem::size_of_val(&backend), backend);
+
+    let backend = Backend::Remote {
+        handle: ExternalHandle(vec![1, 2, 3]),
+        url: String::from("https://example.org"),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&backend), backend);
+}
+
+#[test]
+fn test_ptr() {
+    #[derive(MemoryUsage)]
+    #[repr(C)]
+    struct X(u8);
+
+    #[derive(MemoryUsage)]
+    #[repr(transparent)]
+    struct P(*const X);
+
+    #[derive(MemoryUsage)]
+    struct Q {
+        ptr: *const X,
+    }
+
+    let x = X(42);
+    let ptr = P(&x as *const _);
+    assert_size_of_val_eq!(POINTER_BYTE_SIZE, ptr);
+
+    let ptr = Q {
+        ptr: &x as *const _,
+    };
+    assert_size_of_val_eq!(POINTER_BYTE_SIZE, ptr);
+}
+
+#[test]
+fn test_rc() {
+    use std::rc::Rc;
+
+    #[derive(MemoryUsage)]
+    struct Shared {
+        a: Rc<Vec<u8>>,
+        b: Rc<Vec<u8>>,
+    }
+
+    let rc = Rc::new(vec![1, 2, 3]);
+
+    assert_size_of_val_eq!(
+        2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + 24 + 3,
+        Shared {
+            a: rc.clone(),
+            b: rc
+        }
+    );
+}
+
+#[test]
+fn test_rc_weak_cycle() {
+    use std::cell::RefCell;
+    use std::rc::{Rc, Weak};
+
+    #[derive(MemoryUsage)]
+    struct Node {
+        parent: RefCell<Weak<Node>>,
+        children: RefCell<Vec<Rc<Node>>>,
+    }
+
+    let parent = Rc::new(Node {
+        parent: RefCell::new(Weak::new()),
+        children: RefCell::new(vec![]),
+    });
+    let child = Rc::new(Node {
+        parent: RefCell::new(Rc::downgrade(&parent)),
+        children: RefCell::new(vec![]),
+    });
+    parent.children.borrow_mut().push(child);
+
+    let node_size = std::mem::size_of::<Node>();
+
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE
+            + 2 * POINTER_BYTE_SIZE /* counters */
+            + node_size
+            + POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + node_size, /* child */
+        parent
+    );
+}
+
+#[test]
+fn test_refcell() {
+    use std::cell::RefCell;
+
+    #[derive(MemoryUsage)]
+    struct Cache {
+        entries: RefCell<Vec<u8>>,
+    }
+
+    let cache = Cache {
+        entries: RefCell::new(vec![1, 2, 3]),
+    };
+    let cache_size = std::mem::size_of::<Cache>();
+
+    assert_size_of_val_eq!(cache_size + 3, cache);
+
+    let _borrowed = cache.entries.borrow_mut();
+    assert_size_of_val_eq!(cache_size, cache);
+}
+
+#[test]
+fn test_pinned_field() {
+    use std::pin::Pin;
+
+    #[derive(MemoryUsage)]
+    struct Pinned {
+        value: Pin<Box<(u8, u8, u8)>>,
+    }
+
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE + 3,
+        Pinned {
+            value: Box::pin((1, 2, 3))
+        }
+    );
+}
+
+#[test]
+fn test_wrapping_field() {
+    use std::num::Wrapping;
+
+    #[derive(MemoryUsage)]
+    struct Counter {
+        hits: Wrapping<u64>,
+        name: String,
+    }
+
+    assert_size_of_val_eq!(
+        8 + 24 + 3,
+        Counter {
+            hits: Wrapping(1),
+            name: "abc".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_atomic_fields() {
+    use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize};
+
+    #[derive(MemoryUsage)]
+    struct Stats {
+        requests: AtomicU64,
+        active: AtomicUsize,
+        closed: AtomicBool,
+        last: AtomicPtr<u8>,
+    }
+
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Stats>(),
+        Stats {
+            requests: AtomicU64::new(1),
+            active: AtomicUsize::new(2),
+            closed: AtomicBool::new(false),
+            last: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    );
+}
+
+#[test]
+fn test_socket_addr_field() {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    #[derive(MemoryUsage)]
+    struct Connections {
+        peers: HashMap<SocketAddr, String>,
+    }
+
+    let mut peers = HashMap::new();
+    peers.insert(SocketAddr::from(([127, 0, 0, 1], 80)), "abc".to_string());
+
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Connections>() + std::mem::size_of::<SocketAddr>() + 24 + 3,
+        Connections { peers }
+    );
+}
+
+#[test]
+fn test_marker_fields() {
+    use std::convert::Infallible;
+    use std::marker::PhantomPinned;
+
+    #[derive(MemoryUsage)]
+    struct Unmovable {
+        data: Vec<u8>,
+        _pinned: PhantomPinned,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Parsed {
+        result: Result<Vec<u8>, Infallible>,
+    }
+
+    assert_size_of_val_eq!(
+        24 + 3,
+        Unmovable {
+            data: vec![1, 2, 3],
+            _pinned: PhantomPinned,
+        }
+    );
+    let result: Result<Vec<u8>, Infallible> = Ok(vec![1, 2, 3]);
+    assert_size_of_val_eq!(size_of_val(&result), Parsed { result });
+}
+
+#[test]
+fn test_shared_slices() {
+    use std::sync::Arc;
+
+    #[derive(MemoryUsage)]
+    struct Config {
+        raw: Arc<[u8]>,
+        copy: Arc<[u8]>,
+    }
+
+    let raw: Arc<[u8]> = Arc::from(vec![1, 2, 3]);
+
+    assert_size_of_val_eq!(
+        2 * 2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 3,
+        Config {
+            raw: raw.clone(),
+            copy: raw,
+        }
+    );
+}
+
+#[rustversion::since(1.94)]
+#[test]
+fn test_static_lazylock() {
+    use std::sync::LazyLock;
+
+    static TABLE: LazyLock<Vec<u8>> = LazyLock::new(|| vec![1, 2, 3]);
+
+    #[derive(MemoryUsage)]
+    struct Lookup {
+        table: &'static LazyLock<Vec<u8>>,
+    }
+
+    let lazy_size = std::mem::size_of_val(&TABLE);
+
+    assert_size_of_val_eq!(POINTER_BYTE_SIZE + lazy_size, Lookup { table: &TABLE });
+
+    LazyLock::force(&TABLE);
+    assert_size_of_val_eq!(POINTER_BYTE_SIZE + lazy_size + 3, Lookup { table: &TABLE });
+}
+
+#[test]
+fn test_channel_fields() {
+    use std::sync::mpsc::{self, Receiver, Sender};
+
+    #[derive(MemoryUsage)]
+    struct Worker {
+        jobs: Receiver<String>,
+        results: Sender<String>,
+    }
+
+    let (_, jobs) = mpsc::channel();
+    let (results, _) = mpsc::channel();
+
+    let worker = Worker { jobs, results };
+    assert_size_of_val_eq!(std::mem::size_of_val(&worker), worker);
+}
+
+#[test]
+fn test_callback_fields() {
+    use loupe::MemoryUsageTracker;
+
+    type OnMessage = Box<dyn Fn(&str) + Send>;
+
+    // Closures taking references don't implement `MemoryUsage`.
+    fn size_of_callback(callback: &OnMessage, _: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(callback) + std::mem::size_of_val(&**callback)
+    }
+
+    #[derive(MemoryUsage)]
+    struct Callbacks {
+        transform: fn(u32) -> u32,
+        on_close: Option<fn()>,
+        #[loupe(with = "size_of_callback")]
+        on_message: OnMessage,
+    }
+
+    let prefix = [0u8; 64];
+
+    assert_size_of_val_eq!(
+        POINTER_BYTE_SIZE + POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE + 64,
+        Callbacks {
+            transform: |x| x * 2,
+            on_close: None,
+            on_message: Box::new(move |message| {
+                let _ = (prefix, message);
+            }),
+        }
+    );
+}
+
+#[test]
+fn test_future_state() {
+    use std::task::{Poll, Waker};
+
+    #[derive(MemoryUsage)]
+    struct State {
+        output: Poll<Vec<u8>>,
+        waker: Option<Waker>,
+    }
+
+    let state = State {
+        output: Poll::Ready(vec![1, 2, 3]),
+        waker: Some(Waker::noop().clone()),
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&state) + 3, state);
+
+    let state = State {
+        output: Poll::Pending,
+        waker: None,
+    };
+    assert_size_of_val_eq!(std::mem::size_of_val(&state), state);
+}
+
+#[test]
+fn test_boxed_error_field() {
+    use std::error::Error;
+
+    #[derive(MemoryUsage)]
+    struct Failure {
+        attempts: u32,
+        cause: Box<dyn Error + Send + Sync>,
+    }
+
+    // `fmt::Error` is zero-sized.
+    let cause = std::fmt::Error;
+
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Failure>(),
+        Failure {
+            attempts: 3,
+            cause: Box::new(cause),
+        }
+    );
+}
+
+#[test]
+fn test_trait_object_field() {
+    trait Backend: MemoryUsage {
+        fn name(&self) -> &str;
+    }
+
+    #[derive(MemoryUsage)]
+    struct Memory {
+        data: Vec<u8>,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Disk {
+        path: String,
+        block_size: u64,
+    }
+
+    impl Backend for Memory {
+        fn name(&self) -> &str {
+            "memory"
+        }
+    }
+
+    impl Backend for Disk {
+        fn name(&self) -> &str {
+            &self.path
+        }
+    }
+
+    #[derive(MemoryUsage)]
+    struct Plugin {
+        id: u32,
+        backend: Box<dyn Backend>,
+    }
+
+    let plugin = Plugin {
+        id: 1,
+        backend: Box::new(Memory {
+            data: vec![1, 2, 3, 4],
+        }),
+    };
+    assert_eq!(plugin.backend.name(), "memory");
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Plugin>() + 3 * POINTER_BYTE_SIZE + 4,
+        plugin
+    );
+
+    let plugin = Plugin {
+        id: 2,
+        backend: Box::new(Disk {
+            path: String::from("/tmp/x"),
+            block_size: 4096,
+        }),
+    };
+    assert_eq!(plugin.backend.name(), "/tmp/x");
+    assert_size_of_val_eq!(
+        std::mem::size_of::<Plugin>() + 3 * POINTER_BYTE_SIZE + 8 + 6,
+        plugin
+    );
+}
+
+#[test]
+fn test_wrapper_fields() {
+    use loupe::{Ignored, Opaque};
+    use std::collections::HashMap;
+
+    // Not `MemoryUsage`, as a type of another crate.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Foreign {
+        names: Vec<String>,
+    }
+
+    #[derive(MemoryUsage, Clone, Debug)]
+    struct Index {
+        entries: Vec<u32>,
+        foreign: Opaque<Foreign>,
+        lookup: Ignored<HashMap<u32, String>>,
+    }
+
+    let mut lookup = HashMap::new();
+    lookup.insert(1, String::from("one"));
+
+    let mut index = Index {
+        entries: vec![1, 2],
+        foreign: Opaque::from(Foreign {
+            names: vec![String::from("abc")],
+        }),
+        lookup: Ignored::from(lookup),
+    };
+
+    assert_size_of_val_eq!(std::mem::size_of::<Index>() + 2 * 4, index);
+
+    // The wrappers are transparent.
+    assert_eq!(index.foreign.names.len(), 1);
+    index.lookup.insert(2, String::from("two"));
+    assert_eq!(index.lookup.len(), 2);
+    assert_eq!(
+        std::mem::size_of::<Opaque<Foreign>>(),
+        std::mem::size_of::<Foreign>()
+    );
+    assert_eq!(
+        format!("{:?}", index.clone().foreign),
+        "Opaque(Foreign { names: [\"abc\"] })"
+    );
+    assert_eq!(
+        index.foreign.into_inner(),
+        Foreign {
+            names: vec![String::from("abc")]
+        }
+    );
+
+    // Alone, `Opaque` is its shallow size, and `Ignored` nothing.
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE,
+        Opaque::from(vec![String::from("abc")])
+    );
+    assert_size_of_val_eq!(0, Ignored::from(vec![String::from("abc")]));
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE,
+        vec![Opaque::from(Box::new(1u8)), Opaque::from(Box::new(2u8))]
+    );
+    // The buffer of the `Vec` is still counted.
+    assert_size_of_val_eq!(
+        3 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE,
+        vec![Ignored::from(Box::new(1u8)), Ignored::from(Box::new(2u8))]
+    );
+}
+
+#[test]
+fn test_memory_usage_ext() {
+    use loupe::MemoryUsageExt;
+
+    #[derive(MemoryUsage)]
+    struct Session {
+        user: String,
+        history: Vec<u64>,
+    }
+
+    let session = Session {
+        user: String::from("root"),
+        history: vec![1, 2, 3],
+    };
+
+    assert_eq!(
+        session.memory_usage(),
+        std::mem::size_of::<Session>() + 4 + 3 * 8
+    );
+    assert_eq!(
+        session.memory_usage_with(&mut std::collections::BTreeSet::new()),
+        session.memory_usage()
+    );
+}
+
+#[test]
+fn test_shallow_size() {
+    use loupe::{shallow_size_of, MemoryUsageSized};
+    use std::mem;
+
+    #[derive(MemoryUsage)]
+    struct Point {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Entry<T> {
+        key: u8,
+        value: T,
+        tags: Vec<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(MemoryUsage)]
+    enum Shape {
+        Dot(Point),
+        Polygon(Vec<Point>),
+    }
+
+    assert_eq!(Point::SHALLOW_SIZE, mem::size_of::<Point>());
+    assert_eq!(Entry::<u64>::SHALLOW_SIZE, mem::size_of::<Entry<u64>>());
+    assert_eq!(Shape::SHALLOW_SIZE, mem::size_of::<Shape>());
+    assert_eq!(
+        shallow_size_of::<Entry<Point>>(),
+        mem::size_of::<Entry<Point>>()
+    );
+
+    // The buffer of a `Vec` is its capacity times the shallow size of
+    // its elements.
+    let mut points = Vec::with_capacity(16);
+    points.push(Point { x: 0., y: 1. });
+    let shape = Shape::Polygon(points);
+
+    let capacity = match &shape {
+        Shape::Polygon(points) => points.capacity(),
+        Shape::Dot(_) => unreachable!(),
+    };
+    let estimate = Shape::SHALLOW_SIZE + capacity * Point::SHALLOW_SIZE;
+
+    assert!(size_of_val(&shape) <= estimate);
+    assert_eq!(
+        size_of_val(&shape),
+        Shape::SHALLOW_SIZE + Point::SHALLOW_SIZE
+    );
+}
+
+#[derive(MemoryUsage, Default)]
+struct Service {
+    sessions: std::collections::HashMap<u32, String>,
+    log: Vec<String>,
+}
+
+impl Service {
+    fn handle(&mut self, id: u32) {
+        self.sessions.insert(id, format!("session {}", id));
+        self.log.push(format!("request {}", id));
+
+        self.sessions.remove(&id);
+    }
+
+    fn handle_and_truncate_log(&mut self, id: u32) {
+        self.handle(id);
+        self.log.clear();
+    }
+}
+
+#[test]
+fn test_assert_no_growth() {
+    let mut service = Service::default();
+
+    // Warm up, so that the buffers don't grow anymore.
+    service.handle_and_truncate_log(0);
+
+    loupe::assert_no_growth!(service, {
+        for id in 0..1000 {
+            service.handle_and_truncate_log(id);
+        }
+    });
+}
+
+// An example of failure: the log grows with each request.
+#[test]
+#[ignore]
+fn test_assert_no_growth_leak() {
+    let mut service = Service::default();
+
+    loupe::assert_no_growth!(
+        service,
+        {
+            for id in 0..1000 {
+                service.handle(id);
+            }
+        },
+        1024
+    );
+}
+
+#[test]
+fn test_labels() {
+    use loupe::LabelTracker;
+    use std::collections::BTreeSet;
+    use std::mem::size_of;
+    use std::sync::Arc;
+
+    #[derive(MemoryUsage)]
+    struct Function {
+        #[loupe(label = "code")]
+        body: Vec<u8>,
+        // Nested in the `code` label of `Module::functions`.
+        #[loupe(label = "metadata")]
+        name: String,
+    }
+
+    #[derive(MemoryUsage)]
+    struct Module {
+        #[loupe(label = "code")]
+        functions: Vec<Function>,
+        #[loupe(label = "metadata")]
+        exports: Vec<(String, u32)>,
+        #[loupe(label = "metadata")]
+        source: Arc<String>,
+        id: u64,
+    }
+
+    #[derive(MemoryUsage)]
+    enum Instance {
+        Running {
+            module: Box<Module>,
+            globals: Vec<u64>,
+        },
+    }
+
+    let source = Arc::new(String::from("(module)"));
+    let module = Module {
+        functions: vec![
+            Function {
+                body: vec![0; 100],
+                name: String::from("main"),
+            },
+            Function {
+                body: vec![0; 20],
+                name: String::from("helper"),
+            },
+        ],
+        exports: vec![(String::from("main"), 0)],
+        source: Arc::clone(&source),
+        id: 1,
+    };
+    let instance = Instance::Running {
+        module: Box::new(module),
+        globals: vec![0; 4],
+    };
+
+    let mut tracker = LabelTracker::new(BTreeSet::new());
+    let size = tracker.measure(&instance);
+    let report = tracker.report();
+
+    assert_eq!(size, size_of_val(&instance));
+    assert_eq!(report.total(), size);
+
+    let code = 2 * size_of::<Function>() + 100 + 20;
+    let source_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 8;
+    let metadata = 4 + 6 + size_of::<(String, u32)>() + 4 + source_size;
+    let unlabeled = size_of::<Instance>() + size_of::<Module>() + 4 * 8;
+
+    assert_eq!(report.labels["code"], code);
+    assert_eq!(report.labels["metadata"], metadata);
+    assert_eq!(report.unlabeled, unlabeled);
+
+    // The shared source isn't counted again by another measurement
+    // with the same tracker.
+    tracker.measure(&source);
+    let report = tracker.report();
+
+    assert_eq!(report.labels["metadata"], metadata);
+    assert_eq!(report.unlabeled, unlabeled + POINTER_BYTE_SIZE);
 }
@@ -0,0 +1,98 @@
+#![cfg(feature = "metrics")]
+
+use loupe::{size_of_val, MemoryUsage, MemoryUsageBreakdown, POINTER_BYTE_SIZE};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use std::collections::BTreeMap;
+
+/// Run `export`, and returns the gauges it set, by labels.
+fn collect_gauges<F: FnOnce()>(export: F) -> BTreeMap<Vec<(String, String)>, f64> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, export);
+
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, unit, _, value)| {
+            let (_, key) = key.into_parts();
+
+            assert_eq!(key.name(), loupe::metrics::GAUGE);
+            assert_eq!(unit, Some(metrics::Unit::Bytes));
+
+            let labels = key
+                .labels()
+                .map(|label| (label.key().to_string(), label.value().to_string()))
+                .collect();
+            let value = match value {
+                DebugValue::Gauge(value) => value.into_inner(),
+                value => panic!("not a gauge: {:?}", value),
+            };
+
+            (labels, value)
+        })
+        .collect()
+}
+
+fn labels(labels: &[(&str, &str)]) -> Vec<(String, String)> {
+    labels
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_export_breakdown() {
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Cache {
+        entries: Vec<u32>,
+    }
+
+    #[derive(MemoryUsage, MemoryUsageBreakdown)]
+    struct Server {
+        name: String,
+        cache: Box<Cache>,
+    }
+
+    let server = Server {
+        name: String::from("main"),
+        cache: Box::new(Cache {
+            entries: vec![1, 2, 3],
+        }),
+    };
+    let tree = server.breakdown("server".into(), &mut loupe::DefaultTracker::new());
+
+    let gauges = collect_gauges(|| loupe::metrics::export_breakdown(&tree, 1));
+    let cache_size = POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE + 3 * 4;
+
+    assert_eq!(
+        gauges,
+        vec![
+            (labels(&[("root", "server")]), size_of_val(&server) as f64),
+            (
+                labels(&[("root", "server"), ("field", "name")]),
+                (3 * POINTER_BYTE_SIZE + 4) as f64
+            ),
+            (
+                labels(&[("root", "server"), ("field", "cache")]),
+                cache_size as f64
+            ),
+        ]
+        .into_iter()
+        .collect()
+    );
+
+    // Deeper parts, down to the fields of `Cache`.
+    let gauges = collect_gauges(|| loupe::metrics::export_breakdown(&tree, 3));
+
+    assert_eq!(gauges.len(), 5);
+    assert_eq!(
+        gauges[&labels(&[("root", "server"), ("field", "cache.boxed.entries")])],
+        (3 * POINTER_BYTE_SIZE + 3 * 4) as f64
+    );
+    assert_eq!(
+        gauges[&labels(&[("root", "server"), ("field", "cache.boxed")])],
+        (3 * POINTER_BYTE_SIZE + 3 * 4) as f64
+    );
+}
@@ -0,0 +1,140 @@
+//! Pointer-heavy measurements, meant to be run with `cargo miri
+//! test` so that every cast done while tracking addresses is checked
+//! against the strict provenance rules.
+
+use loupe::{MemoryUsage, MemoryUsageTracker, POINTER_BYTE_SIZE};
+use std::cell::{RefCell, UnsafeCell};
+use std::collections::{BTreeSet, HashSet};
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+fn measure<T>(value: &T, tracker: &mut dyn MemoryUsageTracker) -> usize
+where
+    T: MemoryUsage,
+{
+    MemoryUsage::size_of_val(value, tracker)
+}
+
+macro_rules! with_trackers {
+    (| $tracker:ident | $body:block) => {{
+        let $tracker: &mut dyn MemoryUsageTracker = &mut BTreeSet::new();
+        $body
+    }
+
+    {
+        let $tracker: &mut dyn MemoryUsageTracker = &mut HashSet::new();
+        $body
+    }};
+}
+
+#[test]
+fn test_references() {
+    with_trackers!(|tracker| {
+        let x = 42i32;
+        let y = &x;
+
+        assert_eq!(measure(&(y, y), tracker), 2 * POINTER_BYTE_SIZE + 4);
+    });
+
+    with_trackers!(|tracker| {
+        let mut x = 42i32;
+        let y = &mut x;
+
+        assert_eq!(measure(&y, tracker), POINTER_BYTE_SIZE + 4);
+        assert_eq!(measure(&y, tracker), POINTER_BYTE_SIZE);
+    });
+}
+
+#[test]
+fn test_raw_pointers() {
+    with_trackers!(|tracker| {
+        let mut x = 42i32;
+        let const_ptr: *const i32 = &x;
+        let mut_ptr: *mut i32 = &mut x;
+        let non_null = NonNull::new(mut_ptr).unwrap();
+
+        assert_eq!(measure(&const_ptr, tracker), POINTER_BYTE_SIZE);
+        assert_eq!(measure(&mut_ptr, tracker), POINTER_BYTE_SIZE);
+        assert_eq!(measure(&non_null, tracker), POINTER_BYTE_SIZE);
+    });
+
+    with_trackers!(|tracker| {
+        let dangling = NonNull::<u64>::dangling();
+
+        assert_eq!(measure(&dangling, tracker), POINTER_BYTE_SIZE);
+    });
+}
+
+#[test]
+fn test_slices() {
+    with_trackers!(|tracker| {
+        let array = [1u16, 2, 3, 4];
+        let slice: &[u16] = &array[..];
+
+        assert_eq!(measure(&slice, tracker), 2 * POINTER_BYTE_SIZE + 2 * 4);
+        assert_eq!(measure(&slice, tracker), 2 * POINTER_BYTE_SIZE);
+    });
+
+    with_trackers!(|tracker| {
+        let empty: &[u16] = &[];
+
+        assert_eq!(measure(&empty, tracker), 2 * POINTER_BYTE_SIZE);
+    });
+
+    with_trackers!(|tracker| {
+        let boxed: Box<[u8]> = vec![1, 2, 3].into_boxed_slice();
+
+        assert_eq!(measure(&boxed, tracker), 2 * POINTER_BYTE_SIZE + 3);
+    });
+}
+
+#[test]
+fn test_cells_and_shared_pointers() {
+    with_trackers!(|tracker| {
+        let cell = UnsafeCell::new(1u8);
+
+        assert_eq!(
+            measure(&cell, tracker),
+            std::mem::size_of_val(&cell) + POINTER_BYTE_SIZE
+        );
+    });
+
+    with_trackers!(|tracker| {
+        let cell = RefCell::new(vec![1u8, 2, 3]);
+
+        assert_eq!(measure(&cell, tracker), std::mem::size_of_val(&cell) + 3);
+    });
+
+    with_trackers!(|tracker| {
+        let arc = Arc::new(7u32);
+        let clones = (Arc::clone(&arc), arc);
+
+        assert_eq!(
+            measure(&clones, tracker),
+            2 * POINTER_BYTE_SIZE + 2 * POINTER_BYTE_SIZE /* counters */ + 4
+        );
+    });
+}
+
+#[test]
+fn test_packed_struct() {
+    #[derive(MemoryUsage)]
+    #[repr(C, packed)]
+    struct Packed {
+        tag: u8,
+        value: u32,
+        pointer: *const u8,
+    }
+
+    with_trackers!(|tracker| {
+        let byte = 7u8;
+        // `value` and `pointer` are unaligned.
+        let packed = Packed {
+            tag: 1,
+            value: 2,
+            pointer: &byte,
+        };
+
+        assert_eq!(measure(&packed, tracker), 1 + 4 + POINTER_BYTE_SIZE);
+    });
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "registry")]
+
+use loupe::{registry, size_of_val, MemoryUsage, POINTER_BYTE_SIZE};
+use std::sync::{Arc, Mutex};
+
+// The registry is global: a single test uses it, so that tests running
+// in parallel don't see each other's values.
+#[test]
+fn test_registry() {
+    #[derive(MemoryUsage)]
+    struct ModuleCache {
+        modules: Mutex<Vec<Arc<Vec<u8>>>>,
+    }
+
+    let shared = Arc::new(vec![0u8; 1024]);
+    let module_cache = Arc::new(ModuleCache {
+        modules: Mutex::new(vec![Arc::clone(&shared), Arc::new(vec![1u8; 10])]),
+    });
+    let session_table = Arc::new(vec![shared]);
+    let temporary = Arc::new(String::from("temporary"));
+
+    registry::register("module_cache", Arc::downgrade(&module_cache));
+    registry::register(String::from("temporary"), Arc::downgrade(&temporary));
+    registry::register("session_table", Arc::downgrade(&session_table));
+
+    let snapshot = registry::snapshot();
+    assert_eq!(
+        snapshot
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        ["module_cache", "temporary", "session_table"]
+    );
+
+    drop(temporary);
+
+    // The shared `Vec` is counted in the size of the module cache only.
+    let arc_size = 2 * POINTER_BYTE_SIZE + 3 * POINTER_BYTE_SIZE;
+    let snapshot = registry::snapshot();
+    assert_eq!(
+        snapshot,
+        [
+            (String::from("module_cache"), size_of_val(&*module_cache)),
+            (
+                String::from("session_table"),
+                3 * POINTER_BYTE_SIZE + POINTER_BYTE_SIZE
+            ),
+        ]
+    );
+    assert_eq!(
+        snapshot[1].1 + arc_size + 1024,
+        size_of_val(&*session_table)
+    );
+
+    // A value registered twice is counted once.
+    registry::register("module_cache_again", Arc::downgrade(&module_cache));
+    assert_eq!(
+        registry::snapshot().last(),
+        Some(&(String::from("module_cache_again"), 0))
+    );
+}
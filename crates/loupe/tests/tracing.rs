@@ -0,0 +1,128 @@
+#![cfg(feature = "tracing")]
+
+use loupe::{size_of_val, DefaultTracker, MemoryUsage, TracingTracker, POINTER_BYTE_SIZE};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The fields of an event or of a span, formatted.
+#[derive(Default)]
+struct Fields(BTreeMap<String, String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// The name and the fields of the spans, by identifier.
+type Spans = BTreeMap<u64, (String, BTreeMap<String, String>)>;
+
+/// A layer collecting the events and the spans.
+#[derive(Clone, Default)]
+struct Collector {
+    events: Arc<Mutex<Vec<BTreeMap<String, String>>>>,
+    spans: Arc<Mutex<Spans>>,
+}
+
+impl<S> Layer<S> for Collector
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, _: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+
+        self.spans.lock().unwrap().insert(
+            id.into_u64(),
+            (attributes.metadata().name().to_string(), fields.0),
+        );
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _: Context<'_, S>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+
+        if let Some((_, span)) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            span.extend(fields.0);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        self.events.lock().unwrap().push(fields.0);
+    }
+}
+
+fn collect<F: FnOnce()>(measure: F) -> Collector {
+    let collector = Collector::default();
+    let subscriber = tracing_subscriber::registry().with(collector.clone());
+
+    tracing::subscriber::with_default(subscriber, measure);
+
+    collector
+}
+
+#[test]
+fn test_size_of_val_span() {
+    let value = vec![0u8; 100];
+    let collector = collect(|| assert_eq!(size_of_val(&value), 3 * POINTER_BYTE_SIZE + 100));
+
+    let spans = collector.spans.lock().unwrap();
+    let (name, fields) = spans.values().next().unwrap();
+
+    assert_eq!(spans.len(), 1);
+    assert_eq!(name, "loupe::size_of_val");
+    assert_eq!(fields["type_name"], "alloc::vec::Vec<u8>");
+    assert_eq!(fields["bytes"], (3 * POINTER_BYTE_SIZE + 100).to_string());
+}
+
+#[test]
+fn test_large_parts() {
+    #[derive(MemoryUsage)]
+    struct Module {
+        name: String,
+        code: Box<[u8]>,
+        exports: Vec<u32>,
+    }
+
+    let module = Module {
+        name: String::from("main"),
+        code: vec![0; 1 << 16].into_boxed_slice(),
+        exports: vec![1, 2, 3],
+    };
+
+    let collector = collect(|| {
+        let mut tracker = TracingTracker::new(DefaultTracker::new(), 1 << 12);
+
+        assert_eq!(
+            loupe::size_of_val_with(&module, &mut tracker),
+            size_of_val(&module)
+        );
+    });
+
+    let events = collector.events.lock().unwrap();
+
+    // Only the oversized field is reported: the allocation of the
+    // `Box`, and the slice behind it.
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert_eq!(events[0]["message"], "loupe: large allocation");
+    assert_eq!(events[0]["type_name"], "alloc::boxed::Box<[u8]>");
+    assert_eq!(events[0]["bytes"], (1 << 16).to_string());
+    assert_eq!(events[1]["message"], "loupe: large subtree");
+    assert_eq!(events[1]["type_name"], "[u8]");
+    assert_eq!(events[1]["bytes"], (1 << 16).to_string());
+}